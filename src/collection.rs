@@ -3,15 +3,19 @@
 //! The collections may have a rather minimal interface, as the emphasis is put on demonstrating the flexibility of the
 //! `Store` trait, rather than providing fully implemented collections -- for now.
 
+mod arena;
 mod concurrent_vec;
 mod linked_list;
 mod skip_list;
 mod store_box;
 mod store_vec;
 
+#[cfg(test)]
+mod model;
 #[cfg(test)]
 mod utils;
 
+pub use arena::Arena;
 pub use concurrent_vec::ConcurrentVec;
 pub use linked_list::LinkedList;
 pub use skip_list::SkipList;
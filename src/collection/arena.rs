@@ -0,0 +1,518 @@
+//! A typed arena, handing out plain references.
+//!
+//! Since the store is pinning, the values allocated never move until the arena is dropped, and thus the arena can hand
+//! out plain references bound to its own lifetime rather than handles.
+
+use core::{
+    alloc::{AllocError, Layout},
+    cell::Cell,
+    fmt,
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+    slice,
+};
+
+use crate::{
+    alloc,
+    extension::typed::TypedHandle,
+    interface::{Store, StorePinning},
+};
+
+/// A typed arena, allocating values of type `T` in `S`, and handing out plain references.
+///
+/// All the values allocated by the arena are dropped, in reverse order of allocation, when the arena is dropped.
+///
+/// The arena is invariant in `T`, as values are allocated through a shared reference: were it covariant, an
+/// `&Arena<&'static str, _>` could be shrunk to an `&Arena<&'a str, _>`, then hold a value borrowing a shorter-lived
+/// local, only for the destructor of a `T` to read it after it is gone.
+///
+/// ```compile_fail
+/// #![feature(allocator_api)]
+///
+/// use std::alloc::Global;
+///
+/// use storage::collection::Arena;
+///
+/// fn shrink<'a>(arena: &'a Arena<&'static str, Global>) -> &'a Arena<&'a str, Global> {
+///     arena
+/// }
+/// ```
+pub struct Arena<T, S: Store + StorePinning> {
+    //  Last allocated node, if any, linked to the previously allocated ones.
+    last: Cell<Option<S::Handle>>,
+    store: S,
+    //  Invariant in `T`, as values are allocated through a shared reference.
+    _marker: PhantomData<Cell<T>>,
+}
+
+impl<T, S: Store + StorePinning> Arena<T, S> {
+    /// Creates a new, empty, instance.
+    pub fn new() -> Self
+    where
+        S: Default,
+    {
+        Self::new_in(S::default())
+    }
+
+    /// Creates a new, empty, instance with the given store.
+    pub const fn new_in(store: S) -> Self {
+        let last = Cell::new(None);
+
+        Self {
+            last,
+            store,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the underlying store.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Allocates `value` in the arena, returning a reference to it.
+    ///
+    /// Calls `handle_alloc_error` if the allocation fails.
+    #[track_caller]
+    pub fn alloc(&self, value: T) -> &T {
+        let Ok(value) = self.try_alloc(value) else {
+            alloc::handle_alloc_error(Self::node_layout(1).unwrap_or(Layout::new::<T>()))
+        };
+
+        value
+    }
+
+    /// Attempts to allocate `value` in the arena, returning a reference to it.
+    ///
+    /// If the allocation fails, `value` is dropped, and `AllocError` is returned.
+    #[track_caller]
+    pub fn try_alloc(&self, value: T) -> Result<&T, AllocError> {
+        let header = NodeHeader {
+            previous: self.last.get(),
+            length: 1,
+            capacity: 1,
+        };
+
+        let node = TypedHandle::try_new(Node { header, value }, &self.store)?;
+
+        self.last.set(Some(node.to_raw_parts().0));
+
+        //  Safety:
+        //  -   `node` was allocated by `self.store`, and is valid until `self` is dropped.
+        //  -   `node` points to a live `Node`.
+        //  -   No mutable reference to the `Node` is ever created, until `self` is dropped.
+        //  -   The reference will not be invalidated until `self` is dropped, as `self.store` is pinning.
+        let node = unsafe { node.resolve(&self.store) };
+
+        Ok(&node.value)
+    }
+
+    /// Allocates a copy of `values` in the arena, returning a reference to it.
+    ///
+    /// Calls `handle_alloc_error` if the allocation fails.
+    #[track_caller]
+    pub fn alloc_slice_copy(&self, values: &[T]) -> &[T]
+    where
+        T: Copy,
+    {
+        let Ok(slice) = self.try_alloc_slice_copy(values) else {
+            alloc::handle_alloc_error(Self::node_layout(values.len()).unwrap_or(Layout::new::<T>()))
+        };
+
+        slice
+    }
+
+    /// Attempts to allocate a copy of `values` in the arena, returning a reference to it.
+    #[track_caller]
+    pub fn try_alloc_slice_copy(&self, values: &[T]) -> Result<&[T], AllocError>
+    where
+        T: Copy,
+    {
+        let (handle, _) = self.allocate_node(values.len())?;
+
+        //  Safety:
+        //  -   `handle` was just allocated by `self.store`, with a capacity of `values.len()`.
+        let elements = unsafe { self.elements(handle) };
+
+        //  Safety:
+        //  -   `values` is valid for reads of `values.len()` elements.
+        //  -   `elements` is valid for writes of `values.len()` elements, as per capacity.
+        //  -   `values` and `elements` are both suitably aligned.
+        //  -   `values` and `elements` do not overlap, as `elements` was just allocated.
+        unsafe { ptr::copy_nonoverlapping(values.as_ptr(), elements.as_ptr(), values.len()) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `self.store`, with a capacity of `values.len()`.
+        //  -   The first `values.len()` elements of `handle` are initialized.
+        Ok(unsafe { self.link(handle, values.len(), values.len()) })
+    }
+
+    /// Allocates all the values of `iterator` in the arena, contiguously, returning a reference to them.
+    ///
+    /// Calls `handle_alloc_error` if the allocation fails.
+    #[track_caller]
+    pub fn alloc_from_iter<I>(&self, iterator: I) -> &[T]
+    where
+        I: IntoIterator<Item = T>,
+    {
+        match self.allocate_from_iter(iterator) {
+            Ok(slice) => slice,
+            Err(layout) => alloc::handle_alloc_error(layout),
+        }
+    }
+
+    /// Attempts to allocate all the values of `iterator` in the arena, contiguously, returning a reference to them.
+    ///
+    /// If the allocation fails, the values already taken from `iterator` are dropped, and `AllocError` is returned.
+    ///
+    /// If `iterator` panics, the values already taken from it are leaked.
+    #[track_caller]
+    pub fn try_alloc_from_iter<I>(&self, iterator: I) -> Result<&[T], AllocError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.allocate_from_iter(iterator).map_err(|_| AllocError)
+    }
+}
+
+impl<T, S: Store + StorePinning + Default> Default for Arena<T, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//  Safety:
+//  -   `T` may dangle, as the values are only dropped, not otherwise accessed; `_marker` owning `T` ensures that
+//      dropck still checks the destructors of the values.
+unsafe impl<#[may_dangle] T, S: Store + StorePinning> Drop for Arena<T, S> {
+    fn drop(&mut self) {
+        while let Some(handle) = self.last.take() {
+            //  Safety:
+            //  -   `handle` was allocated by `self.store`, and is still valid.
+            //  -   `handle` points to a live `NodeHeader`.
+            let header = unsafe { ptr::read(self.store.resolve(handle).cast::<NodeHeader<S::Handle>>().as_ptr()) };
+
+            self.last.set(header.previous);
+
+            //  Safety:
+            //  -   `handle` was allocated by `self.store`, with a capacity of `header.capacity`.
+            //  -   The first `header.length` elements of `handle` are initialized.
+            unsafe { self.release(handle, header.length, header.capacity) };
+        }
+    }
+}
+
+impl<T, S: Store + StorePinning> fmt::Debug for Arena<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "Arena")
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Header of each node, followed by `capacity` elements, the first `length` of which are initialized.
+struct NodeHeader<H> {
+    previous: Option<H>,
+    length: usize,
+    capacity: usize,
+}
+
+//  Node of a single element, matching the layout of a node with a capacity of 1.
+#[repr(C)]
+struct Node<H, T> {
+    header: NodeHeader<H>,
+    value: T,
+}
+
+impl<T, S: Store + StorePinning> Arena<T, S> {
+    //  Returns the layout of a node, and the offset of its elements.
+    fn node_layout_with_offset(capacity: usize) -> Result<(Layout, usize), AllocError> {
+        let header = Layout::new::<NodeHeader<S::Handle>>();
+        let elements = Layout::array::<T>(capacity).map_err(|_| AllocError)?;
+
+        let (layout, offset) = header.extend(elements).map_err(|_| AllocError)?;
+
+        Ok((layout.pad_to_align(), offset))
+    }
+
+    //  Returns the layout of a node.
+    fn node_layout(capacity: usize) -> Result<Layout, AllocError> {
+        Self::node_layout_with_offset(capacity).map(|(layout, _)| layout)
+    }
+
+    //  Allocates a node, with a capacity of `capacity` elements, none of which are initialized.
+    #[track_caller]
+    fn allocate_node(&self, capacity: usize) -> Result<(S::Handle, Layout), AllocError> {
+        let layout = Self::node_layout(capacity)?;
+
+        let (handle, _) = self.store.allocate(layout)?;
+
+        Ok((handle, layout))
+    }
+
+    //  Returns the layout of a node, as reported to `handle_alloc_error`, or that of `T` if the capacity overflows.
+    fn requested_layout(capacity: Option<usize>) -> Layout {
+        capacity
+            .and_then(|capacity| Self::node_layout(capacity).ok())
+            .unwrap_or(Layout::new::<T>())
+    }
+
+    //  Allocates all the values of `iterator` in a single node, contiguously, and links it.
+    //
+    //  On failure, returns the layout of the node which could not be allocated.
+    #[track_caller]
+    fn allocate_from_iter<I>(&self, iterator: I) -> Result<&[T], Layout>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let iterator = iterator.into_iter();
+
+        let mut capacity = iterator.size_hint().0;
+        let (mut handle, mut layout) = self
+            .allocate_node(capacity)
+            .map_err(|_| Self::requested_layout(Some(capacity)))?;
+        let mut length = 0;
+
+        //  `iterator` may allocate in `self` in turn, which is fine as the node is only linked at the very end.
+        for value in iterator {
+            if length == capacity {
+                //  Safety:
+                //  -   `handle` was allocated by `self.store`, with `layout` and a capacity of `capacity`.
+                let grown = unsafe { self.grow_node(handle, layout, capacity) };
+
+                let Ok(grown) = grown else {
+                    //  Safety:
+                    //  -   `handle` was allocated by `self.store`, with a capacity of `capacity`.
+                    //  -   The first `length` elements of `handle` are initialized.
+                    unsafe { self.release(handle, length, capacity) };
+
+                    return Err(Self::requested_layout(Self::grown_capacity(capacity)));
+                };
+
+                (handle, layout, capacity) = grown;
+            }
+
+            //  Safety:
+            //  -   `handle` was allocated by `self.store`, with a capacity of `capacity`.
+            let elements = unsafe { self.elements(handle) };
+
+            //  Safety:
+            //  -   `length < capacity`, hence the slot is in bounds.
+            unsafe { ptr::write(elements.as_ptr().add(length), value) };
+
+            length += 1;
+        }
+
+        //  Safety:
+        //  -   `handle` was allocated by `self.store`, with a capacity of `capacity`.
+        //  -   The first `length` elements of `handle` are initialized.
+        Ok(unsafe { self.link(handle, length, capacity) })
+    }
+
+    //  Returns the capacity of a node after growth, or `None` on overflow.
+    fn grown_capacity(capacity: usize) -> Option<usize> {
+        Some(capacity.checked_mul(2)?.max(4))
+    }
+
+    //  Grows a node, doubling its capacity, returning the new handle, layout, and capacity.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must have been allocated by `self.store`, with `layout` and a capacity of `capacity`, and still be
+    //      valid.
+    #[track_caller]
+    unsafe fn grow_node(
+        &self,
+        handle: S::Handle,
+        layout: Layout,
+        capacity: usize,
+    ) -> Result<(S::Handle, Layout, usize), AllocError> {
+        let new_capacity = Self::grown_capacity(capacity).ok_or(AllocError)?;
+        let new_layout = Self::node_layout(new_capacity)?;
+
+        //  Safety:
+        //  -   `handle` was allocated by `self.store`, with `layout`, and is still valid, as per pre-conditions.
+        //  -   `new_layout.size()` is greater than `layout.size()`, as `new_capacity > capacity`.
+        let (new_handle, _) = unsafe { self.store.grow(handle, layout, new_layout)? };
+
+        Ok((new_handle, new_layout, new_capacity))
+    }
+
+    //  Returns a pointer to the first element of the node.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must have been allocated by `self.store`, and still be valid.
+    unsafe fn elements(&self, handle: S::Handle) -> NonNull<T> {
+        //  Safety:
+        //  -   The layout was computed successfully when allocating `handle`.
+        let (_, offset) = unsafe { Self::node_layout_with_offset(0).unwrap_unchecked() };
+
+        //  Safety:
+        //  -   `handle` was allocated by `self.store`, and is still valid, as per pre-conditions.
+        let pointer = unsafe { self.store.resolve(handle) };
+
+        //  Safety:
+        //  -   `offset` is within the bounds of the node, as the offset is independent from the capacity.
+        unsafe { NonNull::new_unchecked(pointer.as_ptr().add(offset)).cast() }
+    }
+
+    //  Links the node, making it the last node, and returns a slice over its elements.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must have been allocated by `self.store`, with a capacity of `capacity`, and still be valid.
+    //  -   The first `length` elements of `handle` must be initialized.
+    unsafe fn link(&self, handle: S::Handle, length: usize, capacity: usize) -> &[T] {
+        let header = NodeHeader {
+            previous: self.last.get(),
+            length,
+            capacity,
+        };
+
+        //  Safety:
+        //  -   `handle` was allocated by `self.store`, and is still valid, as per pre-conditions.
+        let pointer = unsafe { self.store.resolve(handle) };
+
+        //  Safety:
+        //  -   `pointer` is valid for writes, and suitably aligned, for a `NodeHeader`.
+        unsafe { ptr::write(pointer.cast().as_ptr(), header) };
+
+        self.last.set(Some(handle));
+
+        //  Safety:
+        //  -   `handle` was allocated by `self.store`, and is still valid, as per pre-conditions.
+        let elements = unsafe { self.elements(handle) };
+
+        //  Safety:
+        //  -   The first `length` elements are initialized, as per pre-conditions.
+        //  -   No mutable reference to the elements is ever created, until `self` is dropped.
+        //  -   The slice will not be invalidated until `self` is dropped, as `self.store` is pinning.
+        unsafe { slice::from_raw_parts(elements.as_ptr(), length) }
+    }
+
+    //  Drops the elements of the node, and deallocates it.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must have been allocated by `self.store`, with a capacity of `capacity`, and still be valid.
+    //  -   The first `length` elements of `handle` must be initialized, and will not be used afterwards.
+    unsafe fn release(&self, handle: S::Handle, length: usize, capacity: usize) {
+        //  Safety:
+        //  -   `handle` was allocated by `self.store`, and is still valid, as per pre-conditions.
+        let elements = unsafe { self.elements(handle) };
+
+        if mem::needs_drop::<T>() {
+            let elements = ptr::slice_from_raw_parts_mut(elements.as_ptr(), length);
+
+            //  Safety:
+            //  -   The first `length` elements are initialized, and not used afterwards, as per pre-conditions.
+            unsafe { ptr::drop_in_place(elements) };
+        }
+
+        //  Safety:
+        //  -   The layout was computed successfully when allocating `handle`.
+        let layout = unsafe { Self::node_layout(capacity).unwrap_unchecked() };
+
+        //  Safety:
+        //  -   `handle` was allocated by `self.store`, with `layout`, and is still valid, as per pre-conditions.
+        unsafe { self.store.deallocate(handle, layout) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{alloc::Global, cell::RefCell, rc::Rc};
+
+    use crate::store::ArenaStore;
+
+    use super::*;
+
+    type TestArena<T> = Arena<T, ArenaStore<Global>>;
+
+    struct Tracker(Rc<RefCell<Vec<u32>>>, u32);
+
+    impl Drop for Tracker {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    struct GraphNode<'a> {
+        value: u32,
+        edges: Vec<&'a GraphNode<'a>>,
+    }
+
+    #[test]
+    fn graph() {
+        let arena = TestArena::new();
+
+        let leaf = arena.alloc(GraphNode {
+            value: 1,
+            edges: Vec::new(),
+        });
+        let middle = arena.alloc(GraphNode {
+            value: 2,
+            edges: vec![leaf],
+        });
+        let root = arena.alloc(GraphNode {
+            value: 3,
+            edges: vec![middle, leaf],
+        });
+
+        let sum: u32 = root.edges.iter().map(|node| node.value).sum();
+
+        assert_eq!(3, sum);
+        assert_eq!(1, root.edges[0].edges[0].value);
+    }
+
+    #[test]
+    fn alloc_slice_copy() {
+        let arena = Arena::<u32, Global>::new();
+
+        let empty = arena.alloc_slice_copy(&[]);
+        let values = arena.alloc_slice_copy(&[1, 2, 3]);
+        let one = arena.alloc(4);
+
+        assert!(empty.is_empty());
+        assert_eq!(&[1, 2, 3], values);
+        assert_eq!(4, *one);
+    }
+
+    #[test]
+    fn alloc_from_iter() {
+        let arena = TestArena::new();
+
+        //  Unknown length, forcing growth.
+        let odds = arena.alloc_from_iter((0..100).filter(|i| i % 2 == 1));
+
+        assert_eq!(50, odds.len());
+        assert_eq!(Some(&99), odds.last());
+
+        //  Re-entrant allocation.
+        let nested = arena.alloc_from_iter((0..10).map(|i| *arena.alloc(i) * 2));
+
+        assert_eq!(18, nested[9]);
+        assert_eq!(Some(&99), odds.last());
+    }
+
+    #[test]
+    fn drop_reverse_order() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+
+        let arena = TestArena::new();
+
+        arena.alloc(Tracker(dropped.clone(), 1));
+        arena.alloc_from_iter((2..5).map(|i| Tracker(dropped.clone(), i)));
+        arena.alloc(Tracker(dropped.clone(), 5));
+
+        assert!(dropped.borrow().is_empty());
+
+        drop(arena);
+
+        assert_eq!(vec![5, 2, 3, 4, 1], *dropped.borrow());
+    }
+} // mod tests
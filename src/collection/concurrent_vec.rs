@@ -27,6 +27,7 @@ impl<T, S: Store> ConcurrentVec<T, S> {
     /// Creates a vector with a given capacity and a default store.
     ///
     /// Since the vector cannot be resized later, pick well!
+    #[track_caller]
     pub fn new(capacity: usize) -> Self
     where
         S: Default,
@@ -37,6 +38,7 @@ impl<T, S: Store> ConcurrentVec<T, S> {
     /// Creates a vector with a given capacity and store.
     ///
     /// Since the vector cannot be resized later, pick well!
+    #[track_caller]
     pub fn with_store(capacity: usize, store: S) -> Self {
         let length = AtomicIsize::new(1);
         let store = Inner::with_store(capacity, store);
@@ -321,6 +323,7 @@ struct Inner<T, S: Store> {
 
 impl<T, S: Store> Inner<T, S> {
     //  Creates a store with a given capacity and store.
+    #[track_caller]
     fn with_store(capacity: usize, store: S) -> Self {
         let layout = Layout::array::<T>(capacity).expect("Small enough capacity");
 
@@ -54,6 +54,11 @@ impl<T, S: Store> LinkedList<T, S> {
         self.length
     }
 
+    /// Returns a reference to the store of the list.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
     /// Returns whether the list contains `element`, or not.
     pub fn contains(&self, element: &T) -> bool
     where
@@ -226,6 +231,7 @@ impl<T, S: Store> LinkedList<T, S> {
 
 impl<T, S: Store> LinkedList<T, S> {
     /// Pushes an element to the front of the list, unless memory allocation fails.
+    #[track_caller]
     pub fn try_push_front(&mut self, element: T) -> Result<(), AllocError> {
         let node = Node {
             element,
@@ -234,18 +240,28 @@ impl<T, S: Store> LinkedList<T, S> {
         };
         let handle = TypedHandle::try_new(node, &self.store)?;
 
-        self.head = handle;
+        if !self.is_empty() {
+            //  Safety:
+            //  -   `self.head` has been allocated by `self.store`.
+            //  -   `self.head` is valid, since `length` is not 0.
+            //  -   `self.head` is associated with a memory block containing a valid instance of `Node`.
+            //  -   Access to the resulting `head` is exclusive, as guaranteed by `self` being borrowed mutably.
+            let head = unsafe { self.head.resolve_mut(&self.store) };
 
-        if self.is_empty() {
+            head.prev = handle;
+        } else {
             self.tail = handle;
         }
 
+        self.head = handle;
+
         self.length += 1;
 
         Ok(())
     }
 
     /// Pushes an element to the back of the list, unless memory allocation fails.
+    #[track_caller]
     pub fn try_push_back(&mut self, element: T) -> Result<(), AllocError> {
         let node = Node {
             element,
@@ -1315,3 +1331,24 @@ mod inline_bump_tests {
         assert_eq!(r#"["0a", "1a", "2a"]"#, format!("{list:?}"));
     }
 } // mod inline_bump_tests
+
+#[cfg(test)]
+mod checked_tests {
+    use crate::store::{CheckedStore, InlineBumpStore};
+
+    use super::*;
+
+    type TestList = LinkedList<u32, CheckedStore<InlineBumpStore<u8, [u64; 16]>>>;
+
+    #[test]
+    fn push_front_pop_back() {
+        let mut list = TestList::new();
+
+        list.try_push_front(1).unwrap();
+        list.try_push_front(2).unwrap();
+
+        assert_eq!(Some(1), list.pop_back());
+        assert_eq!(Some(2), list.pop_back());
+        assert_eq!(None, list.pop_back());
+    }
+} // mod checked_tests
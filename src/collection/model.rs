@@ -0,0 +1,638 @@
+//! Differential, randomized, testing of the collections against their `std` counterparts.
+//!
+//! Each model generates random sequences of operations, seeded by `oorandom`, applies them to both a collection and its
+//! `std` counterpart, and compares the observable results after each operation. A panic in the collection, or in its
+//! store, counts as a failure too.
+//!
+//! On failure, the sequence is shrunk by removing operations for as long as it still fails, and the smallest failing
+//! sequence is reported alongside the seed and store which produced it.
+
+use core::{
+    alloc::{AllocError, Layout},
+    any, cmp,
+    fmt::Debug,
+    mem::ManuallyDrop,
+    ops::Range,
+    ptr::{Alignment, NonNull},
+};
+
+use std::{
+    alloc::Global,
+    collections::{BTreeMap, VecDeque},
+    panic::{self, AssertUnwindSafe},
+};
+
+use oorandom::Rand32;
+
+use crate::{
+    collection::{ConcurrentVec, LinkedList, SkipList, StoreVec},
+    interface::{Store, StoreDangling, StoreSingle, StoreStable},
+    store::{
+        ArenaStore, AtomicInlineBumpStore, AtomicStackBumpBlock, ChaosStore, CheckedStore, FailingStore, FailurePolicy,
+        Fallback, GenerationalStore, InlineBuddyStore, InlineBumpStore, InlineSingleStore, InlineSlabStore,
+        InlineTlsfStore, PoolStore, QuotaBudget, RecordingStore, RedZoneStore, Segregator, SiteStore, SmallSingleStore,
+        StackBuddyBlock, StackBumpBlock, StatsStore,
+    },
+};
+
+//  Number of sequences generated, per collection and store.
+const SEEDS: u64 = 32;
+
+//  Number of operations in each sequence.
+const OPERATIONS: usize = 64;
+
+//  Maximum number of live handles of the adapters keeping a table.
+const SLOTS: usize = 128;
+
+//  Inline memory of the inline stores, and of the blocks referenced by the stores, large enough for any sequence.
+type Memory = [u64; 2048];
+
+//  Slot of the pool stores, large enough for any allocation of any sequence.
+type Slot = [u64; 64];
+
+//  Checks a model, for a given store, panicking with the smallest failing sequence found, if any.
+#[track_caller]
+fn check<S, O>(generate: impl Fn(&mut Rand32) -> O, run: impl Fn(&[O]) -> Result<(), String>)
+where
+    O: Clone + Debug,
+{
+    let fails = |operations: &[O]| -> Option<String> {
+        match panic::catch_unwind(AssertUnwindSafe(|| run(operations))) {
+            Ok(Ok(())) => None,
+            Ok(Err(error)) => Some(error),
+            Err(payload) => Some(
+                payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "panicked".to_string()),
+            ),
+        }
+    };
+
+    for seed in 0..SEEDS {
+        let mut prng = Rand32::new(seed);
+
+        let operations: Vec<_> = (0..OPERATIONS).map(|_| generate(&mut prng)).collect();
+
+        if fails(&operations).is_none() {
+            continue;
+        }
+
+        let operations = shrink(operations, |operations| fails(operations).is_some());
+        let error = fails(&operations).unwrap_or_default();
+
+        panic!(
+            "{} failed with seed {seed}, smallest failing sequence ({} operations): {operations:?}\n{error}",
+            any::type_name::<S>(),
+            operations.len(),
+        );
+    }
+}
+
+//  Shrinks a failing sequence of operations, by removing ever smaller chunks of operations for as long as the sequence
+//  still fails.
+fn shrink<O: Clone>(mut operations: Vec<O>, fails: impl Fn(&[O]) -> bool) -> Vec<O> {
+    let mut chunk = cmp::max(operations.len() / 2, 1);
+
+    loop {
+        let mut start = 0;
+
+        while start < operations.len() {
+            let mut candidate = operations.clone();
+            candidate.drain(start..cmp::min(start + chunk, operations.len()));
+
+            if fails(&candidate) {
+                operations = candidate;
+            } else {
+                start += chunk;
+            }
+        }
+
+        if chunk == 1 {
+            return operations;
+        }
+
+        chunk /= 2;
+    }
+}
+
+//  Returns a random number in `range`.
+fn random(prng: &mut Rand32, range: Range<u32>) -> u32 {
+    prng.rand_range(range)
+}
+
+//  A store referencing a block of its own, so that each run of a check starts afresh, even after a failing run which
+//  leaked, or exhausted, its block.
+//
+//  A store cannot own the block it references, hence the block is boxed, and only freed once the store is dropped.
+struct FreshStore<B, S> {
+    store: ManuallyDrop<S>,
+    block: NonNull<B>,
+}
+
+impl<B: 'static, S> FreshStore<B, S> {
+    //  Creates a store, with `create`, referencing `block`.
+    fn new(block: B, create: impl FnOnce(&'static B) -> S) -> Self {
+        let block = NonNull::from(Box::leak(Box::new(block)));
+
+        //  Safety:
+        //  -   `block` is valid, and only freed once `store` is dropped.
+        let store = ManuallyDrop::new(create(unsafe { block.as_ref() }));
+
+        Self { store, block }
+    }
+}
+
+impl<B, S> Drop for FreshStore<B, S> {
+    fn drop(&mut self) {
+        //  Safety:
+        //  -   `self.store` is not used after this point.
+        unsafe { ManuallyDrop::drop(&mut self.store) };
+
+        //  Safety:
+        //  -   `self.block` was leaked from a `Box` on creation.
+        //  -   `self.block` is no longer referenced, since `self.store` was dropped.
+        drop(unsafe { Box::from_raw(self.block.as_ptr()) });
+    }
+}
+
+unsafe impl<B, S: StoreDangling> StoreDangling for FreshStore<B, S> {
+    type Handle = S::Handle;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        self.store.dangling(alignment)
+    }
+}
+
+unsafe impl<B, S: Store> Store for FreshStore<B, S> {
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        self.store.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle, layout) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.grow(handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.shrink(handle, old_layout, new_layout) }
+    }
+}
+
+unsafe impl<B, S: StoreSingle> StoreSingle for FreshStore<B, S> {
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle) }
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve_mut(handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        self.store.allocate(layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.grow(handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.shrink(handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<B, S: StoreStable> StoreStable for FreshStore<B, S> {}
+
+//
+//  StoreVec, against Vec.
+//
+
+#[derive(Clone, Debug)]
+enum VecOperation {
+    Push(u32),
+    Pop,
+    Clear,
+    Reserve(usize),
+    Set(usize, u32),
+    Get(usize),
+}
+
+fn generate_vec(prng: &mut Rand32) -> VecOperation {
+    match random(prng, 0..10) {
+        0..=3 => VecOperation::Push(prng.rand_u32()),
+        4 | 5 => VecOperation::Pop,
+        6 => VecOperation::Clear,
+        7 => VecOperation::Reserve(random(prng, 0..16) as usize),
+        8 => VecOperation::Set(random(prng, 0..16) as usize, prng.rand_u32()),
+        _ => VecOperation::Get(random(prng, 0..16) as usize),
+    }
+}
+
+#[track_caller]
+fn check_store_vec<S: StoreSingle>(make: impl Fn() -> S) {
+    let run = |operations: &[VecOperation]| {
+        let mut actual = StoreVec::new_in(make());
+        let mut expected = Vec::new();
+
+        for (index, operation) in operations.iter().enumerate() {
+            match *operation {
+                VecOperation::Push(value) => {
+                    actual.push(value);
+                    expected.push(value);
+                }
+                VecOperation::Pop => compare(index, "pop", actual.pop(), expected.pop())?,
+                VecOperation::Clear => {
+                    actual.clear();
+                    expected.clear();
+                }
+                VecOperation::Reserve(additional) => {
+                    actual.reserve(additional);
+
+                    if actual.capacity() < actual.len() + additional {
+                        return Err(format!(
+                            "#{index}: reserve({additional}) left capacity {}",
+                            actual.capacity()
+                        ));
+                    }
+                }
+                VecOperation::Set(n, value) => {
+                    if let Some(element) = actual.get_mut(n) {
+                        *element = value;
+                    }
+
+                    if let Some(element) = expected.get_mut(n) {
+                        *element = value;
+                    }
+                }
+                VecOperation::Get(n) => compare(index, "get", actual.get(n), expected.get(n))?,
+            }
+
+            compare(index, "as_slice", actual.as_slice(), expected.as_slice())?;
+        }
+
+        Ok(())
+    };
+
+    check::<S, _>(generate_vec, run);
+}
+
+//
+//  LinkedList, against VecDeque.
+//
+
+#[derive(Clone, Debug)]
+enum ListOperation {
+    PushFront(u32),
+    PushBack(u32),
+    PopFront,
+    PopBack,
+    SetFront(u32),
+    SetBack(u32),
+    Contains(u32),
+    Clear,
+}
+
+fn generate_list(prng: &mut Rand32) -> ListOperation {
+    //  Small values, so that `contains` sometimes finds them.
+    let value = random(prng, 0..32);
+
+    match random(prng, 0..14) {
+        0..=2 => ListOperation::PushFront(value),
+        3..=5 => ListOperation::PushBack(value),
+        6 | 7 => ListOperation::PopFront,
+        8 | 9 => ListOperation::PopBack,
+        10 => ListOperation::SetFront(value),
+        11 => ListOperation::SetBack(value),
+        12 => ListOperation::Contains(value),
+        _ => ListOperation::Clear,
+    }
+}
+
+#[track_caller]
+fn check_linked_list<S: Store>(make: impl Fn() -> S) {
+    let run = |operations: &[ListOperation]| {
+        let mut actual = LinkedList::new_in(make());
+        let mut expected = VecDeque::new();
+
+        for (index, operation) in operations.iter().enumerate() {
+            match *operation {
+                ListOperation::PushFront(value) => {
+                    actual
+                        .try_push_front(value)
+                        .map_err(|_| format!("#{index}: push_front failed"))?;
+                    expected.push_front(value);
+                }
+                ListOperation::PushBack(value) => {
+                    actual
+                        .try_push_back(value)
+                        .map_err(|_| format!("#{index}: push_back failed"))?;
+                    expected.push_back(value);
+                }
+                ListOperation::PopFront => compare(index, "pop_front", actual.pop_front(), expected.pop_front())?,
+                ListOperation::PopBack => compare(index, "pop_back", actual.pop_back(), expected.pop_back())?,
+                ListOperation::SetFront(value) => {
+                    if let Some(element) = actual.front_mut() {
+                        *element = value;
+                    }
+
+                    if let Some(element) = expected.front_mut() {
+                        *element = value;
+                    }
+                }
+                ListOperation::SetBack(value) => {
+                    if let Some(element) = actual.back_mut() {
+                        *element = value;
+                    }
+
+                    if let Some(element) = expected.back_mut() {
+                        *element = value;
+                    }
+                }
+                ListOperation::Contains(value) => {
+                    compare(index, "contains", actual.contains(&value), expected.contains(&value))?
+                }
+                ListOperation::Clear => {
+                    actual.clear();
+                    expected.clear();
+                }
+            }
+
+            compare(index, "len", actual.len(), expected.len())?;
+            compare(index, "front", actual.front_mut().copied(), expected.front().copied())?;
+            compare(index, "back", actual.back_mut().copied(), expected.back().copied())?;
+        }
+
+        //  `iter` requires `StoreStable`, hence the contents are compared by draining instead.
+        let index = operations.len();
+
+        while let Some(element) = expected.pop_front() {
+            compare(index, "drain", actual.pop_front(), Some(element))?;
+        }
+
+        compare(index, "drain", actual.pop_front(), None)
+    };
+
+    check::<S, _>(generate_list, run);
+}
+
+//
+//  SkipList, against BTreeMap.
+//
+
+#[derive(Clone, Debug)]
+enum MapOperation {
+    Insert(u32, u32),
+    Get(u32),
+    Set(u32, u32),
+    Clear,
+}
+
+fn generate_map(prng: &mut Rand32) -> MapOperation {
+    //  Small keys, so that insertions sometimes collide.
+    let key = random(prng, 0..48);
+
+    match random(prng, 0..16) {
+        0..=7 => MapOperation::Insert(key, prng.rand_u32()),
+        8..=11 => MapOperation::Get(key),
+        12..=14 => MapOperation::Set(key, prng.rand_u32()),
+        _ => MapOperation::Clear,
+    }
+}
+
+#[track_caller]
+fn check_skip_list<S: Store + StoreStable>(make: impl Fn() -> S) {
+    let run = |operations: &[MapOperation]| {
+        let mut actual = SkipList::with_store(make());
+        let mut expected = BTreeMap::new();
+
+        for (index, operation) in operations.iter().enumerate() {
+            match *operation {
+                MapOperation::Insert(key, value) => {
+                    let previous = actual.insert(key, value).map(|(_, value)| value);
+
+                    compare(index, "insert", previous, expected.insert(key, value))?;
+                }
+                MapOperation::Get(key) => compare(index, "get", actual.get(&key), expected.get(&key))?,
+                MapOperation::Set(key, value) => {
+                    if let Some(element) = actual.get_mut(&key) {
+                        *element = value;
+                    }
+
+                    if let Some(element) = expected.get_mut(&key) {
+                        *element = value;
+                    }
+                }
+                MapOperation::Clear => {
+                    actual.clear();
+                    expected.clear();
+                }
+            }
+
+            compare(index, "len", actual.len(), expected.len())?;
+        }
+
+        let index = operations.len();
+
+        for key in 0..48 {
+            compare(index, "get", actual.get(&key), expected.get(&key))?;
+        }
+
+        Ok(())
+    };
+
+    check::<S, _>(generate_map, run);
+}
+
+//
+//  ConcurrentVec, against Vec.
+//
+
+//  Capacity of the vectors, small enough to be exceeded.
+const CONCURRENT_CAPACITY: usize = 48;
+
+#[derive(Clone, Debug)]
+enum ConcurrentOperation {
+    Push(u32),
+    Set(usize, u32),
+}
+
+fn generate_concurrent(prng: &mut Rand32) -> ConcurrentOperation {
+    match random(prng, 0..4) {
+        0..=2 => ConcurrentOperation::Push(prng.rand_u32()),
+        _ => ConcurrentOperation::Set(random(prng, 0..64) as usize, prng.rand_u32()),
+    }
+}
+
+#[track_caller]
+fn check_concurrent_vec<S: Store>(make: impl Fn() -> S) {
+    let run = |operations: &[ConcurrentOperation]| {
+        let mut actual = ConcurrentVec::with_store(CONCURRENT_CAPACITY, make());
+        let mut expected = Vec::new();
+
+        for (index, operation) in operations.iter().enumerate() {
+            match *operation {
+                ConcurrentOperation::Push(value) => {
+                    let result = actual.push(value);
+
+                    if expected.len() < CONCURRENT_CAPACITY {
+                        expected.push(value);
+
+                        compare(index, "push", result, Ok(()))?;
+                    } else {
+                        compare(index, "push", result, Err(value))?;
+                    }
+                }
+                ConcurrentOperation::Set(n, value) => {
+                    if let Some(element) = actual.as_slice_mut().get_mut(n) {
+                        *element = value;
+                    }
+
+                    if let Some(element) = expected.get_mut(n) {
+                        *element = value;
+                    }
+                }
+            }
+
+            compare(index, "as_slice", actual.as_slice(), expected.as_slice())?;
+        }
+
+        Ok(())
+    };
+
+    check::<S, _>(generate_concurrent, run);
+}
+
+//  Compares the result of the `operation` at `index`.
+fn compare<T: Debug + PartialEq>(index: usize, operation: &str, actual: T, expected: T) -> Result<(), String> {
+    if actual == expected {
+        return Ok(());
+    }
+
+    Err(format!(
+        "#{index}: {operation} returned {actual:?}, expected {expected:?}"
+    ))
+}
+
+//  Instantiates a check for every store, or at least every store implementing the required traits.
+//
+//  The stores referencing a block, or a budget, are wrapped in a `FreshStore`, so that each run gets its own.
+macro_rules! for_each_store {
+    ($check:ident) => {
+        $check(|| Global);
+        $check(ArenaStore::<Global>::default);
+        $check(AtomicInlineBumpStore::<u32, Memory>::default);
+        $check(|| {
+            FreshStore::new(AtomicStackBumpBlock::<Memory>::new(), |block| {
+                block.create_store::<u32>()
+            })
+        });
+        $check(CheckedStore::<Global, SLOTS>::default);
+        $check(|| FailingStore::new_in(Global, FailurePolicy::Never));
+        $check(Fallback::<InlineBumpStore<u32, [u64; 16]>, Global>::default);
+        $check(GenerationalStore::<Global, SLOTS>::default);
+        $check(InlineBuddyStore::<u32, Memory>::default);
+        $check(InlineBumpStore::<u32, Memory>::default);
+        $check(InlineSlabStore::<u32, Memory>::default);
+        $check(InlineTlsfStore::<u32, Memory>::default);
+        $check(|| PoolStore::<Slot, Global>::with_capacity_in(SLOTS, Global));
+        $check(|| FreshStore::new(QuotaBudget::<4>::new(1 << 20), |budget| budget.create_store(Global)));
+        $check(RecordingStore::<Global, String>::default);
+        $check(RedZoneStore::<Global, SLOTS>::default);
+        $check(Segregator::<64, InlineSlabStore<u32, Memory>, Global>::default);
+        $check(SiteStore::<Global>::default);
+        $check(|| FreshStore::new(StackBuddyBlock::<Memory>::new(), |block| block.create_store::<u32>()));
+        $check(|| FreshStore::new(StackBumpBlock::<Memory>::new(), |block| block.create_store::<u32>()));
+        $check(StatsStore::<Global>::default);
+    };
+}
+
+#[test]
+fn store_vec() {
+    for_each_store!(check_store_vec);
+
+    check_store_vec(ChaosStore::<Global, SLOTS>::default);
+    check_store_vec(InlineSingleStore::<Memory>::default);
+    check_store_vec(SmallSingleStore::<[u64; 4], Global>::default);
+}
+
+#[test]
+fn linked_list() {
+    for_each_store!(check_linked_list);
+
+    check_linked_list(ChaosStore::<Global, SLOTS>::default);
+}
+
+#[test]
+fn skip_list() {
+    //  `ChaosStore` is excluded, as `SkipList` requires `StoreStable`.
+    for_each_store!(check_skip_list);
+}
+
+#[test]
+fn concurrent_vec() {
+    for_each_store!(check_concurrent_vec);
+
+    check_concurrent_vec(ChaosStore::<Global, SLOTS>::default);
+}
+
+#[test]
+fn shrinking() {
+    //  A sequence fails if it contains both a 3 and a 7: the smallest failing sequence is thus `[3, 7]`.
+    let fails = |operations: &[u32]| operations.contains(&3) && operations.contains(&7);
+
+    let operations = shrink((0..64).collect(), fails);
+
+    assert_eq!(vec![3, 7], operations);
+}
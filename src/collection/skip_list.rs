@@ -4,7 +4,6 @@
 
 use core::{
     alloc::Layout,
-    cmp,
     marker::PhantomData,
     mem,
     ptr::{self, NonNull},
@@ -138,6 +137,7 @@ where
     /// Inserts a new key and value in the list.
     ///
     /// If a `key` comparing equal is already in the list, it is returned alongside the value it's in with.
+    #[track_caller]
     pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
         if self.length == 0 {
             self.head = NodeHeader::new(key, value, 0, &self.store).0;
@@ -172,7 +172,9 @@ where
 
         //  Well, that'll avoid having to reallocate `head`!
         if key < node.key {
-            let target_links = cmp::max(target_links, head_links);
+            //  A link must point to a node with more links, or to the last node, hence the new head cannot have more
+            //  links than the current head, unless the current head is the last node.
+            let target_links = if head_links == 0 { target_links } else { head_links };
 
             let (node, links) = NodeHeader::new(key, value, target_links, &self.store);
 
@@ -262,6 +264,17 @@ where
             *dangling_handle = prev_handle;
         }
 
+        //  The levels of the new node that the head does not have yet cannot point to any node but the last, since no
+        //  other node has as many links.
+        if last.is_none() && target_links > head_links {
+            //  Safety:
+            //  -   `links[head_links - 1]` was allocated by `self.store`, and is still valid, as it was spliced in.
+            //  -   The nodes following it at `head_links - 1` have more links, or are the last node, by invariant.
+            let tail = unsafe { Self::find_last(links[head_links - 1], head_links - 1, &self.store) };
+
+            links[head_links..].iter_mut().for_each(|link| *link = tail);
+        }
+
         //  Exchange with last, if it goes beyond last.
         if let Some(mut last) = last {
             //  Safety:
@@ -338,6 +351,33 @@ impl<K, V, S: Store> SkipList<K, V, S> {
         (self.prng.rand_u32() | 1).trailing_ones() as usize
     }
 
+    //  Returns the last node, following the links at `level` from `handle`.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must have been allocated by `store`.
+    //  -   `handle` must still be valid.
+    //  -   `handle`, and the nodes following it at `level`, must have more than `level` links, or be the last node.
+    unsafe fn find_last(
+        mut handle: NodeHandle<K, V, S::Handle>,
+        level: usize,
+        store: &S,
+    ) -> NodeHandle<K, V, S::Handle> {
+        loop {
+            //  Safety:
+            //  -   `handle` has been allocated by `store`, as per pre-conditions.
+            //  -   `handle` is still valid, as per pre-conditions.
+            //  -   `handle` is associated to block of memory containing a live instance of `NodeHeader`.
+            let node = unsafe { handle.resolve(store) };
+
+            let Some(next) = node.links().get(level) else {
+                return handle;
+            };
+
+            handle = *next;
+        }
+    }
+
     //  #   Safety
     //
     //  -   `handle` must have been allocated by `store`.
@@ -353,7 +393,7 @@ impl<K, V, S: Store> SkipList<K, V, S> {
         //  Safety:
         //  -   `pointer` points to a valid `NodeHeader`.
         //  -   `offset` is an offset within the allocation of `NodeHeader`.
-        let pointer = unsafe { pointer.as_ptr().add(offset) };
+        let pointer = unsafe { pointer.as_ptr().cast::<u8>().add(offset) };
 
         //  Safety:
         //  -   `pointer` is not null.
@@ -449,6 +489,7 @@ where
 
     //  Creates a node with `number_links` links, returning a handle to the node and an array of dangling links.
     #[allow(clippy::new_ret_no_self, clippy::type_complexity)]
+    #[track_caller]
     fn new<S>(key: K, value: V, number_links: usize, store: &S) -> (NodeHandle<K, V, H>, &mut [NodeHandle<K, V, H>])
     where
         S: Store<Handle = H>,
@@ -512,6 +553,7 @@ where
     //  -   No other reference to its block of memory is active.
     //  -   `old_number_links` must match the previous number of links.
     //  -   `new_number_links` must be strictly greater than `old_number_links`.
+    #[track_caller]
     unsafe fn grow<S>(
         handle: NodeHandle<K, V, H>,
         with: NodeHandle<K, V, H>,
@@ -687,6 +729,16 @@ mod tests {
         assert_eq!(Some(&String::from("00")), list.get(&0));
     }
 
+    #[test]
+    fn insert_value_after_key() {
+        //  The value is laid out after the key, at a non-zero offset within the node.
+        let mut list = SkipList::<u64, u32, Global>::default();
+
+        list.insert(0, 42);
+
+        assert_eq!(Some(&42), list.get(&0));
+    }
+
     //  MIRI does not like the idea of borrowing the "tail" links from the header, due to the original borrow of the
     //  header not encompassing the tail.
     #[cfg_attr(miri, ignore)]
@@ -728,4 +780,22 @@ mod tests {
         assert_eq!(Some(&String::from("1")), list.get(&1));
         assert_eq!(None, list.get(&2));
     }
+
+    //  MIRI does not like the idea of borrowing the "tail" links from the header, due to the original borrow of the
+    //  header not encompassing the tail.
+    #[cfg_attr(miri, ignore)]
+    #[test]
+    fn insert_front_then_back() {
+        let mut list = SkipList::<u32, u32, Global>::default();
+
+        for key in (0..7).rev().chain(7..8) {
+            list.insert(key, key);
+        }
+
+        assert_eq!(8, list.len());
+
+        for key in 0..8 {
+            assert_eq!(Some(&key), list.get(&key));
+        }
+    }
 } // mod tests
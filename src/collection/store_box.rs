@@ -21,6 +21,7 @@ pub struct StoreBox<T: ?Sized, S: StoreSingle> {
 
 impl<T, S: StoreSingle + Default> StoreBox<T, S> {
     /// Creates a new instance.
+    #[track_caller]
     pub fn new(value: T) -> Self {
         Self::new_in(value, S::default())
     }
@@ -28,6 +29,7 @@ impl<T, S: StoreSingle + Default> StoreBox<T, S> {
 
 impl<T, S: StoreSingle> StoreBox<T, S> {
     /// Creates a new instance.
+    #[track_caller]
     pub fn new_in(value: T, mut store: S) -> Self {
         let handle = UniqueSingleHandle::new(value, &mut store);
         let store = ManuallyDrop::new(store);
@@ -36,6 +38,7 @@ impl<T, S: StoreSingle> StoreBox<T, S> {
     }
 
     /// Attempts to create a new instance.
+    #[track_caller]
     pub fn try_new_in(value: T, mut store: S) -> Result<Self, AllocError> {
         let handle = UniqueSingleHandle::try_new(value, &mut store)?;
         let store = ManuallyDrop::new(store);
@@ -30,6 +30,7 @@ impl<T, S: StoreSingle + Default> StoreVec<T, S> {
     }
 
     /// Creates a new, empty, instance with at least the specified capacity.
+    #[track_caller]
     pub fn with_capacity(capacity: usize) -> Self {
         Self::with_capacity_in(capacity, S::default())
     }
@@ -48,6 +49,7 @@ impl<T, S: StoreSingle> StoreVec<T, S> {
     }
 
     /// Creates a new, empty, instance with at least the specified capacity.
+    #[track_caller]
     pub const fn with_capacity_in(capacity: usize, store: S) -> Self
     where
         S: ~const StoreSingle + ~const StoreDangling,
@@ -75,6 +77,11 @@ impl<T, S: StoreSingle> StoreVec<T, S> {
         self.array.capacity()
     }
 
+    /// Returns a reference to the store of the vector.
+    pub const fn store(&self) -> &S {
+        &self.array.store
+    }
+
     /// Forces the length of the vector to `new_len`.
     ///
     /// #   Safety
@@ -171,6 +178,7 @@ impl<T, S: StoreSingle> StoreVec<T, S> {
     /// #   Panics
     ///
     /// Panics if the new capacity exceeds `isize::MAX` bytes.
+    #[track_caller]
     pub const fn reserve(&mut self, additional: usize)
     where
         S: ~const StoreSingle + ~const StoreDangling,
@@ -256,6 +264,7 @@ impl<T, S: StoreSingle> StoreVec<T, S> {
     }
 
     /// Appends an element at the back the vector.
+    #[track_caller]
     pub const fn push(&mut self, value: T)
     where
         S: ~const StoreSingle + ~const StoreDangling,
@@ -325,6 +334,7 @@ impl<T, S: StoreSingle> Drop for StoreVec<T, S> {
 
 impl<T, S: StoreSingle> StoreVec<T, S> {
     #[inline(never)]
+    #[track_caller]
     const fn grow_for(&mut self, additional: usize)
     where
         S: ~const StoreSingle + ~const StoreDangling,
@@ -361,6 +371,7 @@ impl<T, S: StoreSingle> UniqueArray<T, S> {
         Self { handle, store }
     }
 
+    #[track_caller]
     const fn with_capacity_in(capacity: usize, mut store: S) -> Self
     where
         S: ~const StoreSingle + ~const StoreDangling,
@@ -477,6 +488,7 @@ impl<T, S: StoreSingle> UniqueArray<T, S> {
     //  #   Panics
     //
     //  If the new capacity exceeds `isize::MAX` bytes.
+    #[track_caller]
     const unsafe fn grow_to(&mut self, target_capacity: usize)
     where
         S: ~const StoreSingle + ~const StoreDangling,
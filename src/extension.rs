@@ -1,5 +1,6 @@
 //! Possible extensions to be built around `Store`.
 
+pub mod drop_arena;
 pub mod typed;
 pub mod typed_metadata;
 pub mod unique;
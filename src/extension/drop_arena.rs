@@ -0,0 +1,290 @@
+//! Arena, running the destructors of the values it holds on reset or drop.
+//!
+//! Allocating values in a bump store, or any store really, means either leaking them or tracking them by hand in order
+//! to drop them. A `DropArena` instead registers the destructor of each value it allocates, and runs all registered
+//! destructors, in reverse order of registration, on `reset` or drop.
+
+use core::{
+    alloc::{AllocError, Layout},
+    cell::Cell,
+    fmt, mem,
+    ptr::{self, NonNull},
+};
+
+use crate::{
+    alloc,
+    extension::{typed::TypedHandle, typed_metadata::TypedMetadata},
+    interface::Store,
+};
+
+/// Arena atop a `Store`, running the destructors of the values it holds on reset or drop.
+///
+/// The values allocated by the arena must not be deallocated by the user, they are only deallocated on `reset` or drop,
+/// once their destructor ran.
+///
+/// The values must be `'static`, as their destructor runs long after they are inserted, once any data they borrow may
+/// be gone.
+///
+/// ```compile_fail
+/// use storage::{extension::drop_arena::DropArena, store::InlineBumpStore};
+///
+/// struct Loud<'a>(&'a str);
+///
+/// impl Drop for Loud<'_> {
+///     fn drop(&mut self) {
+///         println!("{}", self.0);
+///     }
+/// }
+///
+/// let arena = DropArena::<InlineBumpStore<u8, [usize; 8]>>::new();
+///
+/// let local = String::from("local");
+/// arena.insert(Loud(&local));
+///
+/// drop(local);
+/// ```
+pub struct DropArena<S: Store> {
+    //  Last registered destructor, if any, linked to the previously registered ones.
+    last: Cell<Option<S::Handle>>,
+    store: S,
+}
+
+impl<S: Store + Default> DropArena<S> {
+    /// Creates a new instance.
+    pub fn new() -> Self {
+        Self::new_in(S::default())
+    }
+}
+
+impl<S: Store> DropArena<S> {
+    /// Creates a new instance, atop `store`.
+    pub const fn new_in(store: S) -> Self {
+        let last = Cell::new(None);
+
+        Self { last, store }
+    }
+
+    /// Returns a reference to the underlying store, to resolve the handles allocated by the arena.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Allocates a new `T`, whose destructor will be run on `reset` or drop.
+    ///
+    /// Calls `handle_alloc_error` if the allocation fails.
+    pub fn insert<T: 'static>(&self, value: T) -> TypedHandle<T, S::Handle> {
+        let Ok(handle) = self.try_insert(value) else {
+            alloc::handle_alloc_error(Layout::new::<T>())
+        };
+
+        handle
+    }
+
+    /// Attempts to allocate a new `T`, whose destructor will be run on `reset` or drop.
+    ///
+    /// If the allocation fails, `value` is dropped, and `AllocError` is returned.
+    pub fn try_insert<T: 'static>(&self, value: T) -> Result<TypedHandle<T, S::Handle>, AllocError> {
+        //  Values which do not need dropping need not be tracked.
+        if !mem::needs_drop::<T>() {
+            return TypedHandle::try_new(value, &self.store);
+        }
+
+        //  Allocate the value first, so that destroying in reverse order deallocates the entry before the value.
+        let handle = TypedHandle::try_new(value, &self.store)?;
+
+        let entry = DropEntry {
+            previous: self.last.get(),
+            value: handle.to_raw_parts().0,
+            layout: Layout::new::<T>(),
+            destructor: drop_value::<T>,
+        };
+
+        match TypedHandle::try_new(entry, &self.store) {
+            Ok(entry) => {
+                self.last.set(Some(entry.to_raw_parts().0));
+
+                Ok(handle)
+            }
+            Err(error) => {
+                //  Safety:
+                //  -   `handle` was allocated by `self.store`, and is still valid.
+                //  -   `handle` points to a live `T`, which will not be used again.
+                unsafe { ptr::drop_in_place(handle.resolve_raw(&self.store).as_ptr()) };
+
+                //  Safety:
+                //  -   `handle` was allocated by `self.store`, and is still valid.
+                unsafe { handle.deallocate(&self.store) };
+
+                Err(error)
+            }
+        }
+    }
+
+    /// Runs all registered destructors, in reverse order of registration, and deallocates the associated values.
+    ///
+    /// All the handles returned by `insert` and `try_insert` for values needing to be dropped are invalidated.
+    pub fn reset(&mut self) {
+        while let Some(entry) = self.last.take() {
+            let entry: TypedHandle<DropEntry<S::Handle>, S::Handle> =
+                TypedHandle::from_raw_parts(entry, TypedMetadata::new());
+
+            //  Safety:
+            //  -   `entry` was allocated by `self.store`, and is still valid, as it was never deallocated.
+            //  -   `entry` points to a live `DropEntry`.
+            let DropEntry {
+                previous,
+                value,
+                layout,
+                destructor,
+            } = unsafe { ptr::read(entry.resolve_raw(&self.store).as_ptr()) };
+
+            //  Safety:
+            //  -   `entry` was allocated by `self.store`, and is still valid.
+            unsafe { entry.deallocate(&self.store) };
+
+            //  Safety:
+            //  -   `value` was allocated by `self.store`, and is still valid.
+            let pointer = unsafe { self.store.resolve(value) };
+
+            //  Safety:
+            //  -   `destructor` matches the type of the live value `pointer` points to.
+            //  -   The value will not be used again, as per the pre-conditions of `TypedHandle::resolve`.
+            unsafe { destructor(pointer) };
+
+            //  Safety:
+            //  -   `value` was allocated by `self.store`, with `layout`, and is still valid.
+            unsafe { self.store.deallocate(value, layout) };
+
+            self.last.set(previous);
+        }
+    }
+
+    /// Runs all registered destructors, in reverse order of registration, and returns the underlying store.
+    pub fn into_inner(mut self) -> S {
+        self.reset();
+
+        let this = mem::ManuallyDrop::new(self);
+
+        //  Safety:
+        //  -   `this.store` is never used again, and `this` is never dropped.
+        unsafe { ptr::read(&this.store) }
+    }
+}
+
+impl<S: Store + Default> Default for DropArena<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Store> Drop for DropArena<S> {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+impl<S: Store + fmt::Debug> fmt::Debug for DropArena<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("DropArena").field("store", &self.store).finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Registered destructor, linked to the previously registered one.
+struct DropEntry<H> {
+    previous: Option<H>,
+    value: H,
+    layout: Layout,
+    destructor: unsafe fn(NonNull<u8>),
+}
+
+//  #   Safety
+//
+//  -   `pointer` must point to a live `T`, which is not used afterwards.
+unsafe fn drop_value<T>(pointer: NonNull<u8>) {
+    //  Safety:
+    //  -   As per pre-conditions.
+    unsafe { ptr::drop_in_place(pointer.cast::<T>().as_ptr()) };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::store::InlineBumpStore;
+
+    use super::*;
+
+    type TestArena = DropArena<InlineBumpStore<u8, [usize; 24]>>;
+
+    struct Tracker(Rc<RefCell<Vec<u32>>>, u32);
+
+    impl Drop for Tracker {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    #[test]
+    fn reset_reverse_order() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+
+        let mut arena = TestArena::new();
+
+        let first = arena.insert(Tracker(dropped.clone(), 1));
+        arena.insert(42u64);
+        arena.insert(Tracker(dropped.clone(), 2));
+
+        assert_eq!(1, unsafe { first.resolve(arena.store()) }.1);
+
+        arena.reset();
+
+        assert_eq!(vec![2, 1], *dropped.borrow());
+
+        arena.insert(Tracker(dropped.clone(), 3));
+
+        drop(arena);
+
+        assert_eq!(vec![2, 1, 3], *dropped.borrow());
+    }
+
+    #[test]
+    fn reset_reclaims() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+
+        let mut arena = TestArena::new();
+
+        let first = arena.insert(Tracker(dropped.clone(), 1));
+        arena.insert(Tracker(dropped.clone(), 2));
+
+        arena.reset();
+
+        //  The bump store reclaims memory deallocated in reverse order.
+        let second = arena.insert(Tracker(dropped.clone(), 3));
+
+        assert_eq!(first.to_raw_parts().0, second.to_raw_parts().0);
+    }
+
+    #[test]
+    fn exhaustion() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+
+        let arena = DropArena::<InlineBumpStore<u8, [usize; 16]>>::new();
+
+        let mut inserted = 0;
+
+        while arena.try_insert(Tracker(dropped.clone(), inserted)).is_ok() {
+            inserted += 1;
+        }
+
+        //  The value failing to be inserted is dropped immediately.
+        assert_eq!(vec![inserted], *dropped.borrow());
+
+        drop(arena);
+
+        assert_eq!(inserted as usize + 1, dropped.borrow().len());
+    }
+} // mod tests
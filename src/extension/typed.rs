@@ -60,6 +60,7 @@ impl<T, H: Copy> TypedHandle<T, H> {
 
     /// Creates a new handle, pointing to a `T`.
     #[inline(always)]
+    #[track_caller]
     pub fn new<S>(value: T, store: &S) -> Self
     where
         S: Store<Handle = H>,
@@ -73,6 +74,7 @@ impl<T, H: Copy> TypedHandle<T, H> {
 
     /// Attempts to create a new handle, pointing to a `T`.
     #[inline(always)]
+    #[track_caller]
     pub fn try_new<S>(value: T, store: &S) -> Result<Self, AllocError>
     where
         S: Store<Handle = H>,
@@ -99,6 +101,7 @@ impl<T, H: Copy> TypedHandle<T, H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate<S>(store: &S) -> Self
     where
         S: ~const Store<Handle = H>,
@@ -114,6 +117,7 @@ impl<T, H: Copy> TypedHandle<T, H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate<S>(store: &S) -> Result<Self, AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -131,6 +135,7 @@ impl<T, H: Copy> TypedHandle<T, H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_zeroed<S>(store: &S) -> Self
     where
         S: ~const Store<Handle = H>,
@@ -146,6 +151,7 @@ impl<T, H: Copy> TypedHandle<T, H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_zeroed<S>(store: &S) -> Result<Self, AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -341,6 +347,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_slice<S>(size: usize, store: &S) -> Self
     where
         S: ~const Store<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -356,6 +363,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_slice<S>(size: usize, store: &S) -> Result<Self, AllocError>
     where
         S: ~const Store<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -389,6 +397,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_zeroed_slice<S>(size: usize, store: &S) -> Self
     where
         S: ~const Store<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -404,6 +413,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_zeroed_slice<S>(size: usize, store: &S) -> Result<Self, AllocError>
     where
         S: ~const Store<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -453,6 +463,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn grow<S>(&mut self, new_size: usize, store: &S)
     where
         S: ~const Store<Handle = H>,
@@ -478,6 +489,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_grow<S>(&mut self, new_size: usize, store: &S) -> Result<(), AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -521,6 +533,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn grow_zeroed<S>(&mut self, new_size: usize, store: &S)
     where
         S: ~const Store<Handle = H>,
@@ -546,6 +559,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_grow_zeroed<S>(&mut self, new_size: usize, store: &S) -> Result<(), AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -588,6 +602,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be less than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn shrink<S>(&mut self, new_size: usize, store: &S)
     where
         S: ~const Store<Handle = H>,
@@ -612,6 +627,7 @@ impl<T, H: Copy> TypedHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be less than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_shrink<S>(&mut self, new_size: usize, store: &S) -> Result<(), AllocError>
     where
         S: ~const Store<Handle = H>,
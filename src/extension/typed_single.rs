@@ -60,6 +60,7 @@ impl<T, H: Copy> TypedSingleHandle<T, H> {
 
     /// Creates a new handle, pointing to a `T`.
     #[inline(always)]
+    #[track_caller]
     pub fn new<S>(value: T, store: &mut S) -> Self
     where
         S: StoreSingle<Handle = H>,
@@ -73,6 +74,7 @@ impl<T, H: Copy> TypedSingleHandle<T, H> {
 
     /// Attempts to create a new handle, pointing to a `T`.
     #[inline(always)]
+    #[track_caller]
     pub fn try_new<S>(value: T, store: &mut S) -> Result<Self, AllocError>
     where
         S: StoreSingle<Handle = H>,
@@ -99,6 +101,7 @@ impl<T, H: Copy> TypedSingleHandle<T, H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate<S>(store: &mut S) -> Self
     where
         S: ~const StoreSingle<Handle = H>,
@@ -114,6 +117,7 @@ impl<T, H: Copy> TypedSingleHandle<T, H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate<S>(store: &mut S) -> Result<Self, AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -131,6 +135,7 @@ impl<T, H: Copy> TypedSingleHandle<T, H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_zeroed<S>(store: &mut S) -> Self
     where
         S: ~const StoreSingle<Handle = H>,
@@ -146,6 +151,7 @@ impl<T, H: Copy> TypedSingleHandle<T, H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_zeroed<S>(store: &mut S) -> Result<Self, AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -365,6 +371,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_slice<S>(size: usize, store: &mut S) -> Self
     where
         S: ~const StoreSingle<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -380,6 +387,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_slice<S>(size: usize, store: &mut S) -> Result<Self, AllocError>
     where
         S: ~const StoreSingle<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -413,6 +421,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_zeroed_slice<S>(size: usize, store: &mut S) -> Self
     where
         S: ~const StoreSingle<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -428,6 +437,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_zeroed_slice<S>(size: usize, store: &mut S) -> Result<Self, AllocError>
     where
         S: ~const StoreSingle<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -477,6 +487,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn grow<S>(&mut self, new_size: usize, store: &mut S)
     where
         S: ~const StoreSingle<Handle = H>,
@@ -502,6 +513,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_grow<S>(&mut self, new_size: usize, store: &mut S) -> Result<(), AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -545,6 +557,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn grow_zeroed<S>(&mut self, new_size: usize, store: &mut S)
     where
         S: ~const StoreSingle<Handle = H>,
@@ -570,6 +583,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_grow_zeroed<S>(&mut self, new_size: usize, store: &mut S) -> Result<(), AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -612,6 +626,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be less than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn shrink<S>(&mut self, new_size: usize, store: &mut S)
     where
         S: ~const StoreSingle<Handle = H>,
@@ -636,6 +651,7 @@ impl<T, H: Copy> TypedSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be less than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_shrink<S>(&mut self, new_size: usize, store: &mut S) -> Result<(), AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
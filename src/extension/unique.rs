@@ -42,6 +42,7 @@ impl<T, H: Copy> UniqueHandle<T, H> {
 
     /// Creates a new handle, pointing to a `T`.
     #[inline(always)]
+    #[track_caller]
     pub fn new<S>(value: T, store: &S) -> Self
     where
         S: Store<Handle = H>,
@@ -51,6 +52,7 @@ impl<T, H: Copy> UniqueHandle<T, H> {
 
     /// Attempts to create a new handle, pointing to a `T`.
     #[inline(always)]
+    #[track_caller]
     pub fn try_new<S>(value: T, store: &S) -> Result<Self, AllocError>
     where
         S: Store<Handle = H>,
@@ -62,6 +64,7 @@ impl<T, H: Copy> UniqueHandle<T, H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate<S>(store: &S) -> Self
     where
         S: ~const Store<Handle = H>,
@@ -73,6 +76,7 @@ impl<T, H: Copy> UniqueHandle<T, H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate<S>(store: &S) -> Result<Self, AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -88,6 +92,7 @@ impl<T, H: Copy> UniqueHandle<T, H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_zeroed<S>(store: &S) -> Self
     where
         S: ~const Store<Handle = H>,
@@ -99,6 +104,7 @@ impl<T, H: Copy> UniqueHandle<T, H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_zeroed<S>(store: &S) -> Result<Self, AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -267,6 +273,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_slice<S>(size: usize, store: &S) -> Self
     where
         S: ~const Store<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -278,6 +285,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_slice<S>(size: usize, store: &S) -> Result<Self, AllocError>
     where
         S: ~const Store<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -293,6 +301,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_zeroed_slice<S>(size: usize, store: &S) -> Self
     where
         S: ~const Store<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -304,6 +313,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_zeroed_slice<S>(size: usize, store: &S) -> Result<Self, AllocError>
     where
         S: ~const Store<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -334,6 +344,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn grow<S>(&mut self, new_size: usize, store: &S)
     where
         S: ~const Store<Handle = H>,
@@ -354,6 +365,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_grow<S>(&mut self, new_size: usize, store: &S) -> Result<(), AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -374,6 +386,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn grow_zeroed<S>(&mut self, new_size: usize, store: &S)
     where
         S: ~const Store<Handle = H>,
@@ -394,6 +407,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_grow_zeroed<S>(&mut self, new_size: usize, store: &S) -> Result<(), AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -414,6 +428,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be less than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn shrink<S>(&mut self, new_size: usize, store: &S)
     where
         S: ~const Store<Handle = H>,
@@ -434,6 +449,7 @@ impl<T, H: Copy> UniqueHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be less than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_shrink<S>(&mut self, new_size: usize, store: &S) -> Result<(), AllocError>
     where
         S: ~const Store<Handle = H>,
@@ -42,6 +42,7 @@ impl<T, H: Copy> UniqueSingleHandle<T, H> {
 
     /// Creates a new handle, pointing to a `T`.
     #[inline(always)]
+    #[track_caller]
     pub fn new<S>(value: T, store: &mut S) -> Self
     where
         S: StoreSingle<Handle = H>,
@@ -51,6 +52,7 @@ impl<T, H: Copy> UniqueSingleHandle<T, H> {
 
     /// Attempts to create a new handle, pointing to a `T`.
     #[inline(always)]
+    #[track_caller]
     pub fn try_new<S>(value: T, store: &mut S) -> Result<Self, AllocError>
     where
         S: StoreSingle<Handle = H>,
@@ -62,6 +64,7 @@ impl<T, H: Copy> UniqueSingleHandle<T, H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate<S>(store: &mut S) -> Self
     where
         S: ~const StoreSingle<Handle = H>,
@@ -73,6 +76,7 @@ impl<T, H: Copy> UniqueSingleHandle<T, H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate<S>(store: &mut S) -> Result<Self, AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -88,6 +92,7 @@ impl<T, H: Copy> UniqueSingleHandle<T, H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_zeroed<S>(store: &mut S) -> Self
     where
         S: ~const StoreSingle<Handle = H>,
@@ -99,6 +104,7 @@ impl<T, H: Copy> UniqueSingleHandle<T, H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_zeroed<S>(store: &mut S) -> Result<Self, AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -289,6 +295,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_slice<S>(size: usize, store: &mut S) -> Self
     where
         S: ~const StoreSingle<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -300,6 +307,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     ///
     /// The allocated memory is left uninitialized.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_slice<S>(size: usize, store: &mut S) -> Result<Self, AllocError>
     where
         S: ~const StoreSingle<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -315,6 +323,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn allocate_zeroed_slice<S>(size: usize, store: &mut S) -> Self
     where
         S: ~const StoreSingle<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -326,6 +335,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     ///
     /// The allocated memory is zeroed out.
     #[inline(always)]
+    #[track_caller]
     pub const fn try_allocate_zeroed_slice<S>(size: usize, store: &mut S) -> Result<Self, AllocError>
     where
         S: ~const StoreSingle<Handle = H> + ~const StoreDangling<Handle = H>,
@@ -356,6 +366,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn grow<S>(&mut self, new_size: usize, store: &mut S)
     where
         S: ~const StoreSingle<Handle = H>,
@@ -376,6 +387,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_grow<S>(&mut self, new_size: usize, store: &mut S) -> Result<(), AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -396,6 +408,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn grow_zeroed<S>(&mut self, new_size: usize, store: &mut S)
     where
         S: ~const StoreSingle<Handle = H>,
@@ -416,6 +429,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be greater than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_grow_zeroed<S>(&mut self, new_size: usize, store: &mut S) -> Result<(), AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -436,6 +450,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be less than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn shrink<S>(&mut self, new_size: usize, store: &mut S)
     where
         S: ~const StoreSingle<Handle = H>,
@@ -456,6 +471,7 @@ impl<T, H: Copy> UniqueSingleHandle<[T], H> {
     /// -   `self` must have been allocated by `store`.
     /// -   `self` must still be valid.
     /// -   `new_size` must be less than or equal to `self.len()`.
+    #[track_caller]
     pub const unsafe fn try_shrink<S>(&mut self, new_size: usize, store: &mut S) -> Result<(), AllocError>
     where
         S: ~const StoreSingle<Handle = H>,
@@ -222,6 +222,7 @@ pub unsafe trait Store: StoreDangling {
     ///
     /// Returning `Err` indicates that either the memory is exhausted, or the store cannot satisfy `new_layout`
     /// constraints.
+    #[track_caller]
     fn allocate_zeroed(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
         let Ok((handle, size)) = self.allocate(layout) else {
             return Err(AllocError);
@@ -251,6 +252,7 @@ pub unsafe trait Store: StoreDangling {
     ///
     /// Returning `Err` indicates that either the memory is exhausted, or the store cannot satisfy `new_layout`
     /// constraints.
+    #[track_caller]
     unsafe fn grow_zeroed(
         &self,
         handle: Self::Handle,
@@ -435,6 +437,7 @@ pub unsafe trait StoreSingle: StoreDangling {
     ///
     /// Returning `Err` indicates that either the memory is exhausted, or the store cannot satisfy `new_layout`
     /// constraints.
+    #[track_caller]
     fn allocate_zeroed(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
         let Ok((handle, size)) = self.allocate(layout) else {
             return Err(AllocError);
@@ -464,6 +467,7 @@ pub unsafe trait StoreSingle: StoreDangling {
     ///
     /// Returning `Err` indicates that either the memory is exhausted, or the store cannot satisfy `new_layout`
     /// constraints.
+    #[track_caller]
     unsafe fn grow_zeroed(
         &mut self,
         handle: Self::Handle,
@@ -21,6 +21,7 @@
 #![feature(const_trait_impl)]
 #![feature(const_try)]
 #![feature(const_ptr_write)]
+#![feature(dropck_eyepatch)]
 #![feature(hasher_prefixfree_extras)]
 #![feature(layout_for_ptr)]
 #![feature(maybe_uninit_write_slice)]
@@ -42,6 +43,9 @@
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(all(feature = "std", not(test)))]
+extern crate std;
+
 mod alloc;
 pub mod collection;
 pub mod extension;
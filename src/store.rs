@@ -1,10 +1,56 @@
 //! Provides implementations of multiple stores or store adapters.
 
+pub mod conformance;
+
 mod allocator_store;
+mod arena_store;
+mod atomic_inline_bump_store;
+mod atomic_stack_bump_store;
+mod bump_checkpoint;
+mod chaos_store;
+mod checked_store;
+mod failing_store;
+mod fallback_store;
+mod generational_store;
+mod inline_buddy_store;
 mod inline_bump_store;
 mod inline_single_store;
+mod inline_slab_store;
+mod inline_tlsf_store;
+mod layout_dump;
+mod pool_store;
+mod quota_store;
+mod recording_store;
+mod red_zone_store;
+mod segregator_store;
+mod site_store;
+mod small_single_store;
+mod stack_buddy_store;
 mod stack_bump_store;
+mod stats_store;
 
+pub use arena_store::ArenaStore;
+pub use atomic_inline_bump_store::AtomicInlineBumpStore;
+pub use atomic_stack_bump_store::{AtomicStackBumpBlock, AtomicStackBumpStore};
+pub use bump_checkpoint::BumpCheckpoint;
+pub use chaos_store::{ChaosHandle, ChaosStore, CHAOS_POISON};
+pub use checked_store::{CheckedHandle, CheckedStore};
+pub use failing_store::{FailingStore, FailurePolicy};
+pub use fallback_store::{Fallback, FallbackHandle, FallbackSharingError};
+pub use generational_store::{GenerationalHandle, GenerationalStore};
+pub use inline_buddy_store::InlineBuddyStore;
 pub use inline_bump_store::InlineBumpStore;
 pub use inline_single_store::InlineSingleStore;
+pub use inline_slab_store::InlineSlabStore;
+pub use inline_tlsf_store::InlineTlsfStore;
+pub use layout_dump::{LayoutDump, LayoutSummary, Region, RegionKind, StoreLayoutDump};
+pub use pool_store::{PoolHandle, PoolStore};
+pub use quota_store::{QuotaBudget, QuotaHandle, QuotaStore};
+pub use recording_store::{RecordedHandle, RecordingStore, ReplayReport, TraceError, TraceReplayer};
+pub use red_zone_store::{RedZoneHandle, RedZoneStore};
+pub use segregator_store::{Segregator, SegregatorHandle, SegregatorSharingError};
+pub use site_store::{SiteHandle, SiteReport, SiteStats, SiteStore};
+pub use small_single_store::{SmallHandle, SmallSingleStore};
+pub use stack_buddy_store::{StackBuddyBlock, StackBuddyStore};
 pub use stack_bump_store::{StackBumpBlock, StackBumpStore};
+pub use stats_store::{StatsHandle, StatsStore, StoreStats};
@@ -0,0 +1,571 @@
+//! A growable "bump allocator" Store, carving its memory blocks out of chunks obtained from an `Allocator`.
+//!
+//! Unlike `StackBumpBlock`, whose capacity is fixed at compile time, this store chains a new, larger, chunk whenever
+//! the current one is exhausted, making it suitable for data-structures of unknown size, such as parse trees.
+//!
+//! Memory blocks are only reclaimed when the store is dropped, with the exception of the last allocated memory block,
+//! which is reclaimed on deallocation, and may be grown or shrunk in place.
+
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::Cell,
+    cmp, fmt, iter, mem,
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::{
+    interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable},
+    store::{
+        layout_dump::{Region, RegionKind},
+        StoreLayoutDump,
+    },
+};
+
+/// An implementation of `Store` bump-allocating within a list of chunks, themselves allocated by `A`.
+///
+/// The handles are plain pointers, and remain valid until the store is dropped, even if the store itself is moved.
+pub struct ArenaStore<A: Allocator> {
+    //  Next free byte of the current chunk, or null if no chunk was allocated yet.
+    cursor: Cell<*mut u8>,
+    //  One past the last byte of the current chunk, or null if no chunk was allocated yet.
+    end: Cell<*mut u8>,
+    //  Header of the current chunk, linked to the headers of the previous chunks.
+    chunk: Cell<Option<NonNull<ChunkHeader>>>,
+    chunk_size: usize,
+    allocator: A,
+}
+
+impl<A: Allocator> ArenaStore<A> {
+    /// The default size of the first chunk, in bytes.
+    pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+    /// Creates a new instance, whose first chunk will be `DEFAULT_CHUNK_SIZE` bytes.
+    ///
+    /// No memory is allocated until the first allocation.
+    pub fn new_in(allocator: A) -> Self {
+        Self::with_chunk_size_in(Self::DEFAULT_CHUNK_SIZE, allocator)
+    }
+
+    /// Creates a new instance, whose first chunk will be (at least) `chunk_size` bytes.
+    ///
+    /// No memory is allocated until the first allocation.
+    pub fn with_chunk_size_in(chunk_size: usize, allocator: A) -> Self {
+        let (cursor, end, chunk) = (Cell::new(ptr::null_mut()), Cell::new(ptr::null_mut()), Cell::new(None));
+
+        Self {
+            cursor,
+            end,
+            chunk,
+            chunk_size,
+            allocator,
+        }
+    }
+
+    /// Returns a reference to the underlying allocator.
+    pub fn allocator(&self) -> &A {
+        &self.allocator
+    }
+}
+
+impl<A: Allocator + Default> Default for ArenaStore<A> {
+    fn default() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+impl<A: Allocator> Drop for ArenaStore<A> {
+    fn drop(&mut self) {
+        let mut next = self.chunk.take();
+
+        while let Some(chunk) = next {
+            //  Safety:
+            //  -   `chunk` points to a live header, as chunks are only deallocated here.
+            let ChunkHeader { previous, layout } = unsafe { ptr::read(chunk.as_ptr()) };
+
+            //  Safety:
+            //  -   `chunk` was allocated by `self.allocator`, with `layout`.
+            unsafe { self.allocator.deallocate(chunk.cast(), layout) };
+
+            next = previous;
+        }
+    }
+}
+
+unsafe impl<A: Allocator> StoreDangling for ArenaStore<A> {
+    type Handle = NonNull<u8>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let pointer = ptr::invalid_mut(alignment.as_usize());
+
+        //  Safety:
+        //  -   Non-null, since `alignment` is non-zero.
+        Ok(unsafe { NonNull::new_unchecked(pointer) })
+    }
+}
+
+unsafe impl<A: Allocator> Store for ArenaStore<A> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        handle
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        if layout.size() == 0 {
+            let pointer = ptr::invalid_mut(layout.align());
+
+            //  Safety:
+            //  -   Non-null, since `layout.align()` is non-zero.
+            return Ok((unsafe { NonNull::new_unchecked(pointer) }, 0));
+        }
+
+        if let Some(handle) = self.bump(layout) {
+            return Ok((handle, layout.size()));
+        }
+
+        self.allocate_chunk(layout)?;
+
+        self.bump(layout)
+            .map(|handle| (handle, layout.size()))
+            .ok_or(AllocError)
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  If `handle` points to the last allocation, its memory block can be reclaimed by lowering the cursor.
+        if handle.as_ptr().wrapping_add(layout.size()) == self.cursor.get() {
+            self.cursor.set(handle.as_ptr());
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        //  As an optimization, if `handle` points to the last allocation, growth may actually occur _in place_.
+        if self.is_last(handle, old_layout)
+            && handle.as_ptr().addr() % new_layout.align() == 0
+            && new_layout.size() <= self.end.get().addr() - handle.as_ptr().addr()
+        {
+            self.cursor.set(handle.as_ptr().wrapping_add(new_layout.size()));
+
+            return Ok((handle, new_layout.size()));
+        }
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.relocate(handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        if handle.as_ptr().addr() % new_layout.align() != 0 {
+            //  Safety:
+            //  -   As per pre-conditions.
+            return unsafe { self.relocate(handle, old_layout, new_layout) };
+        }
+
+        //  As an optimization, if `handle` points to the last allocation, the tail of its memory block may be
+        //  reclaimed.
+        if self.is_last(handle, old_layout) {
+            self.cursor.set(handle.as_ptr().wrapping_add(new_layout.size()));
+
+            return Ok((handle, new_layout.size()));
+        }
+
+        Ok((handle, old_layout.size()))
+    }
+}
+
+unsafe impl<A: Allocator> StoreSingle for ArenaStore<A> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        handle
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        handle
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, as chunks are never moved.
+unsafe impl<A: Allocator> StoreStable for ArenaStore<A> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, even if `self` is moved, as chunks are never moved.
+unsafe impl<A: Allocator> StorePinning for ArenaStore<A> {}
+
+//  The chunks are laid out one after the other, from the oldest to the current one, hence the watermark is the offset
+//  of the cursor within the current chunk, and all memory of the previous chunks is used, or at least unavailable.
+impl<A: Allocator> StoreLayoutDump for ArenaStore<A> {
+    fn capacity(&self) -> usize {
+        self.chunks().map(|(_, size)| size).sum()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        let available = self.end.get().addr() - self.cursor.get().addr();
+
+        Some(self.capacity() - available)
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        let current = self.chunk.get();
+        let mut offset = 0;
+
+        //  The chunks are linked from the current one to the oldest one, and the number of chunks is logarithmic in the
+        //  amount of memory allocated, hence walking the list once per chunk is cheap enough.
+        for index in (0..self.chunks().count()).rev() {
+            let Some((chunk, size)) = self.chunks().nth(index) else {
+                break;
+            };
+
+            visitor(Region {
+                offset,
+                size: HEADER_SIZE,
+                kind: RegionKind::Overhead,
+            });
+
+            let used = if Some(chunk) == current {
+                self.cursor.get().addr() - chunk.as_ptr().addr()
+            } else {
+                size
+            };
+
+            if used > HEADER_SIZE {
+                visitor(Region {
+                    offset: offset + HEADER_SIZE,
+                    size: used - HEADER_SIZE,
+                    kind: RegionKind::Used,
+                });
+            }
+
+            if size > used {
+                visitor(Region {
+                    offset: offset + used,
+                    size: size - used,
+                    kind: RegionKind::Free,
+                });
+            }
+
+            offset += size;
+        }
+    }
+}
+
+impl<A: Allocator> fmt::Debug for ArenaStore<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let available = self.end.get().addr() - self.cursor.get().addr();
+
+        f.debug_struct("ArenaStore")
+            .field("chunk_size", &self.chunk_size)
+            .field("available", &available)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Header of each chunk, stored at its very beginning.
+struct ChunkHeader {
+    previous: Option<NonNull<ChunkHeader>>,
+    layout: Layout,
+}
+
+const HEADER_SIZE: usize = mem::size_of::<ChunkHeader>();
+
+impl<A: Allocator> ArenaStore<A> {
+    //  Returns whether `handle` is the last allocated memory block.
+    fn is_last(&self, handle: NonNull<u8>, layout: Layout) -> bool {
+        handle.as_ptr().wrapping_add(layout.size()) == self.cursor.get()
+    }
+
+    //  Returns the chunks, and their usable size, from the current one to the oldest one.
+    fn chunks(&self) -> impl Iterator<Item = (NonNull<ChunkHeader>, usize)> + '_ {
+        let mut next = self.chunk.get();
+
+        iter::from_fn(move || {
+            let chunk = next?;
+
+            //  Safety:
+            //  -   `chunk` points to a live header, as chunks are only deallocated on drop.
+            let header = unsafe { chunk.as_ref() };
+
+            //  The current chunk may be larger than requested, if the allocator returned a larger block of memory.
+            let size = if next == self.chunk.get() {
+                self.end.get().addr() - chunk.as_ptr().addr()
+            } else {
+                header.layout.size()
+            };
+
+            next = header.previous;
+
+            Some((chunk, size))
+        })
+    }
+
+    //  Attempts to carve a memory block fitting `layout` out of the current chunk.
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let (cursor, end) = (self.cursor.get(), self.end.get());
+
+        let aligned = {
+            //  Since `layout.align()` is always a power of 2, aligning to the next multiple of `layout.align()` can be
+            //  done with this one simple trick.
+            let alignment_mask = layout.align() - 1;
+
+            cursor.addr().checked_add(alignment_mask)? & !alignment_mask
+        };
+
+        let new_cursor = aligned.checked_add(layout.size())?;
+
+        if new_cursor > end.addr() {
+            return None;
+        }
+
+        self.cursor.set(cursor.with_addr(new_cursor));
+
+        NonNull::new(cursor.with_addr(aligned))
+    }
+
+    //  Allocates a new chunk, large enough to fit `layout`, and makes it the current chunk.
+    #[inline(never)]
+    fn allocate_chunk(&self, layout: Layout) -> Result<(), AllocError> {
+        let previous = self.chunk.get();
+
+        let minimum_size = match previous {
+            //  Safety:
+            //  -   `chunk` points to a live header, as chunks are only deallocated on drop.
+            Some(chunk) => unsafe { chunk.as_ref().layout.size() }.saturating_mul(2),
+            None => self.chunk_size,
+        };
+
+        //  Accounts for the worst case padding required to align the memory block past the header.
+        let required = HEADER_SIZE
+            .checked_add(layout.align() - 1)
+            .and_then(|n| n.checked_add(layout.size()))
+            .ok_or(AllocError)?;
+
+        let size = cmp::max(minimum_size, required);
+        let align = cmp::max(mem::align_of::<ChunkHeader>(), layout.align());
+
+        let chunk_layout = Layout::from_size_align(size, align).map_err(|_| AllocError)?;
+
+        let memory = self.allocator.allocate(chunk_layout)?;
+
+        let chunk: NonNull<ChunkHeader> = memory.as_non_null_ptr().cast();
+
+        //  Safety:
+        //  -   `chunk` is valid for writes, as it was just allocated.
+        //  -   `chunk` is suitably aligned, as `chunk_layout.align()` is at least that of `ChunkHeader`.
+        unsafe {
+            chunk.as_ptr().write(ChunkHeader {
+                previous,
+                layout: chunk_layout,
+            })
+        };
+
+        let start = memory.as_mut_ptr();
+
+        self.chunk.set(Some(chunk));
+        self.cursor.set(start.wrapping_add(HEADER_SIZE));
+        self.end.set(start.wrapping_add(memory.len()));
+
+        Ok(())
+    }
+
+    //  Slow part of `grow` and `shrink`.
+    //
+    //  #   Safety
+    //
+    //  -   As per the pre-conditions of `grow` and `shrink`.
+    #[inline(never)]
+    unsafe fn relocate(
+        &self,
+        handle: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(NonNull<u8>, usize), AllocError> {
+        let (result, size) = <Self as Store>::allocate(self, new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is valid for reads of `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `result` is valid for writes of `new_layout.size()` bytes, since it was just allocated.
+        //  -   `handle` and `result` are at least 1-byte aligned.
+        //  -   `handle` and `result` do not overlap, since `result` was just allocated, and `handle` is still live.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                handle.as_ptr(),
+                result.as_ptr(),
+                cmp::min(old_layout.size(), new_layout.size()),
+            )
+        };
+
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and fits `old_layout`, as per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, old_layout) };
+
+        Ok((result, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::collection::{LinkedList, StoreVec};
+
+    use super::*;
+
+    #[test]
+    fn chain_chunks() {
+        let store = ArenaStore::with_chunk_size_in(64, Global);
+        let layout = Layout::new::<u64>();
+
+        let handles: Vec<_> = (0..64u64)
+            .map(|i| {
+                let (handle, _) = Store::allocate(&store, layout).unwrap();
+
+                unsafe { Store::resolve(&store, handle).cast::<u64>().as_ptr().write(i) };
+
+                handle
+            })
+            .collect();
+
+        //  Moving the store does not invalidate any pointer.
+        let store = Box::new(store);
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            let value = unsafe { Store::resolve(&*store, handle).cast::<u64>().as_ptr().read() };
+
+            assert_eq!(i as u64, value);
+        }
+    }
+
+    #[test]
+    fn oversized() {
+        let store = ArenaStore::with_chunk_size_in(64, Global);
+
+        let (handle, size) = Store::allocate(&store, Layout::new::<[u64; 64]>()).unwrap();
+
+        assert_eq!(512, size);
+        assert_eq!(0, handle.as_ptr().addr() % 8);
+    }
+
+    #[test]
+    fn grow_shrink_in_place() {
+        let store = ArenaStore::<Global>::default();
+        let (small, large) = (Layout::new::<u32>(), Layout::new::<[u32; 6]>());
+
+        let (handle, _) = Store::allocate(&store, small).unwrap();
+
+        let (grown, size) = unsafe { Store::grow(&store, handle, small, large).unwrap() };
+
+        assert_eq!(handle, grown);
+        assert_eq!(24, size);
+
+        let (shrunk, size) = unsafe { Store::shrink(&store, grown, large, small).unwrap() };
+
+        assert_eq!(handle, shrunk);
+        assert_eq!(4, size);
+
+        unsafe { Store::deallocate(&store, shrunk, small) };
+
+        let (reused, _) = Store::allocate(&store, small).unwrap();
+
+        assert_eq!(handle, reused);
+    }
+
+    #[test]
+    fn collections() {
+        let mut list = LinkedList::new_in(ArenaStore::with_chunk_size_in(128, Global));
+
+        for i in 0..100 {
+            list.try_push_back(i).unwrap();
+        }
+
+        assert_eq!(100, list.len());
+
+        let mut v = StoreVec::<u32, ArenaStore<Global>>::new_in(ArenaStore::with_chunk_size_in(16, Global));
+
+        for i in 0..1000 {
+            v.push(i);
+        }
+
+        assert_eq!(1000, v.len());
+        assert_eq!(Some(&999), v.as_slice().last());
+    }
+
+    #[test]
+    fn layout_dump() {
+        let store = ArenaStore::with_chunk_size_in(64, Global);
+
+        let empty = store.summary();
+
+        assert_eq!((0, Some(0)), (empty.capacity, empty.watermark));
+
+        let layout = Layout::new::<[u8; 32]>();
+
+        for _ in 0..2 {
+            Store::allocate(&store, layout).unwrap();
+        }
+
+        //  The first chunk only fits one allocation, hence the second one is carved out of a second chunk, twice as
+        //  large.
+        let summary = store.summary();
+
+        assert_eq!(64 * 3, summary.capacity);
+        assert_eq!(2 * HEADER_SIZE, summary.overhead_bytes);
+        assert_eq!(64 - HEADER_SIZE + 32, summary.used_bytes);
+        assert_eq!((1, 128 - HEADER_SIZE - 32), (summary.free_regions, summary.free_bytes));
+        assert_eq!(Some(64 + HEADER_SIZE + 32), summary.watermark);
+    }
+} // mod tests
@@ -0,0 +1,520 @@
+//! A "bump allocator" Store, with an atomic watermark so it may be allocated from by multiple threads.
+//!
+//! This store is the thread-safe counterpart of `InlineBumpStore`: since allocation takes `&self`, a single instance
+//! may be shared between threads -- for example in a `ConcurrentVec` -- and each thread may allocate from it without
+//! any lock. The watermark is updated with compare-and-swap loops, and only ever lowered by the deallocation, or
+//! shrinking, of the very last allocation.
+
+use core::{
+    alloc::{AllocError, Layout},
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ptr::{self, Alignment, NonNull},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    interface::{Store, StoreDangling, StoreSingle, StoreStable},
+    store::{
+        layout_dump::{visit_watermarked, Region},
+        BumpCheckpoint, StoreLayoutDump,
+    },
+};
+
+/// An implementation of `Store` providing a single, inline, block of memory, which may be allocated from by multiple
+/// threads.
+///
+/// Generic parameters:
+///
+/// -   `H` is the handle type, it must convertible to and from `usize`.
+/// -   The block of memory is aligned and sized as per `T`.
+pub struct AtomicInlineBumpStore<H, T> {
+    watermark: AtomicUsize,
+    memory: UnsafeCell<MaybeUninit<T>>,
+    _marker: PhantomData<fn(H) -> H>,
+}
+
+impl<H, T> AtomicInlineBumpStore<H, T>
+where
+    H: TryFrom<usize>,
+{
+    fn new() -> Result<Self, AllocError> {
+        //  Any offset within `memory`, or just past its end, must be representable by `H`.
+        let _ = Self::from_offset(Self::memory_layout().size())?;
+
+        let watermark = AtomicUsize::new(0);
+        let memory = UnsafeCell::new(MaybeUninit::uninit());
+        let _marker = PhantomData;
+
+        Ok(Self {
+            watermark,
+            memory,
+            _marker,
+        })
+    }
+}
+
+impl<H, T> AtomicInlineBumpStore<H, T> {
+    /// Returns a checkpoint of the current watermark, to rewind to later.
+    pub fn checkpoint(&self) -> BumpCheckpoint {
+        BumpCheckpoint::new(self.watermark.load(Ordering::Acquire))
+    }
+
+    /// Rewinds the store to `checkpoint`, releasing all the blocks of memory allocated since.
+    ///
+    /// All the handles allocated since `checkpoint` was taken are invalidated, as well as any pointer resolved from
+    /// them. Only rewinding forward, to a checkpoint above the current watermark, is a no-op. A checkpoint taken from
+    /// another store is not told apart, and rewinds this store all the same.
+    pub fn rewind(&mut self, checkpoint: BumpCheckpoint) {
+        let watermark = self.watermark.get_mut();
+
+        if checkpoint.watermark() < *watermark {
+            *watermark = checkpoint.watermark();
+        }
+    }
+
+    /// Resets the store, releasing all the blocks of memory.
+    ///
+    /// All the handles allocated by this store are invalidated, as well as any pointer resolved from them.
+    pub fn reset(&mut self) {
+        self.rewind(BumpCheckpoint::new(0));
+    }
+}
+
+impl<H, T> Default for AtomicInlineBumpStore<H, T>
+where
+    H: TryFrom<usize>,
+{
+    fn default() -> Self {
+        Self::new().expect("Size of `T` to be representable by `H`")
+    }
+}
+
+//  Cannot be const, because TryFrom is not marked #[const_trait].
+unsafe impl<H, T> StoreDangling for AtomicInlineBumpStore<H, T>
+where
+    H: Copy + TryFrom<usize>,
+{
+    type Handle = H;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let layout = Self::memory_layout();
+
+        if alignment.as_usize() > layout.align() {
+            return Err(AllocError);
+        }
+
+        Self::from_offset(alignment.as_usize())
+    }
+}
+
+unsafe impl<H, T> Store for AtomicInlineBumpStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let mut watermark = self.watermark.load(Ordering::Acquire);
+
+        loop {
+            let (result, new_watermark) = Self::compute_offset(watermark, layout)?;
+
+            //  Acquire the writes of any thread which deallocated the memory block, prior to reusing it.
+            match self
+                .watermark
+                .compare_exchange_weak(watermark, new_watermark, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return Ok((result, layout.size())),
+                Err(current) => watermark = current,
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  If `handle` points to the last allocation, its memory block can be reclaimed by lowering the watermark.
+        let offset = Self::into_offset(handle);
+
+        let _ = self
+            .watermark
+            .compare_exchange(offset + layout.size(), offset, Ordering::AcqRel, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        debug_assert!(Self::into_offset(handle) <= Self::memory_layout().size());
+
+        let offset = Self::into_offset(handle);
+        let pointer = self.memory.get() as *mut u8;
+
+        //  Safety:
+        //  -   `offset` is within bounds of `self.memory`, as `handle` was allocated by `self` as per pre-conditions.
+        let pointer = unsafe { pointer.add(offset) };
+
+        //  Safety:
+        //  -   `pointer` is non null as `self` is non null.
+        unsafe { NonNull::new_unchecked(pointer) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        //  As an optimization, if `handle` points to the last allocation, growth may actually occur _in place_.
+        {
+            let offset = Self::into_offset(handle);
+
+            if offset % new_layout.align() == 0
+                && new_layout.align() <= Self::memory_layout().align()
+                && offset + new_layout.size() <= Self::memory_layout().size()
+                && self
+                    .watermark
+                    .compare_exchange(
+                        offset + old_layout.size(),
+                        offset + new_layout.size(),
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+            {
+                return Ok((handle, new_layout.size()));
+            }
+        }
+
+        self.grow_by_relocation(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        //  As an optimization, if `handle` points to the last allocation, the tail of its memory block may be
+        //  reclaimed.
+        let offset = Self::into_offset(handle);
+
+        let reclaimed = self.watermark.compare_exchange(
+            offset + old_layout.size(),
+            offset + new_layout.size(),
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+
+        if reclaimed.is_ok() {
+            return Ok((handle, new_layout.size()));
+        }
+
+        Ok((handle, old_layout.size()))
+    }
+}
+
+unsafe impl<H, T> StoreSingle for AtomicInlineBumpStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    #[inline(always)]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, as long as `self` doesn't move.
+unsafe impl<H, T> StoreStable for AtomicInlineBumpStore<H, T> where H: Copy + TryFrom<usize> + TryInto<usize> {}
+
+//  Safety:
+//  -   The watermark is only ever accessed atomically, and each block of memory is handed out to a single allocation.
+//  -   The memory is never read nor written by the store itself, only through the pointers resolved by its users.
+unsafe impl<H, T> Sync for AtomicInlineBumpStore<H, T> {}
+
+impl<H, T> StoreLayoutDump for AtomicInlineBumpStore<H, T> {
+    fn capacity(&self) -> usize {
+        Self::memory_layout().size()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        Some(self.watermark.load(Ordering::Acquire))
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        let watermark = self.watermark.load(Ordering::Acquire);
+
+        //  The boundaries of the blocks of memory are not tracked, hence all memory below the watermark is used.
+        visit_watermarked(watermark, self.capacity(), visitor);
+    }
+}
+
+impl<H, T> fmt::Debug for AtomicInlineBumpStore<H, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let layout = Layout::new::<T>();
+
+        f.debug_struct("AtomicInlineBumpStore")
+            .field("size", &layout.size())
+            .field("align", &layout.align())
+            .field("watermark", &self.watermark.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+impl<H, T> AtomicInlineBumpStore<H, T> {
+    #[inline(always)]
+    const fn memory_layout() -> Layout {
+        Layout::new::<T>()
+    }
+}
+
+impl<H, T> AtomicInlineBumpStore<H, T>
+where
+    H: TryFrom<usize>,
+{
+    #[inline(always)]
+    fn from_offset(offset: usize) -> Result<H, AllocError> {
+        debug_assert!(offset <= Self::memory_layout().size());
+
+        offset.try_into().map_err(|_| AllocError)
+    }
+}
+
+impl<H, T> AtomicInlineBumpStore<H, T>
+where
+    H: TryInto<usize>,
+{
+    #[inline(always)]
+    fn into_offset(handle: H) -> usize {
+        let offset = handle.try_into();
+
+        debug_assert!(offset.is_ok());
+
+        //  Safety:
+        //  -   `handle` was created from `usize`, hence converting back always succeeds.
+        unsafe { offset.unwrap_unchecked() }
+    }
+}
+
+impl<H, T> AtomicInlineBumpStore<H, T>
+where
+    H: TryFrom<usize>,
+{
+    //  Returns the offset and new watermark of the newly allocated memory block.
+    fn compute_offset(watermark: usize, layout: Layout) -> Result<(H, usize), AllocError> {
+        let memory = Self::memory_layout();
+
+        if layout.align() > memory.align() {
+            //  Even if the memory block was aligned for the current address of `self.memory`, moving `self` would risk
+            //  breaking this alignment.
+
+            return Err(AllocError);
+        }
+
+        let aligned = {
+            //  Since `layout.align()` is always a power of 2, aligning to the next multiple of `layout.align()` can be
+            //  done with this one simple trick.
+            let alignment_mask = layout.align() - 1;
+
+            (watermark + alignment_mask) & !alignment_mask
+        };
+
+        let new_watermark = aligned + layout.size();
+
+        if new_watermark > memory.size() {
+            return Err(AllocError);
+        }
+
+        let aligned = Self::from_offset(aligned)?;
+
+        Ok((aligned, new_watermark))
+    }
+}
+
+impl<H, T> AtomicInlineBumpStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    //  Slow part of `grow`.
+    #[inline(never)]
+    fn grow_by_relocation(&self, handle: H, old_layout: Layout, new_layout: Layout) -> Result<(H, usize), AllocError> {
+        let (result, _) = Store::allocate(self, new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (new, old) = unsafe { (Store::resolve(self, result), Store::resolve(self, handle)) };
+
+        //  Safety:
+        //  -   `old` is valid for `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `new` is valid for `old_layout.size()` bytes, since it is valid for `new_layout.size()` bytes and as per
+        //      pre-conditions `new_layout.size() >= old_layout.size()`.
+        //  -   `old` and `new` are at least 1-byte aligned.
+        //  -   `old` and `new` point to non-overlapping areas, since both are live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), old_layout.size()) };
+
+        Ok((result, new_layout.size()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::collection::ConcurrentVec;
+
+    use super::*;
+
+    type TestStore = AtomicInlineBumpStore<u16, [u64; 1024]>;
+
+    #[test]
+    fn concurrent_allocations() {
+        const THREADS: usize = 4;
+        const ALLOCATIONS: usize = 64;
+
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let handles: Vec<Vec<u16>> = thread::scope(|scope| {
+            let threads: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let store = &store;
+
+                    scope.spawn(move || {
+                        (0..ALLOCATIONS)
+                            .map(|i| {
+                                let (handle, _) = Store::allocate(store, layout).unwrap();
+
+                                //  Safety:
+                                //  -   `handle` was just allocated by `store`, with `layout`.
+                                unsafe {
+                                    Store::resolve(store, handle)
+                                        .as_ptr()
+                                        .cast::<u64>()
+                                        .write((t * 1000 + i) as u64)
+                                };
+
+                                handle
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+
+        for (t, handles) in handles.iter().enumerate() {
+            for (i, handle) in handles.iter().enumerate() {
+                //  Safety:
+                //  -   `handle` was allocated by `store`, and is still valid.
+                let value = unsafe { Store::resolve(&store, *handle).as_ptr().cast::<u64>().read() };
+
+                assert_eq!((t * 1000 + i) as u64, value);
+            }
+        }
+
+        assert_eq!(Some(THREADS * ALLOCATIONS * 8), store.watermark());
+    }
+
+    #[test]
+    fn concurrent_vec() {
+        let v = ConcurrentVec::<u32, TestStore>::new(64);
+
+        thread::scope(|scope| {
+            for t in 0..4 {
+                let v = &v;
+
+                scope.spawn(move || {
+                    for i in 0..16 {
+                        v.push(t * 16 + i).unwrap();
+                    }
+                });
+            }
+        });
+
+        let mut elements = v.as_slice().to_vec();
+        elements.sort_unstable();
+
+        assert!(elements.into_iter().eq(0..64));
+    }
+
+    #[test]
+    fn rewind() {
+        let mut store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (first, _) = Store::allocate(&store, layout).unwrap();
+
+        let checkpoint = store.checkpoint();
+
+        let (second, _) = Store::allocate(&store, layout).unwrap();
+        Store::allocate(&store, layout).unwrap();
+
+        store.rewind(checkpoint);
+
+        let (third, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(second, third);
+
+        store.reset();
+
+        let (fourth, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(first, fourth);
+    }
+} // mod tests
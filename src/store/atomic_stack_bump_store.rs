@@ -0,0 +1,554 @@
+//! A "bump allocator" Store referencing a block, with an atomic watermark so it may be allocated from by multiple
+//! threads.
+//!
+//! This store is the thread-safe counterpart of `StackBumpStore`: the block may be shared between threads, each thread
+//! creating its own stores referencing it, and all stores may allocate from it concurrently without any lock. The
+//! stores are themselves `Send` and `Sync`, hence may also be moved to, or shared with, other threads.
+
+use core::{
+    alloc::{AllocError, Layout},
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr::{self, Alignment, NonNull},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    interface::{Store, StoreDangling, StorePinning, StoreSharing, StoreSingle, StoreStable},
+    store::{
+        layout_dump::{visit_watermarked, Region},
+        BumpCheckpoint, StoreLayoutDump,
+    },
+};
+
+/// The backing block of memory for the store, which may be shared between threads.
+///
+/// Generic parameters:
+///
+/// -   The block of memory is aligned and sized as per `T`.
+pub struct AtomicStackBumpBlock<T> {
+    watermark: AtomicUsize,
+    memory: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T> AtomicStackBumpBlock<T> {
+    /// Creates a new, empty, block.
+    pub fn new() -> Self {
+        let watermark = AtomicUsize::new(0);
+        let memory = UnsafeCell::new(MaybeUninit::uninit());
+
+        Self { watermark, memory }
+    }
+
+    /// Creates a new store referencing this block.
+    pub fn create_store<H>(&self) -> AtomicStackBumpStore<'_, H> {
+        let watermark = &self.watermark;
+
+        let memory = {
+            let length = mem::size_of::<T>();
+            let address = NonNull::from(&self.memory).cast();
+
+            NonNull::slice_from_raw_parts(address, length)
+        };
+
+        let _marker = PhantomData;
+
+        AtomicStackBumpStore {
+            watermark,
+            memory,
+            _marker,
+        }
+    }
+
+    /// Returns a checkpoint of the current watermark, to rewind to later.
+    pub fn checkpoint(&self) -> BumpCheckpoint {
+        BumpCheckpoint::new(self.watermark.load(Ordering::Acquire))
+    }
+
+    /// Rewinds the block to `checkpoint`, releasing all the blocks of memory allocated since.
+    ///
+    /// Since `self` is borrowed mutably, no store referencing this block is alive. All the handles allocated since
+    /// `checkpoint` was taken are invalidated. Only rewinding forward, to a checkpoint above the current watermark, is
+    /// a no-op. A checkpoint taken from another block is not told apart, and rewinds this block all the same.
+    pub fn rewind(&mut self, checkpoint: BumpCheckpoint) {
+        let watermark = self.watermark.get_mut();
+
+        if checkpoint.watermark() < *watermark {
+            *watermark = checkpoint.watermark();
+        }
+    }
+
+    /// Resets the block, releasing all the blocks of memory.
+    ///
+    /// Since `self` is borrowed mutably, no store referencing this block is alive. All the handles allocated from this
+    /// block are invalidated.
+    pub fn reset(&mut self) {
+        self.rewind(BumpCheckpoint::new(0));
+    }
+}
+
+impl<T> Default for AtomicStackBumpBlock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//  Safety:
+//  -   The watermark is only ever accessed atomically, and each block of memory is handed out to a single allocation.
+//  -   The memory is never read nor written by the block itself, only through the pointers resolved by the stores.
+unsafe impl<T> Sync for AtomicStackBumpBlock<T> {}
+
+/// A store instance referencing its block, which may be allocated from by multiple threads.
+///
+/// Generic parameters:
+///
+/// -   `H` is the handle type, it must convertible to and from `usize`.
+pub struct AtomicStackBumpStore<'a, H> {
+    watermark: &'a AtomicUsize,
+    memory: NonNull<[u8]>,
+    _marker: PhantomData<fn(H) -> H>,
+}
+
+//  Cannot be const, because TryFrom is not marked #[const_trait].
+unsafe impl<'a, H> StoreDangling for AtomicStackBumpStore<'a, H>
+where
+    H: Copy + TryFrom<usize>,
+{
+    type Handle = H;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let offset = {
+            //  The block may be less aligned than `alignment`, hence the address, rather than the offset, is aligned.
+            let base = self.memory.as_mut_ptr().addr();
+
+            let alignment_mask = alignment.as_usize() - 1;
+
+            //  As for `NonNull::dangling`, the offset is never 0.
+            ((base + 1 + alignment_mask) & !alignment_mask) - base
+        };
+
+        //  The block may be too small to contain any suitably aligned offset.
+        if offset > self.memory.len() {
+            return Err(AllocError);
+        }
+
+        Self::from_offset(offset)
+    }
+}
+
+unsafe impl<'a, H> Store for AtomicStackBumpStore<'a, H>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let mut watermark = self.watermark.load(Ordering::Acquire);
+
+        loop {
+            let (result, new_watermark) = self.compute_offset(watermark, layout)?;
+
+            //  Acquire the writes of any thread which deallocated the memory block, prior to reusing it.
+            match self
+                .watermark
+                .compare_exchange_weak(watermark, new_watermark, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return Ok((result, layout.size())),
+                Err(current) => watermark = current,
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  If `handle` points to the last allocation, its memory block can be reclaimed by lowering the watermark.
+        let offset = Self::into_offset(handle);
+
+        let _ = self
+            .watermark
+            .compare_exchange(offset + layout.size(), offset, Ordering::AcqRel, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        debug_assert!(Self::into_offset(handle) <= self.memory.len());
+
+        let offset = Self::into_offset(handle);
+        let pointer = self.memory.as_mut_ptr();
+
+        //  Safety:
+        //  -   `offset` is within bounds of `self.memory`, as `handle` was allocated by `self` as per pre-conditions.
+        let pointer = unsafe { pointer.add(offset) };
+
+        //  Safety:
+        //  -   `pointer` is non null as `self` is non null.
+        unsafe { NonNull::new_unchecked(pointer) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        //  As an optimization, if `handle` points to the last allocation, growth may actually occur _in place_.
+        {
+            let offset = Self::into_offset(handle);
+
+            if (self.memory.as_mut_ptr().addr() + offset) % new_layout.align() == 0
+                && offset + new_layout.size() <= self.memory.len()
+                && self
+                    .watermark
+                    .compare_exchange(
+                        offset + old_layout.size(),
+                        offset + new_layout.size(),
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+            {
+                return Ok((handle, new_layout.size()));
+            }
+        }
+
+        self.grow_by_relocation(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        //  As an optimization, if `handle` points to the last allocation, the tail of its memory block may be
+        //  reclaimed.
+        let offset = Self::into_offset(handle);
+
+        let reclaimed = self.watermark.compare_exchange(
+            offset + old_layout.size(),
+            offset + new_layout.size(),
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+
+        if reclaimed.is_ok() {
+            return Ok((handle, new_layout.size()));
+        }
+
+        Ok((handle, old_layout.size()))
+    }
+}
+
+unsafe impl<'a, H> StoreSingle for AtomicStackBumpStore<'a, H>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    #[inline(always)]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address.
+unsafe impl<'a, H> StoreStable for AtomicStackBumpStore<'a, H> where H: Copy + TryFrom<usize> + TryInto<usize> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address.
+unsafe impl<'a, H> StorePinning for AtomicStackBumpStore<'a, H> where H: Copy + TryFrom<usize> + TryInto<usize> {}
+
+/// Safety:
+/// -   All instances referencing the same AtomicStackBumpBlock are fungible.
+unsafe impl<'a, H> StoreSharing for AtomicStackBumpStore<'a, H>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    type SharingError = !;
+
+    fn is_sharing_with(&self, other: &Self) -> bool {
+        self.memory == other.memory
+    }
+
+    fn share(&self) -> Result<Self, Self::SharingError>
+    where
+        Self: Sized,
+    {
+        let watermark = self.watermark;
+        let memory = self.memory;
+        let _marker = PhantomData;
+
+        Ok(Self {
+            watermark,
+            memory,
+            _marker,
+        })
+    }
+}
+
+//  Safety:
+//  -   The store only references an `AtomicStackBumpBlock`, which is `Sync`.
+unsafe impl<'a, H> Send for AtomicStackBumpStore<'a, H> {}
+
+//  Safety:
+//  -   The store only references an `AtomicStackBumpBlock`, which is `Sync`.
+unsafe impl<'a, H> Sync for AtomicStackBumpStore<'a, H> {}
+
+impl<'a, H> AtomicStackBumpStore<'a, H> {
+    /// Returns a checkpoint of the current watermark of the referenced block, to rewind the block to later.
+    ///
+    /// See `AtomicStackBumpBlock::rewind`.
+    pub fn checkpoint(&self) -> BumpCheckpoint {
+        BumpCheckpoint::new(self.watermark.load(Ordering::Acquire))
+    }
+}
+
+impl<'a, H> StoreLayoutDump for AtomicStackBumpStore<'a, H> {
+    fn capacity(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        Some(self.watermark.load(Ordering::Acquire))
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        //  The boundaries of the blocks of memory are not tracked, hence all memory below the watermark is used.
+        visit_watermarked(self.watermark.load(Ordering::Acquire), self.capacity(), visitor);
+    }
+}
+
+impl<'a, H> fmt::Debug for AtomicStackBumpStore<'a, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("AtomicStackBumpStore")
+            .field("watermark", &self.watermark.load(Ordering::Relaxed))
+            .field("memory", &self.memory.len())
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+impl<'a, H> AtomicStackBumpStore<'a, H>
+where
+    H: TryFrom<usize>,
+{
+    #[inline(always)]
+    fn from_offset(offset: usize) -> Result<H, AllocError> {
+        offset.try_into().map_err(|_| AllocError)
+    }
+}
+
+impl<'a, H> AtomicStackBumpStore<'a, H>
+where
+    H: TryInto<usize>,
+{
+    #[inline(always)]
+    fn into_offset(handle: H) -> usize {
+        let offset = handle.try_into();
+
+        debug_assert!(offset.is_ok());
+
+        //  Safety:
+        //  -   `handle` was created from `usize`, hence converting back always succeeds.
+        unsafe { offset.unwrap_unchecked() }
+    }
+}
+
+impl<'a, H> AtomicStackBumpStore<'a, H>
+where
+    H: TryFrom<usize> + TryInto<usize>,
+{
+    //  Returns the offset and new watermark of the newly allocated memory block.
+    fn compute_offset(&self, watermark: usize, layout: Layout) -> Result<(H, usize), AllocError> {
+        let aligned = {
+            //  The block may be less aligned than `layout`, hence the address, rather than the offset, is aligned.
+            let base = self.memory.as_mut_ptr().addr();
+
+            //  Since `layout.align()` is always a power of 2, aligning to the next multiple of `layout.align()` can be
+            //  done with this one simple trick.
+            let alignment_mask = layout.align() - 1;
+
+            ((base + watermark + alignment_mask) & !alignment_mask) - base
+        };
+
+        let new_watermark = aligned + layout.size();
+
+        if new_watermark > self.memory.len() {
+            return Err(AllocError);
+        }
+
+        let aligned = Self::from_offset(aligned)?;
+
+        Ok((aligned, new_watermark))
+    }
+}
+
+impl<'a, H> AtomicStackBumpStore<'a, H>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    //  Slow part of `grow`.
+    #[inline(never)]
+    fn grow_by_relocation(&self, handle: H, old_layout: Layout, new_layout: Layout) -> Result<(H, usize), AllocError> {
+        let (result, _) = Store::allocate(self, new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (new, old) = unsafe { (Store::resolve(self, result), Store::resolve(self, handle)) };
+
+        //  Safety:
+        //  -   `old` is valid for `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `new` is valid for `old_layout.size()` bytes, since it is valid for `new_layout.size()` bytes and as per
+        //      pre-conditions `new_layout.size() >= old_layout.size()`.
+        //  -   `old` and `new` are at least 1-byte aligned.
+        //  -   `old` and `new` point to non-overlapping areas, since both are live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), old_layout.size()) };
+
+        Ok((result, new_layout.size()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::collection::LinkedList;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_lists() {
+        let block = AtomicStackBumpBlock::<[u64; 1024]>::new();
+
+        let lists: Vec<_> = thread::scope(|scope| {
+            let threads: Vec<_> = (0..4)
+                .map(|t| {
+                    let store = block.create_store::<u16>();
+
+                    scope.spawn(move || {
+                        let mut list = LinkedList::<u32, AtomicStackBumpStore<'_, u16>>::new_in(store);
+
+                        for i in 0..32 {
+                            list.try_push_back(t * 32 + i).unwrap();
+                        }
+
+                        list
+                    })
+                })
+                .collect();
+
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+
+        for (t, list) in (0..).zip(lists.iter()) {
+            assert!(list.iter().copied().eq(t * 32..(t + 1) * 32));
+        }
+    }
+
+    #[test]
+    fn rewind() {
+        let mut block = AtomicStackBumpBlock::<[u64; 4]>::new();
+        let layout = Layout::new::<u64>();
+
+        let checkpoint = block.checkpoint();
+
+        for _ in 0..3 {
+            let store = block.create_store::<u8>();
+
+            for _ in 0..4 {
+                Store::allocate(&store, layout).unwrap();
+            }
+
+            Store::allocate(&store, layout).unwrap_err();
+
+            block.rewind(checkpoint);
+        }
+    }
+
+    #[test]
+    fn dangling_larger_than_block() {
+        //  The block is aligned on 8 bytes, as per its watermark, yet only 4 bytes large.
+        let block = AtomicStackBumpBlock::<[u8; 4]>::new();
+        let store = block.create_store::<usize>();
+
+        store.dangling(Alignment::new(4).unwrap()).unwrap();
+        store.dangling(Alignment::new(8).unwrap()).unwrap_err();
+    }
+
+    #[test]
+    fn under_aligned_dangling() {
+        #[repr(align(16))]
+        struct Blocks([AtomicStackBumpBlock<[u8; 16]>; 2]);
+
+        //  The blocks are 24 bytes apart, hence at least one of them is not 16-bytes aligned.
+        let blocks = Blocks([AtomicStackBumpBlock::new(), AtomicStackBumpBlock::new()]);
+
+        let alignment = Alignment::new(16).unwrap();
+
+        for block in &blocks.0 {
+            let store = block.create_store::<usize>();
+
+            let handle = store.dangling(alignment).unwrap();
+
+            //  Safety:
+            //  -   `handle` is a dangling handle of `store`.
+            let pointer = unsafe { Store::resolve(&store, handle) };
+
+            assert_eq!(0, pointer.addr().get() % alignment.as_usize());
+        }
+    }
+} // mod tests
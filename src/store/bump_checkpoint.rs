@@ -0,0 +1,18 @@
+//! A checkpoint of a "bump allocator" Store, to rewind it later.
+
+/// A checkpoint of the watermark of a "bump allocator" store.
+///
+/// Rewinding a store to a checkpoint releases all the blocks of memory allocated since the checkpoint was taken, and
+/// invalidates their handles, as well as all the pointers resolved from those handles.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BumpCheckpoint(usize);
+
+impl BumpCheckpoint {
+    pub(crate) const fn new(watermark: usize) -> Self {
+        Self(watermark)
+    }
+
+    pub(crate) const fn watermark(&self) -> usize {
+        self.0
+    }
+}
@@ -0,0 +1,402 @@
+//! A Store adapter, relocating all its blocks of memory at every opportunity.
+//!
+//! Collections must re-resolve their handles after any call to a store which does not implement `StoreStable`, yet a
+//! store which does not actually move its blocks of memory cannot catch a collection which fails to do so.
+//!
+//! This store physically moves all its live blocks of memory on every call to `allocate`, `deallocate`, `grow`, and
+//! `shrink`, and poisons the bytes of their previous location, so that any collection holding a resolved pointer
+//! across such a call reads garbage -- and hopefully fails fast -- under test.
+
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    array,
+    cell::Cell,
+    cmp, fmt,
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StoreSingle};
+
+/// The byte written over the previous location of each block of memory.
+pub const CHAOS_POISON: u8 = 0xA5;
+
+/// A handle of a `ChaosStore`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ChaosHandle {
+    index: u32,
+    //  Alignment of dangling handles, 0 otherwise.
+    alignment: u32,
+}
+
+/// An adapter of `Allocator`, relocating all its blocks of memory on every call to `allocate`, `deallocate`, `grow`,
+/// and `shrink`.
+///
+/// It does NOT implement `StoreStable`, and is only meant for testing.
+///
+/// Generic parameters:
+///
+/// -   `A` is the underlying allocator.
+/// -   `N` is the maximum number of blocks of memory allocated at any one time.
+pub struct ChaosStore<A: Allocator, const N: usize = 64> {
+    slots: [Cell<Option<Block>>; N],
+    //  The previous location of each block of memory relocated, poisoned, kept until the next relocation so that
+    //  reading from it reads the poison rather than freed memory.
+    quarantine: [Cell<Option<Block>>; N],
+    allocator: A,
+}
+
+impl<A: Allocator, const N: usize> ChaosStore<A, N> {
+    /// Creates a new instance, atop `allocator`.
+    pub fn new_in(allocator: A) -> Self {
+        assert!(N < u32::MAX as usize, "{N} slots cannot be represented");
+
+        let slots = array::from_fn(|_| Cell::new(None));
+        let quarantine = array::from_fn(|_| Cell::new(None));
+
+        Self {
+            slots,
+            quarantine,
+            allocator,
+        }
+    }
+}
+
+impl<A: Allocator + Default, const N: usize> Default for ChaosStore<A, N> {
+    fn default() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+impl<A: Allocator, const N: usize> Drop for ChaosStore<A, N> {
+    fn drop(&mut self) {
+        for cell in self.slots.iter().chain(self.quarantine.iter()) {
+            if let Some(block) = cell.take() {
+                //  Safety:
+                //  -   `block` was allocated by `self.allocator`, and not deallocated since.
+                unsafe { self.allocator.deallocate(block.pointer, block.layout) };
+            }
+        }
+    }
+}
+
+unsafe impl<A: Allocator, const N: usize> StoreDangling for ChaosStore<A, N> {
+    type Handle = ChaosHandle;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let alignment = u32::try_from(alignment.as_usize()).map_err(|_| AllocError)?;
+
+        Ok(ChaosHandle {
+            index: u32::MAX,
+            alignment,
+        })
+    }
+}
+
+unsafe impl<A: Allocator, const N: usize> Store for ChaosStore<A, N> {
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.alignment != 0 {
+            let pointer = ptr::invalid_mut(handle.alignment as usize);
+
+            //  Safety:
+            //  -   Non-null, since `handle.alignment` is non-zero.
+            return unsafe { NonNull::new_unchecked(pointer) };
+        }
+
+        let block = self.slots[handle.index as usize].get();
+
+        debug_assert!(block.is_some(), "{handle:?} is not live");
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions, hence its slot is occupied.
+        unsafe { block.unwrap_unchecked() }.pointer
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        self.relocate_all();
+
+        let index = self
+            .slots
+            .iter()
+            .position(|slot| slot.get().is_none())
+            .ok_or(AllocError)?;
+
+        let pointer = self.allocator.allocate(layout)?.as_non_null_ptr();
+
+        self.slots[index].set(Some(Block { pointer, layout }));
+
+        let handle = ChaosHandle {
+            index: index as u32,
+            alignment: 0,
+        };
+
+        Ok((handle, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        debug_assert!(
+            self.slots[handle.index as usize].get().is_some(),
+            "{handle:?} is not live"
+        );
+
+        if let Some(block) = self.slots[handle.index as usize].take() {
+            debug_assert!(block.layout.size() >= layout.size());
+
+            self.quarantine(handle.index as usize, block);
+        }
+
+        self.relocate_all();
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.resize(handle, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.resize(handle, new_layout) }
+    }
+}
+
+unsafe impl<A: Allocator, const N: usize> StoreSingle for ChaosStore<A, N> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+impl<A: Allocator, const N: usize> fmt::Debug for ChaosStore<A, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let live = self.slots.iter().filter(|slot| slot.get().is_some()).count();
+
+        f.debug_struct("ChaosStore")
+            .field("slots", &N)
+            .field("live", &live)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+#[derive(Clone, Copy)]
+struct Block {
+    pointer: NonNull<u8>,
+    layout: Layout,
+}
+
+impl<A: Allocator, const N: usize> ChaosStore<A, N> {
+    //  Moves every live block of memory to a new location.
+    //
+    //  A block of memory which cannot be moved, for lack of memory, stays where it is.
+    fn relocate_all(&self) {
+        for index in 0..N {
+            let Some(block) = self.slots[index].get() else {
+                continue;
+            };
+
+            let Ok(new) = self.move_block(block, block.layout) else {
+                continue;
+            };
+
+            self.slots[index].set(Some(new));
+            self.quarantine(index, block);
+        }
+    }
+
+    //  Moves `block` to a new location, fitting `layout`, without releasing its current location.
+    fn move_block(&self, block: Block, layout: Layout) -> Result<Block, AllocError> {
+        let pointer = self.allocator.allocate(layout)?.as_non_null_ptr();
+
+        //  Safety:
+        //  -   `block.pointer` is valid for reads of `block.layout.size()` bytes, as it is live.
+        //  -   `pointer` is valid for writes of `layout.size()` bytes, as it was just allocated.
+        //  -   `block.pointer` and `pointer` do not overlap, as `pointer` was just allocated.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                block.pointer.as_ptr(),
+                pointer.as_ptr(),
+                cmp::min(block.layout.size(), layout.size()),
+            )
+        };
+
+        Ok(Block { pointer, layout })
+    }
+
+    //  Poisons `block`, and places it in quarantine, releasing the block previously in quarantine, if any.
+    fn quarantine(&self, index: usize, block: Block) {
+        //  Safety:
+        //  -   `block.pointer` is valid for writes of `block.layout.size()` bytes, as it was not deallocated yet.
+        unsafe { ptr::write_bytes(block.pointer.as_ptr(), CHAOS_POISON, block.layout.size()) };
+
+        if let Some(previous) = self.quarantine[index].replace(Some(block)) {
+            //  Safety:
+            //  -   `previous` was allocated by `self.allocator`, and not deallocated since.
+            unsafe { self.allocator.deallocate(previous.pointer, previous.layout) };
+        }
+    }
+
+    //  Moves the block of memory associated to `handle` to a new location, fitting `layout`, and relocates all others.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must be valid.
+    unsafe fn resize(&self, handle: ChaosHandle, layout: Layout) -> Result<(ChaosHandle, usize), AllocError> {
+        let index = handle.index as usize;
+
+        debug_assert!(self.slots[index].get().is_some(), "{handle:?} is not live");
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions, hence its slot is occupied.
+        let block = unsafe { self.slots[index].get().unwrap_unchecked() };
+
+        let new = self.move_block(block, layout)?;
+
+        self.slots[index].set(Some(new));
+        self.quarantine(index, block);
+
+        self.relocate_all();
+
+        Ok((handle, layout.size()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::collection::{LinkedList, StoreBox, StoreVec};
+
+    use super::*;
+
+    type TestStore = ChaosStore<Global>;
+
+    #[test]
+    fn relocates() {
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (handle, _) = Store::allocate(&store, layout).unwrap();
+
+        //  Safety:
+        //  -   `handle` was just allocated by `store`.
+        let pointer = unsafe { Store::resolve(&store, handle) }.cast::<u64>();
+
+        //  Safety:
+        //  -   `pointer` is valid for writes of a `u64`, as it was allocated with `layout`.
+        unsafe { pointer.as_ptr().write(42) };
+
+        let (other, _) = Store::allocate(&store, layout).unwrap();
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        let moved = unsafe { Store::resolve(&store, handle) }.cast::<u64>();
+
+        assert_ne!(pointer, moved);
+
+        //  Safety:
+        //  -   `moved` is valid for reads of a `u64`, as it was just resolved from `handle`.
+        assert_eq!(42, unsafe { moved.as_ptr().read() });
+
+        //  Safety:
+        //  -   `pointer` is still valid for reads of 8 bytes, as the previous location is kept in quarantine until the
+        //      next relocation.
+        assert_eq!([CHAOS_POISON; 8], unsafe { pointer.cast::<[u8; 8]>().as_ptr().read() });
+
+        //  Safety:
+        //  -   `other` and `handle` were allocated by `store`, with `layout`, and are still valid.
+        unsafe {
+            Store::deallocate(&store, other, layout);
+            Store::deallocate(&store, handle, layout);
+        }
+    }
+
+    #[test]
+    fn collections() {
+        let mut v = StoreVec::<String, TestStore>::new();
+
+        for i in 0..50 {
+            v.push(i.to_string());
+        }
+
+        assert!(v.as_slice().iter().enumerate().all(|(i, s)| *s == i.to_string()));
+
+        let boxed = StoreBox::<String, TestStore>::new("Hello".to_string());
+
+        assert_eq!("Hello", &*boxed);
+
+        let mut list = LinkedList::<String, TestStore>::new();
+
+        for i in 0..10 {
+            list.try_push_back(i.to_string()).unwrap();
+        }
+
+        for i in 0..10 {
+            assert_eq!(Some(i.to_string()), list.pop_front());
+        }
+    }
+} // mod tests
@@ -0,0 +1,517 @@
+//! A Store adapter, validating every call against the pre-conditions of the `Store` and `StoreSingle` traits.
+//!
+//! This store is meant for debugging, and testing, collections: it records every live handle along with its `Layout`,
+//! and panics with a clear message whenever a pre-condition is violated, such as deallocating a handle twice, or
+//! deallocating a handle with a layout which does not fit. It also panics on drop if any handle was leaked.
+
+use core::{
+    alloc::{AllocError, Layout},
+    array,
+    cell::Cell,
+    fmt,
+    ptr::{Alignment, NonNull},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable};
+
+/// A handle of a `CheckedStore`, wrapping the handle of the underlying store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CheckedHandle<H> {
+    handle: H,
+    //  Identifier of the store which allocated the handle.
+    store: u32,
+    //  Unique serial number of the allocation, or `DANGLING`.
+    serial: u64,
+}
+
+/// An adapter of `Store`, validating every call against the pre-conditions of the `Store` and `StoreSingle` traits.
+///
+/// Generic parameters:
+///
+/// -   `S` is the underlying store.
+/// -   `N` is the maximum number of live handles at any one time, allocations fail beyond.
+///
+/// #   Panics
+///
+/// On any violation of the pre-conditions of the `Store` and `StoreSingle` traits it can detect, and in
+/// `assert_no_leaks` if any handle is still live.
+///
+/// With the `std` feature, leaks are also reported on drop, unless the thread is already panicking. Without it, there
+/// is no telling whether the thread is unwinding, and a panic on drop would then abort, hence leaks are only reported
+/// by `assert_no_leaks`.
+pub struct CheckedStore<S: Store, const N: usize = 64> {
+    id: u32,
+    next_serial: Cell<u64>,
+    //  Set once a violation was reported, to avoid reporting leaks while unwinding.
+    poisoned: Cell<bool>,
+    entries: [Cell<Option<Entry>>; N],
+    store: S,
+}
+
+impl<S: Store, const N: usize> CheckedStore<S, N> {
+    /// Creates a new instance, atop `store`.
+    pub fn new_in(store: S) -> Self {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let next_serial = Cell::new(0);
+        let poisoned = Cell::new(false);
+        let entries = array::from_fn(|_| Cell::new(None));
+
+        Self {
+            id,
+            next_serial,
+            poisoned,
+            entries,
+            store,
+        }
+    }
+
+    /// Returns the number of live handles.
+    pub fn live(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.get().is_some()).count()
+    }
+
+    /// Asserts that no handle is live.
+    ///
+    /// #   Panics
+    ///
+    /// If any handle is still live.
+    #[track_caller]
+    pub fn assert_no_leaks(&self) {
+        let live = self.live();
+
+        if live == 0 {
+            return;
+        }
+
+        let first = self.entries.iter().find_map(Cell::get);
+
+        if let Some(first) = first {
+            self.violation(format_args!(
+                "{live} live handle(s), first allocated as #{} with {:?}",
+                first.serial, first.layout
+            ));
+        }
+    }
+}
+
+impl<S: Store + Default, const N: usize> Default for CheckedStore<S, N> {
+    fn default() -> Self {
+        Self::new_in(S::default())
+    }
+}
+
+impl<S: Store, const N: usize> Drop for CheckedStore<S, N> {
+    fn drop(&mut self) {
+        if self.poisoned.get() {
+            return;
+        }
+
+        //  A collection panicking mid-operation may leak its handles, reporting them would panic while unwinding, and
+        //  abort. Only `std` can tell whether the thread is unwinding.
+        #[cfg(any(test, feature = "std"))]
+        if !std::thread::panicking() {
+            self.assert_no_leaks();
+        }
+    }
+}
+
+unsafe impl<S: Store, const N: usize> StoreDangling for CheckedStore<S, N> {
+    type Handle = CheckedHandle<S::Handle>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let handle = self.store.dangling(alignment)?;
+
+        Ok(CheckedHandle {
+            handle,
+            store: self.id,
+            serial: DANGLING,
+        })
+    }
+}
+
+unsafe impl<S: Store, const N: usize> Store for CheckedStore<S, N> {
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.serial != DANGLING || handle.store != self.id {
+            self.lookup(handle, "resolve");
+        }
+
+        //  Safety:
+        //  -   `handle.handle` was allocated by `self.store`, and is still valid, as checked.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let Some(index) = self.entries.iter().position(|entry| entry.get().is_none()) else {
+            return Err(AllocError);
+        };
+
+        let (handle, size) = self.store.allocate(layout)?;
+
+        Ok((self.register(index, handle, layout, size), size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        let index = self.lookup(handle, "deallocate");
+
+        self.check_fit(index, layout, "deallocate");
+
+        self.entries[index].set(None);
+
+        //  Safety:
+        //  -   `handle.handle` was allocated by `self.store`, and is still valid, as checked.
+        //  -   `layout` fits, as checked.
+        unsafe { self.store.deallocate(handle.handle, layout) };
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        let index = self.lookup(handle, "grow");
+
+        self.check_fit(index, old_layout, "grow");
+
+        if new_layout.size() < old_layout.size() {
+            self.violation(format_args!(
+                "grow called with {new_layout:?} smaller than {old_layout:?}"
+            ));
+        }
+
+        //  Safety:
+        //  -   `handle.handle` was allocated by `self.store`, and is still valid, as checked.
+        //  -   `old_layout` fits, as checked.
+        //  -   `new_layout.size()` is greater than or equal to `old_layout.size()`, as checked.
+        let (inner, size) = unsafe { self.store.grow(handle.handle, old_layout, new_layout)? };
+
+        Ok((self.register(index, inner, new_layout, size), size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        let index = self.lookup(handle, "shrink");
+
+        self.check_fit(index, old_layout, "shrink");
+
+        if new_layout.size() > old_layout.size() {
+            self.violation(format_args!(
+                "shrink called with {new_layout:?} greater than {old_layout:?}"
+            ));
+        }
+
+        //  Safety:
+        //  -   `handle.handle` was allocated by `self.store`, and is still valid, as checked.
+        //  -   `old_layout` fits, as checked.
+        //  -   `new_layout.size()` is smaller than or equal to `old_layout.size()`, as checked.
+        let (inner, size) = unsafe { self.store.shrink(handle.handle, old_layout, new_layout)? };
+
+        Ok((self.register(index, inner, new_layout, size), size))
+    }
+}
+
+unsafe impl<S: Store, const N: usize> StoreSingle for CheckedStore<S, N> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: Store + StoreStable, const N: usize> StoreStable for CheckedStore<S, N> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: Store + StorePinning, const N: usize> StorePinning for CheckedStore<S, N> {}
+
+impl<S: Store + fmt::Debug, const N: usize> fmt::Debug for CheckedStore<S, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("CheckedStore")
+            .field("live", &self.live())
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Serial number of dangling handles.
+const DANGLING: u64 = u64::MAX;
+
+//  Record of a live handle.
+#[derive(Clone, Copy)]
+struct Entry {
+    serial: u64,
+    //  Layout requested.
+    layout: Layout,
+    //  Size returned.
+    size: usize,
+}
+
+impl<S: Store, const N: usize> CheckedStore<S, N> {
+    //  Reports a violation.
+    #[cold]
+    #[track_caller]
+    fn violation(&self, message: fmt::Arguments<'_>) -> ! {
+        self.poisoned.set(true);
+
+        panic!("CheckedStore: {message}")
+    }
+
+    //  Registers a new live handle, at `index`.
+    fn register(&self, index: usize, handle: S::Handle, layout: Layout, size: usize) -> CheckedHandle<S::Handle> {
+        let serial = self.next_serial.get();
+        self.next_serial.set(serial + 1);
+
+        self.entries[index].set(Some(Entry { serial, layout, size }));
+
+        CheckedHandle {
+            handle,
+            store: self.id,
+            serial,
+        }
+    }
+
+    //  Returns the index of the entry of `handle`.
+    //
+    //  #   Panics
+    //
+    //  If `handle` is not live.
+    #[track_caller]
+    fn lookup(&self, handle: CheckedHandle<S::Handle>, operation: &str) -> usize {
+        if handle.store != self.id {
+            self.violation(format_args!(
+                "{operation} called with a handle from a different store (#{} instead of #{})",
+                handle.store, self.id
+            ));
+        }
+
+        if handle.serial == DANGLING {
+            self.violation(format_args!("{operation} called with a dangling handle"));
+        }
+
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.get().map_or(false, |entry| entry.serial == handle.serial));
+
+        let Some(index) = index else {
+            self.violation(format_args!(
+                "{operation} called with handle #{}, already deallocated or invalidated",
+                handle.serial
+            ));
+        };
+
+        index
+    }
+
+    //  Checks that `layout` fits the block of memory of the entry at `index`.
+    //
+    //  #   Panics
+    //
+    //  If `layout` does not fit.
+    #[track_caller]
+    fn check_fit(&self, index: usize, layout: Layout, operation: &str) {
+        let Some(entry) = self.entries[index].get() else {
+            return;
+        };
+
+        if layout.align() != entry.layout.align() || layout.size() < entry.layout.size() || layout.size() > entry.size {
+            self.violation(format_args!(
+                "{operation} called on handle #{} with {layout:?}, which does not fit {:?} (of size {})",
+                entry.serial, entry.layout, entry.size
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::collection::{LinkedList, SkipList, StoreBox, StoreVec};
+
+    use super::*;
+
+    type TestStore = CheckedStore<Global>;
+
+    #[test]
+    fn collections() {
+        let mut v = StoreVec::<String, TestStore>::new();
+
+        for i in 0..100 {
+            v.push(i.to_string());
+        }
+
+        drop(v);
+
+        let mut list = LinkedList::<String, TestStore>::new();
+
+        for i in 0..10 {
+            list.try_push_back(i.to_string()).unwrap();
+            list.try_push_front(i.to_string()).unwrap();
+        }
+
+        while list.pop_front().is_some() {}
+
+        drop(list);
+
+        let mut skip = SkipList::<u32, String, TestStore>::new();
+
+        for i in 0..10 {
+            skip.insert(i, i.to_string());
+        }
+
+        drop(skip);
+
+        let boxed = StoreBox::<[u32; 4], TestStore>::new([1, 2, 3, 4]);
+
+        drop(boxed);
+    }
+
+    #[test]
+    #[should_panic(expected = "deallocate called with handle #0, already deallocated or invalidated")]
+    fn double_deallocate() {
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (handle, _) = Store::allocate(&store, layout).unwrap();
+
+        unsafe { Store::deallocate(&store, handle, layout) };
+        unsafe { Store::deallocate(&store, handle, layout) };
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit")]
+    fn layout_mismatch() {
+        let store = TestStore::default();
+
+        let (handle, _) = Store::allocate(&store, Layout::new::<u64>()).unwrap();
+
+        unsafe { Store::deallocate(&store, handle, Layout::new::<[u64; 2]>()) };
+    }
+
+    #[test]
+    #[should_panic(expected = "grow called with")]
+    fn grow_smaller() {
+        let store = TestStore::default();
+
+        let (handle, _) = Store::allocate(&store, Layout::new::<u64>()).unwrap();
+
+        let _ = unsafe { Store::grow(&store, handle, Layout::new::<u64>(), Layout::new::<u32>()) };
+    }
+
+    #[test]
+    #[should_panic(expected = "resolve called with handle #0, already deallocated or invalidated")]
+    fn resolve_freed() {
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (handle, _) = Store::allocate(&store, layout).unwrap();
+
+        unsafe { Store::deallocate(&store, handle, layout) };
+        unsafe { Store::resolve(&store, handle) };
+    }
+
+    #[test]
+    #[should_panic(expected = "from a different store")]
+    fn different_store() {
+        let (first, second) = (TestStore::default(), TestStore::default());
+
+        let layout = Layout::new::<u64>();
+
+        let (handle, _) = Store::allocate(&first, layout).unwrap();
+
+        unsafe { Store::deallocate(&first, handle, layout) };
+        unsafe { Store::resolve(&second, handle) };
+    }
+
+    #[test]
+    #[should_panic(expected = "1 live handle(s), first allocated as #0")]
+    fn leak() {
+        let store = TestStore::default();
+
+        Store::allocate(&store, Layout::new::<u64>()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "unrelated")]
+    fn leak_while_unwinding() {
+        let store = TestStore::default();
+
+        Store::allocate(&store, Layout::new::<u64>()).unwrap();
+
+        panic!("unrelated");
+    }
+
+    #[test]
+    #[should_panic(expected = "2 live handle(s)")]
+    fn assert_no_leaks() {
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (handle, _) = Store::allocate(&store, layout).unwrap();
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `layout`, and is still valid.
+        unsafe { Store::deallocate(&store, handle, layout) };
+
+        store.assert_no_leaks();
+
+        Store::allocate(&store, layout).unwrap();
+        Store::allocate(&store, layout).unwrap();
+
+        store.assert_no_leaks();
+    }
+} // mod tests
@@ -0,0 +1,1010 @@
+//! A conformance test suite, checking implementations against the contracts of the `Store` traits.
+//!
+//! Each function creates fresh stores with `make`, exercises them, and panics with a message naming the violated
+//! guarantee on the first violation it detects.
+//!
+//! Allocation failures are permitted by the contracts, hence any check which cannot obtain the memory it requires is
+//! silently skipped: a store with a small capacity passes as long as it behaves correctly within its capacity. Each
+//! function still panics if not a single allocation, or dangling handle, succeeded, as nothing would have been checked
+//! then.
+//!
+//! ```
+//! #![feature(allocator_api)]
+//!
+//! use std::alloc::Global;
+//!
+//! use storage::store::{conformance, ArenaStore};
+//!
+//! conformance::check_store(|| ArenaStore::new_in(Global));
+//! conformance::check_store_stable(|| ArenaStore::new_in(Global));
+//! conformance::check_store_pinning(|| ArenaStore::new_in(Global));
+//! ```
+
+use core::{
+    alloc::{AllocError, Layout},
+    mem,
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::interface::{Store, StorePinning, StoreSharing, StoreSingle, StoreStable};
+
+/// Checks the contract of `Store`, and `StoreDangling`.
+///
+/// #   Panics
+///
+/// If any violation of the contract is detected.
+#[track_caller]
+pub fn check_store<S: Store>(make: impl Fn() -> S) {
+    check_operations("check_store", || Shared(make()));
+
+    check_disjoint(&make());
+}
+
+/// Checks the contract of `StoreSingle`, and `StoreDangling`.
+///
+/// #   Panics
+///
+/// If any violation of the contract is detected.
+#[track_caller]
+pub fn check_store_single<S: StoreSingle>(make: impl Fn() -> S) {
+    check_operations("check_store_single", || Exclusive(make()));
+}
+
+/// Checks the guarantees of `StoreStable`: a handle resolves to the same block of memory, regardless of the operations
+/// performed on other handles.
+///
+/// #   Panics
+///
+/// If any violation of the guarantees is detected.
+#[track_caller]
+pub fn check_store_stable<S: Store + StoreStable>(make: impl Fn() -> S) {
+    let store = make();
+    let layout = Layout::new::<[u64; 2]>();
+
+    let Ok((handle, _)) = store.allocate(layout) else {
+        nothing_checked("check_store_stable", "allocation");
+    };
+
+    //  Safety:
+    //  -   `handle` was just allocated by `store`, with `layout`.
+    let pointer = unsafe { store.resolve(handle) };
+
+    //  Safety:
+    //  -   `pointer` points to a block of memory of at least `layout.size()` bytes.
+    unsafe { fill(pointer, layout.size(), SEED) };
+
+    let mut others = [None; 4];
+
+    for other in &mut others {
+        *other = store.allocate(layout).ok().map(|(handle, _)| handle);
+
+        //  Safety:
+        //  -   `handle` is still valid, as no operation was performed on it.
+        unsafe { check_same("allocate", pointer, store.resolve(handle)) };
+    }
+
+    for other in others.iter_mut().step_by(2) {
+        let Some(other) = other.take() else { continue };
+
+        //  Safety:
+        //  -   `other` was allocated by `store`, with `layout`.
+        unsafe { store.deallocate(other, layout) };
+
+        //  Safety:
+        //  -   `handle` is still valid, as no operation was performed on it.
+        unsafe { check_same("deallocate", pointer, store.resolve(handle)) };
+    }
+
+    for other in others.iter_mut().skip(1).step_by(2) {
+        let Some(current) = *other else { continue };
+
+        let new_layout = Layout::new::<[u64; 8]>();
+
+        //  Safety:
+        //  -   `current` was allocated by `store`, with `layout`.
+        //  -   `new_layout` is larger than `layout`.
+        let grown = unsafe { store.grow(current, layout, new_layout) };
+
+        //  Safety:
+        //  -   `handle` is still valid, as no operation was performed on it.
+        unsafe { check_same("grow", pointer, store.resolve(handle)) };
+
+        *other = None;
+
+        match grown {
+            //  Safety:
+            //  -   `current` was grown by `store`, to `new_layout`.
+            Ok((current, _)) => unsafe { store.deallocate(current, new_layout) },
+            //  Safety:
+            //  -   `current` is still valid, as `grow` failed.
+            Err(_) => unsafe { store.deallocate(current, layout) },
+        }
+
+        //  Safety:
+        //  -   `handle` is still valid, as no operation was performed on it.
+        unsafe { check_same("deallocate", pointer, store.resolve(handle)) };
+    }
+
+    //  Safety:
+    //  -   `pointer` is still valid, as per the guarantees of `StoreStable`.
+    unsafe { check_pattern("StoreStable", pointer, layout.size(), SEED) };
+
+    //  Safety:
+    //  -   `handle` was allocated by `store`, with `layout`.
+    unsafe { store.deallocate(handle, layout) };
+}
+
+/// Checks the guarantees of `StorePinning`: a handle resolves to the same block of memory, even after the store moved.
+///
+/// #   Panics
+///
+/// If any violation of the guarantees is detected.
+#[track_caller]
+pub fn check_store_pinning<S: Store + StorePinning>(make: impl Fn() -> S) {
+    check_store_stable(&make);
+
+    let store = make();
+    let layout = Layout::new::<[u64; 2]>();
+
+    let Ok((handle, _)) = store.allocate(layout) else {
+        nothing_checked("check_store_pinning", "allocation");
+    };
+
+    //  Safety:
+    //  -   `handle` was just allocated by `store`, with `layout`.
+    let pointer = unsafe { store.resolve(handle) };
+
+    //  Safety:
+    //  -   `pointer` points to a block of memory of at least `layout.size()` bytes.
+    unsafe { fill(pointer, layout.size(), SEED) };
+
+    //  Move the store around, through a different stack slot.
+    let mut moved = [Some(store), None];
+    moved.swap(0, 1);
+
+    let Some(store) = mem::take(&mut moved[1]) else {
+        unreachable!()
+    };
+
+    //  Safety:
+    //  -   `handle` is still valid, as no operation was performed on it.
+    unsafe { check_same("move", pointer, store.resolve(handle)) };
+
+    //  Safety:
+    //  -   `pointer` is still valid, as per the guarantees of `StorePinning`.
+    unsafe { check_pattern("StorePinning", pointer, layout.size(), SEED) };
+
+    //  Safety:
+    //  -   `handle` was allocated by `store`, with `layout`.
+    unsafe { store.deallocate(handle, layout) };
+}
+
+/// Checks the guarantees of `StoreSharing`: a handle may be used with any part of a sharing set.
+///
+/// #   Panics
+///
+/// If any violation of the guarantees is detected.
+#[track_caller]
+pub fn check_store_sharing<S: Store + StoreSharing>(make: impl Fn() -> S) {
+    check_store_pinning(&make);
+
+    let store = make();
+
+    let Ok(shared) = store.share() else {
+        nothing_checked("check_store_sharing", "share");
+    };
+
+    assert!(
+        store.is_sharing_with(&store),
+        "StoreSharing violation: a store is not sharing with itself"
+    );
+
+    assert!(
+        store.is_sharing_with(&shared) && shared.is_sharing_with(&store),
+        "StoreSharing violation: a store is not sharing with its share"
+    );
+
+    let layout = Layout::new::<[u64; 2]>();
+
+    let Ok((handle, _)) = store.allocate(layout) else {
+        nothing_checked("check_store_sharing", "allocation");
+    };
+
+    //  Safety:
+    //  -   `handle` was just allocated by `store`, with `layout`.
+    let pointer = unsafe { store.resolve(handle) };
+
+    //  Safety:
+    //  -   `pointer` points to a block of memory of at least `layout.size()` bytes.
+    unsafe { fill(pointer, layout.size(), SEED) };
+
+    //  Safety:
+    //  -   `handle` was allocated by a part of the sharing set of `shared`.
+    unsafe { check_same("share", pointer, shared.resolve(handle)) };
+
+    let new_layout = Layout::new::<[u64; 4]>();
+
+    //  Safety:
+    //  -   `handle` was allocated by a part of the sharing set of `shared`, with `layout`.
+    //  -   `new_layout` is larger than `layout`.
+    match unsafe { shared.grow(handle, layout, new_layout) } {
+        Ok((handle, _)) => {
+            //  Safety:
+            //  -   `handle` was just grown by a part of the sharing set of `store`, to `new_layout`.
+            unsafe { check_pattern("share", store.resolve(handle), layout.size(), SEED) };
+
+            //  Safety:
+            //  -   `handle` was grown by a part of the sharing set of `store`, to `new_layout`.
+            unsafe { store.deallocate(handle, new_layout) };
+        }
+        //  Safety:
+        //  -   `handle` is still valid, as `grow` failed.
+        Err(_) => unsafe { shared.deallocate(handle, layout) },
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Seed of the patterns written.
+const SEED: u8 = 0x5A;
+
+//  Layouts allocated.
+const LAYOUTS: [Layout; 8] = [
+    layout(0, 1),
+    layout(1, 1),
+    layout(3, 1),
+    layout(8, 8),
+    layout(12, 4),
+    layout(24, 8),
+    layout(64, 16),
+    layout(100, 2),
+];
+
+//  Pairs of (smaller, larger) layouts, used for grows and shrinks.
+const RESIZES: [(Layout, Layout); 5] = [
+    (layout(0, 1), layout(8, 1)),
+    (layout(1, 1), layout(16, 1)),
+    (layout(8, 8), layout(8, 8)),
+    (layout(8, 8), layout(40, 8)),
+    (layout(12, 4), layout(100, 4)),
+];
+
+//  Alignments of dangling handles, within the capacity of the stores tested.
+const ALIGNMENTS: [usize; 8] = [1, 2, 4, 8, 16, 64, 256, 1024];
+
+const fn layout(size: usize, align: usize) -> Layout {
+    //  Safety:
+    //  -   All alignments used are powers of 2, and all sizes are small.
+    unsafe { Layout::from_size_align_unchecked(size, align) }
+}
+
+//  Fails `check`, as no `operation` succeeded: since failures are skipped, a store failing them all would otherwise
+//  pass.
+#[track_caller]
+fn nothing_checked(check: &str, operation: &str) -> ! {
+    panic!("{check}: no {operation} succeeded, hence nothing was checked")
+}
+
+//  Operations of either `Store` or `StoreSingle`, checked by `check_operations`.
+//
+//  The pre-conditions of each method are those of the matching method of `Store` and `StoreSingle`.
+trait Operations {
+    type Handle: Copy;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError>;
+
+    unsafe fn resolve(&mut self, handle: Self::Handle) -> NonNull<u8>;
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError>;
+
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError>;
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout);
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError>;
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError>;
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError>;
+}
+
+//  Operations of a `Store`, through `&self`.
+struct Shared<S>(S);
+
+impl<S: Store> Operations for Shared<S> {
+    type Handle = S::Handle;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        self.0.dangling(alignment)
+    }
+
+    unsafe fn resolve(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Store::resolve(&self.0, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        Store::allocate(&self.0, layout)
+    }
+
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        Store::allocate_zeroed(&self.0, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Store::deallocate(&self.0, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Store::grow(&self.0, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Store::grow_zeroed(&self.0, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Store::shrink(&self.0, handle, old_layout, new_layout) }
+    }
+}
+
+//  Operations of a `StoreSingle`, through `&mut self`.
+struct Exclusive<S>(S);
+
+impl<S: StoreSingle> Operations for Exclusive<S> {
+    type Handle = S::Handle;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        self.0.dangling(alignment)
+    }
+
+    unsafe fn resolve(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { StoreSingle::resolve_mut(&mut self.0, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        StoreSingle::allocate(&mut self.0, layout)
+    }
+
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        StoreSingle::allocate_zeroed(&mut self.0, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { StoreSingle::deallocate(&mut self.0, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { StoreSingle::grow(&mut self.0, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { StoreSingle::grow_zeroed(&mut self.0, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { StoreSingle::shrink(&mut self.0, handle, old_layout, new_layout) }
+    }
+}
+
+//  Checks the contract of `Store` or `StoreSingle`, and `StoreDangling`, through `Operations`.
+#[track_caller]
+fn check_operations<O: Operations>(check: &str, make: impl Fn() -> O) {
+    check_dangling(check, &mut make());
+
+    let mut allocated = 0;
+
+    for layout in LAYOUTS {
+        let mut store = make();
+
+        if let Ok((handle, size)) = store.allocate(layout) {
+            allocated += 1;
+
+            //  Safety:
+            //  -   `handle` was just allocated by `store`, with `layout`.
+            unsafe { check_block("allocate", store.resolve(handle), layout, size) };
+
+            //  Safety:
+            //  -   `handle` was allocated by `store`, with `layout`.
+            unsafe { store.deallocate(handle, layout) };
+        }
+    }
+
+    for layout in LAYOUTS {
+        let mut store = make();
+
+        //  Dirty the memory first, in the hope that it is reused by `allocate_zeroed`.
+        if let Ok((handle, size)) = store.allocate(layout) {
+            allocated += 1;
+
+            //  Safety:
+            //  -   `handle` was just allocated by `store`, with `layout`, and is `size` bytes large.
+            unsafe { fill(store.resolve(handle), size, 0xFF) };
+
+            //  Safety:
+            //  -   `handle` was allocated by `store`, with `layout`.
+            unsafe { store.deallocate(handle, layout) };
+        }
+
+        if let Ok((handle, _)) = store.allocate_zeroed(layout) {
+            allocated += 1;
+
+            //  Safety:
+            //  -   `handle` was just allocated by `store`, with `layout`.
+            unsafe { check_zeroed("allocate_zeroed", store.resolve(handle), 0..layout.size()) };
+
+            //  Safety:
+            //  -   `handle` was allocated by `store`, with `layout`.
+            unsafe { store.deallocate(handle, layout) };
+        }
+    }
+
+    for (old_layout, new_layout) in RESIZES {
+        let mut store = make();
+
+        let Ok((handle, _)) = store.allocate(old_layout) else {
+            continue;
+        };
+
+        allocated += 1;
+
+        //  Safety:
+        //  -   `handle` was just allocated by `store`, with `old_layout`.
+        unsafe { fill(store.resolve(handle), old_layout.size(), SEED) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `old_layout`.
+        //  -   `new_layout` is at least as large as `old_layout`.
+        let Ok((handle, size)) = (unsafe { store.grow(handle, old_layout, new_layout) }) else {
+            //  Safety:
+            //  -   `handle` is still valid, as `grow` failed.
+            unsafe { store.deallocate(handle, old_layout) };
+
+            continue;
+        };
+
+        //  Safety:
+        //  -   `handle` was just grown by `store`, to `new_layout`.
+        let pointer = unsafe { store.resolve(handle) };
+
+        //  Safety:
+        //  -   `pointer` points to a block of memory of at least `new_layout.size()` bytes.
+        unsafe {
+            check_pattern("grow", pointer, old_layout.size(), SEED);
+            check_block("grow", pointer, new_layout, size);
+        }
+
+        //  Safety:
+        //  -   `handle` was grown by `store`, to `new_layout`.
+        unsafe { store.deallocate(handle, new_layout) };
+    }
+
+    for (old_layout, new_layout) in RESIZES {
+        let mut store = make();
+
+        let Ok((handle, _)) = store.allocate(old_layout) else {
+            continue;
+        };
+
+        allocated += 1;
+
+        //  Safety:
+        //  -   `handle` was just allocated by `store`, with `old_layout`.
+        unsafe { fill(store.resolve(handle), old_layout.size(), SEED) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `old_layout`.
+        //  -   `new_layout` is at least as large as `old_layout`.
+        let Ok((handle, _)) = (unsafe { store.grow_zeroed(handle, old_layout, new_layout) }) else {
+            //  Safety:
+            //  -   `handle` is still valid, as `grow_zeroed` failed.
+            unsafe { store.deallocate(handle, old_layout) };
+
+            continue;
+        };
+
+        //  Safety:
+        //  -   `handle` was just grown by `store`, to `new_layout`.
+        let pointer = unsafe { store.resolve(handle) };
+
+        //  Safety:
+        //  -   `pointer` points to a block of memory of at least `new_layout.size()` bytes.
+        unsafe {
+            check_pattern("grow_zeroed", pointer, old_layout.size(), SEED);
+            check_zeroed("grow_zeroed", pointer, old_layout.size()..new_layout.size());
+        }
+
+        //  Safety:
+        //  -   `handle` was grown by `store`, to `new_layout`.
+        unsafe { store.deallocate(handle, new_layout) };
+    }
+
+    for (new_layout, old_layout) in RESIZES {
+        let mut store = make();
+
+        let Ok((handle, _)) = store.allocate(old_layout) else {
+            continue;
+        };
+
+        allocated += 1;
+
+        //  Safety:
+        //  -   `handle` was just allocated by `store`, with `old_layout`.
+        unsafe { fill(store.resolve(handle), old_layout.size(), SEED) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `old_layout`.
+        //  -   `new_layout` is at most as large as `old_layout`.
+        let Ok((handle, size)) = (unsafe { store.shrink(handle, old_layout, new_layout) }) else {
+            //  Safety:
+            //  -   `handle` is still valid, as `shrink` failed.
+            unsafe { store.deallocate(handle, old_layout) };
+
+            continue;
+        };
+
+        //  Safety:
+        //  -   `handle` was just shrunk by `store`, to `new_layout`.
+        let pointer = unsafe { store.resolve(handle) };
+
+        //  Safety:
+        //  -   `pointer` points to a block of memory of at least `new_layout.size()` bytes.
+        unsafe {
+            check_pattern("shrink", pointer, new_layout.size(), SEED);
+            check_block("shrink", pointer, new_layout, size);
+        }
+
+        //  Safety:
+        //  -   `handle` was shrunk by `store`, to `new_layout`.
+        unsafe { store.deallocate(handle, new_layout) };
+    }
+
+    if allocated == 0 {
+        nothing_checked(check, "allocation");
+    }
+}
+
+//  Checks that dangling handles resolve to suitably aligned pointers.
+#[track_caller]
+fn check_dangling<O: Operations>(check: &str, store: &mut O) {
+    let mut checked = 0;
+
+    for alignment in ALIGNMENTS {
+        //  Safety:
+        //  -   `alignment` is a power of 2.
+        let alignment = unsafe { Alignment::new_unchecked(alignment) };
+
+        let Ok(handle) = store.dangling(alignment) else {
+            continue;
+        };
+
+        checked += 1;
+
+        //  Safety:
+        //  -   `handle` was just created by `store`.
+        let pointer = unsafe { store.resolve(handle) };
+
+        assert!(
+            pointer.as_ptr() as usize % alignment.as_usize() == 0,
+            "StoreDangling violation: dangling handle of alignment {} resolved to {pointer:?}",
+            alignment.as_usize()
+        );
+    }
+
+    if checked == 0 {
+        nothing_checked(check, "dangling");
+    }
+}
+
+//  Checks that simultaneously live blocks of memory do not overlap.
+#[track_caller]
+fn check_disjoint<S: Store>(store: &S) {
+    let layout = Layout::new::<[u8; 16]>();
+
+    let mut handles = [None; 8];
+
+    for (index, slot) in handles.iter_mut().enumerate() {
+        let Ok((handle, size)) = store.allocate(layout) else {
+            break;
+        };
+
+        //  Safety:
+        //  -   `handle` was just allocated by `store`, and is `size` bytes large.
+        unsafe { fill(store.resolve(handle), size, index as u8 * 32) };
+
+        *slot = Some(handle);
+    }
+
+    for (index, slot) in handles.iter_mut().enumerate() {
+        let Some(handle) = slot.take() else { break };
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `layout`.
+        unsafe {
+            check_pattern(
+                "allocate (overlapping blocks)",
+                store.resolve(handle),
+                layout.size(),
+                index as u8 * 32,
+            );
+            store.deallocate(handle, layout);
+        }
+    }
+}
+
+//  Checks that the block of memory returned for `layout`, of `size` bytes, at `pointer` is suitable and usable.
+//
+//  #   Safety
+//
+//  -   `pointer` must point to a writeable block of memory of `size` bytes.
+#[track_caller]
+unsafe fn check_block(operation: &str, pointer: NonNull<u8>, layout: Layout, size: usize) {
+    assert!(
+        size >= layout.size(),
+        "Store violation: {operation} returned {size} bytes for {layout:?}"
+    );
+
+    assert!(
+        pointer.as_ptr() as usize % layout.align() == 0,
+        "Store violation: {operation} returned {pointer:?} for {layout:?}"
+    );
+
+    //  Safety:
+    //  -   As per pre-conditions.
+    unsafe {
+        fill(pointer, size, SEED);
+        check_pattern(operation, pointer, size, SEED);
+    }
+}
+
+//  Checks that the bytes within `range` are zeroed.
+//
+//  #   Safety
+//
+//  -   `pointer` must point to a readable block of memory of at least `range.end` bytes.
+#[track_caller]
+unsafe fn check_zeroed(operation: &str, pointer: NonNull<u8>, range: core::ops::Range<usize>) {
+    for index in range {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let byte = unsafe { *pointer.as_ptr().add(index) };
+
+        assert!(byte == 0, "Store violation: {operation} left byte {index} at {byte:#x}");
+    }
+}
+
+//  Checks that `pointer` still resolves to the same address.
+#[track_caller]
+fn check_same(operation: &str, expected: NonNull<u8>, actual: NonNull<u8>) {
+    assert!(
+        expected == actual,
+        "StoreStable violation: handle resolved to {expected:?} then to {actual:?} after {operation}"
+    );
+}
+
+//  Fills `size` bytes at `pointer` with a pattern derived from `seed`.
+//
+//  #   Safety
+//
+//  -   `pointer` must point to a writeable block of memory of at least `size` bytes.
+unsafe fn fill(pointer: NonNull<u8>, size: usize, seed: u8) {
+    for index in 0..size {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { ptr::write(pointer.as_ptr().add(index), seed.wrapping_add(index as u8)) };
+    }
+}
+
+//  Checks that `size` bytes at `pointer` contain the pattern derived from `seed`.
+//
+//  #   Safety
+//
+//  -   `pointer` must point to a readable block of memory of at least `size` bytes.
+#[track_caller]
+unsafe fn check_pattern(operation: &str, pointer: NonNull<u8>, size: usize, seed: u8) {
+    for index in 0..size {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let byte = unsafe { ptr::read(pointer.as_ptr().add(index)) };
+
+        assert!(
+            byte == seed.wrapping_add(index as u8),
+            "Store violation: {operation} did not preserve byte {index}, found {byte:#x}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::store::{
+        ArenaStore, AtomicInlineBumpStore, AtomicStackBumpBlock, ChaosStore, CheckedStore, FailingStore, FailurePolicy,
+        Fallback, GenerationalStore, InlineBuddyStore, InlineBumpStore, InlineSingleStore, InlineSlabStore,
+        InlineTlsfStore, PoolStore, QuotaBudget, RecordingStore, RedZoneStore, Segregator, SiteStore, SmallSingleStore,
+        StackBuddyBlock, StackBumpBlock, StatsStore,
+    };
+
+    use super::*;
+
+    #[test]
+    fn allocator() {
+        check_store(|| Global);
+        check_store_single(|| Global);
+        check_store_pinning(|| Global);
+    }
+
+    #[test]
+    fn arena_store() {
+        check_store(|| ArenaStore::new_in(Global));
+        check_store_single(|| ArenaStore::new_in(Global));
+        check_store_pinning(|| ArenaStore::new_in(Global));
+    }
+
+    #[test]
+    fn atomic_inline_bump_store() {
+        type TestStore = AtomicInlineBumpStore<u16, [u64; 64]>;
+
+        check_store(TestStore::default);
+        check_store_single(TestStore::default);
+        check_store_stable(TestStore::default);
+    }
+
+    #[test]
+    fn atomic_stack_bump_store() {
+        let block = AtomicStackBumpBlock::<[u64; 256]>::new();
+
+        check_store(|| block.create_store::<u16>());
+        check_store_single(|| block.create_store::<u16>());
+        check_store_sharing(|| block.create_store::<u16>());
+    }
+
+    #[test]
+    fn chaos_store() {
+        check_store(ChaosStore::<Global>::default);
+        check_store_single(ChaosStore::<Global>::default);
+    }
+
+    #[test]
+    fn checked_store() {
+        check_store(CheckedStore::<Global>::default);
+        check_store_single(CheckedStore::<Global>::default);
+        check_store_pinning(CheckedStore::<Global>::default);
+    }
+
+    #[test]
+    #[should_panic(expected = "no dangling succeeded")]
+    fn dangling_never() {
+        //  No offset within an empty block is suitable for a dangling handle.
+        let block = StackBumpBlock::<[u8; 0]>::new();
+
+        check_store(|| block.create_store::<u16>());
+    }
+
+    #[test]
+    fn failing_store() {
+        let make = || FailingStore::new_in(Global, FailurePolicy::EveryNth(3));
+
+        check_store(make);
+        check_store_single(make);
+        check_store_pinning(make);
+    }
+
+    #[test]
+    #[should_panic(expected = "nothing was checked")]
+    fn failing_store_always() {
+        check_store(|| FailingStore::new_in(Global, FailurePolicy::EveryNth(1)));
+    }
+
+    #[test]
+    fn fallback_store() {
+        let (small, large) = (StackBumpBlock::<[u64; 32]>::new(), StackBumpBlock::<[u64; 256]>::new());
+
+        check_store(|| Fallback::new(small.create_store::<u16>(), Global));
+        check_store_single(|| Fallback::new(small.create_store::<u16>(), Global));
+        check_store_sharing(|| Fallback::new(small.create_store::<u16>(), large.create_store::<u16>()));
+    }
+
+    #[test]
+    fn generational_store() {
+        check_store(GenerationalStore::<Global, 16>::default);
+        check_store_single(GenerationalStore::<Global, 16>::default);
+        check_store_pinning(GenerationalStore::<Global, 16>::default);
+    }
+
+    #[test]
+    fn inline_buddy_store() {
+        type TestStore = InlineBuddyStore<u16, [u64; 128]>;
+
+        check_store(TestStore::default);
+        check_store_single(TestStore::default);
+        check_store_stable(TestStore::default);
+    }
+
+    #[test]
+    fn inline_bump_store() {
+        type TestStore = InlineBumpStore<u16, [u64; 64]>;
+
+        check_store(TestStore::default);
+        check_store_single(TestStore::default);
+        check_store_stable(TestStore::default);
+    }
+
+    #[test]
+    fn inline_single_store() {
+        check_store_single(InlineSingleStore::<[u64; 16]>::new);
+    }
+
+    #[test]
+    fn inline_slab_store() {
+        type TestStore = InlineSlabStore<u16, [u64; 64]>;
+
+        check_store(TestStore::default);
+        check_store_single(TestStore::default);
+        check_store_stable(TestStore::default);
+    }
+
+    #[test]
+    fn inline_tlsf_store() {
+        type TestStore = InlineTlsfStore<u16, [u64; 128]>;
+
+        check_store(TestStore::default);
+        check_store_single(TestStore::default);
+        check_store_stable(TestStore::default);
+    }
+
+    #[test]
+    fn pool_store() {
+        let make = || PoolStore::<[u64; 8], Global>::with_capacity_in(32, Global);
+
+        check_store(make);
+        check_store_single(make);
+        check_store_pinning(make);
+    }
+
+    #[test]
+    fn quota_store() {
+        let budget = QuotaBudget::<4>::new(1 << 20);
+        let block = StackBumpBlock::<[u64; 256]>::new();
+
+        check_store(|| budget.create_store(Global));
+        check_store_single(|| budget.create_store(Global));
+        check_store_sharing(|| budget.create_store(block.create_store::<u16>()));
+
+        assert_eq!(0, budget.used());
+    }
+
+    #[test]
+    fn recording_store() {
+        check_store(RecordingStore::<Global, String>::default);
+        check_store_single(RecordingStore::<Global, String>::default);
+        check_store_pinning(RecordingStore::<Global, String>::default);
+    }
+
+    #[test]
+    fn red_zone_store() {
+        check_store(RedZoneStore::<Global>::default);
+        check_store_single(RedZoneStore::<Global>::default);
+        check_store_pinning(RedZoneStore::<Global>::default);
+    }
+
+    #[test]
+    fn segregator_store() {
+        type TestStore = Segregator<64, InlineSlabStore<u16, [u64; 64]>, Global>;
+
+        let (small, large) = (StackBumpBlock::<[u64; 32]>::new(), StackBumpBlock::<[u64; 256]>::new());
+
+        check_store(TestStore::default);
+        check_store_single(TestStore::default);
+        check_store_stable(TestStore::default);
+        check_store_sharing(|| Segregator::<64, _, _>::new(small.create_store::<u16>(), large.create_store::<u16>()));
+    }
+
+    #[test]
+    fn site_store() {
+        check_store(SiteStore::<Global>::default);
+        check_store_single(SiteStore::<Global>::default);
+        check_store_pinning(SiteStore::<Global>::default);
+    }
+
+    #[test]
+    fn small_single_store() {
+        check_store_single(SmallSingleStore::<[u64; 4], Global>::default);
+        check_store_single(|| {
+            let mut store = SmallSingleStore::<[u64; 4], Global>::default();
+            store.set_inline_on_shrink(true);
+            store
+        });
+    }
+
+    #[test]
+    fn stack_buddy_store() {
+        let block = StackBuddyBlock::<[u64; 256]>::new();
+
+        check_store(|| block.create_store::<u16>());
+        check_store_single(|| block.create_store::<u16>());
+        check_store_sharing(|| block.create_store::<u16>());
+    }
+
+    #[test]
+    fn stack_bump_store() {
+        let block = StackBumpBlock::<[u64; 256]>::new();
+
+        check_store(|| block.create_store::<u16>());
+        check_store_single(|| block.create_store::<u16>());
+        check_store_sharing(|| block.create_store::<u16>());
+    }
+
+    #[test]
+    fn stats_store() {
+        check_store(StatsStore::<Global>::default);
+        check_store_single(StatsStore::<Global>::default);
+        check_store_pinning(StatsStore::<Global>::default);
+    }
+} // mod tests
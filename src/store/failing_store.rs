@@ -0,0 +1,385 @@
+//! A Store adapter, injecting allocation failures.
+//!
+//! This store is meant for testing the out-of-memory paths of collections: it makes chosen calls to `allocate`,
+//! `grow`, and `shrink` -- and their zeroed variants -- fail with `AllocError`, according to a `FailurePolicy`, and
+//! forwards all other calls to the underlying store.
+
+use core::{
+    alloc::{AllocError, Layout},
+    cell::Cell,
+    fmt,
+    ptr::{Alignment, NonNull},
+};
+
+use oorandom::Rand32;
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable};
+
+/// The policy determining which calls of a `FailingStore` fail.
+///
+/// Calls are numbered from 1, and only calls to `allocate`, `grow`, and `shrink` -- and their zeroed variants -- are
+/// numbered, and may fail.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FailurePolicy {
+    /// No call fails.
+    Never,
+    /// Only the Nth call fails.
+    Nth(u64),
+    /// Every Nth call fails, that is the Nth, 2Nth, 3Nth, ...
+    EveryNth(u64),
+    /// Each call fails with a probability of 1 in `one_in`, as determined by a PRNG seeded with `seed`.
+    Random {
+        /// Seed of the PRNG, the same seed always leads to the same sequence of failures.
+        seed: u64,
+        /// Inverse probability of failure.
+        one_in: u32,
+    },
+    /// Calls fail if they would bring the number of allocated bytes above the budget.
+    Budget(usize),
+}
+
+/// An adapter of `Store` or `StoreSingle`, injecting allocation failures as per a `FailurePolicy`.
+///
+/// Implements `Store` whenever `S` does, and `StoreSingle` whenever `S` does.
+pub struct FailingStore<S> {
+    policy: Cell<FailurePolicy>,
+    prng: Cell<Rand32>,
+    calls: Cell<u64>,
+    failures: Cell<u64>,
+    //  Number of bytes allocated, as per the layouts passed.
+    live_bytes: Cell<usize>,
+    store: S,
+}
+
+impl<S> FailingStore<S> {
+    /// Creates a new instance, atop `store`, failing calls as per `policy`.
+    pub fn new_in(store: S, policy: FailurePolicy) -> Self {
+        let this = Self {
+            policy: Cell::new(FailurePolicy::Never),
+            prng: Cell::new(Rand32::new(0)),
+            calls: Cell::new(0),
+            failures: Cell::new(0),
+            live_bytes: Cell::new(0),
+            store,
+        };
+
+        this.set_policy(policy);
+
+        this
+    }
+
+    /// Returns the current policy.
+    pub fn policy(&self) -> FailurePolicy {
+        self.policy.get()
+    }
+
+    /// Switches to `policy`, and restarts the numbering of calls.
+    pub fn set_policy(&self, policy: FailurePolicy) {
+        if let FailurePolicy::Random { seed, .. } = policy {
+            self.prng.set(Rand32::new(seed));
+        }
+
+        self.policy.set(policy);
+        self.calls.set(0);
+    }
+
+    /// Returns the number of calls which may fail since the policy was last set.
+    pub fn calls(&self) -> u64 {
+        self.calls.get()
+    }
+
+    /// Returns the number of calls which failed since the creation of the store.
+    pub fn failures(&self) -> u64 {
+        self.failures.get()
+    }
+
+    /// Returns a reference to the underlying store.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<S: Default> Default for FailingStore<S> {
+    fn default() -> Self {
+        Self::new_in(S::default(), FailurePolicy::Never)
+    }
+}
+
+unsafe impl<S: StoreDangling> StoreDangling for FailingStore<S> {
+    type Handle = S::Handle;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        self.store.dangling(alignment)
+    }
+}
+
+unsafe impl<S: Store> Store for FailingStore<S> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        self.check(0, layout.size())?;
+
+        let result = self.store.allocate(layout);
+
+        self.record(0, layout.size(), result.is_ok());
+
+        result
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        self.record(layout.size(), 0, true);
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle, layout) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        self.check(old_layout.size(), new_layout.size())?;
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let result = unsafe { self.store.grow(handle, old_layout, new_layout) };
+
+        self.record(old_layout.size(), new_layout.size(), result.is_ok());
+
+        result
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        self.check(old_layout.size(), new_layout.size())?;
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let result = unsafe { self.store.shrink(handle, old_layout, new_layout) };
+
+        self.record(old_layout.size(), new_layout.size(), result.is_ok());
+
+        result
+    }
+}
+
+unsafe impl<S: StoreSingle> StoreSingle for FailingStore<S> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve_mut(handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        self.check(0, layout.size())?;
+
+        let result = self.store.allocate(layout);
+
+        self.record(0, layout.size(), result.is_ok());
+
+        result
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.record(layout.size(), 0, true);
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        self.check(old_layout.size(), new_layout.size())?;
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let result = unsafe { self.store.grow(handle, old_layout, new_layout) };
+
+        self.record(old_layout.size(), new_layout.size(), result.is_ok());
+
+        result
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        self.check(old_layout.size(), new_layout.size())?;
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let result = unsafe { self.store.shrink(handle, old_layout, new_layout) };
+
+        self.record(old_layout.size(), new_layout.size(), result.is_ok());
+
+        result
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: StoreStable> StoreStable for FailingStore<S> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: StorePinning> StorePinning for FailingStore<S> {}
+
+impl<S: fmt::Debug> fmt::Debug for FailingStore<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("FailingStore")
+            .field("policy", &self.policy.get())
+            .field("calls", &self.calls.get())
+            .field("failures", &self.failures.get())
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+impl<S> FailingStore<S> {
+    //  Numbers the call, replacing a block of `old_size` bytes by a block of `new_size` bytes, and determines whether
+    //  it should fail.
+    fn check(&self, old_size: usize, new_size: usize) -> Result<(), AllocError> {
+        let call = self.calls.get() + 1;
+        self.calls.set(call);
+
+        let fail = match self.policy.get() {
+            FailurePolicy::Never => false,
+            FailurePolicy::Nth(n) => call == n,
+            FailurePolicy::EveryNth(n) => n != 0 && call % n == 0,
+            FailurePolicy::Random { one_in, .. } => {
+                let mut prng = self.prng.get();
+                let fail = one_in != 0 && prng.rand_range(0..one_in) == 0;
+                self.prng.set(prng);
+
+                fail
+            }
+            //  Overflowing the number of live bytes is necessarily over budget.
+            FailurePolicy::Budget(budget) => self
+                .live_bytes
+                .get()
+                .saturating_sub(old_size)
+                .checked_add(new_size)
+                .filter(|live_bytes| *live_bytes <= budget)
+                .is_none(),
+        };
+
+        if fail {
+            self.failures.set(self.failures.get() + 1);
+
+            return Err(AllocError);
+        }
+
+        Ok(())
+    }
+
+    //  Records the replacement of a block of `old_size` bytes by a block of `new_size` bytes, if successful.
+    fn record(&self, old_size: usize, new_size: usize, success: bool) {
+        if success {
+            self.live_bytes
+                .set(self.live_bytes.get().saturating_sub(old_size).saturating_add(new_size));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::collection::{LinkedList, StoreBox};
+
+    use super::*;
+
+    #[test]
+    fn nth() {
+        let result = StoreBox::try_new_in(1u32, FailingStore::new_in(Global, FailurePolicy::Nth(1)));
+
+        assert!(result.is_err());
+
+        let result = StoreBox::try_new_in(1u32, FailingStore::new_in(Global, FailurePolicy::Nth(2)));
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn every_nth() {
+        let mut list = LinkedList::new_in(FailingStore::new_in(Global, FailurePolicy::EveryNth(3)));
+
+        let mut expected = Vec::new();
+
+        for i in 0..30 {
+            if list.try_push_back(i.to_string()).is_ok() {
+                expected.push(i.to_string());
+            }
+        }
+
+        assert_eq!(10, list.store().failures());
+        assert_eq!(expected.len(), list.len());
+        assert!(list.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn random() {
+        let pushes = |seed| {
+            let mut list = LinkedList::new_in(FailingStore::new_in(Global, FailurePolicy::Random { seed, one_in: 4 }));
+
+            let results: Vec<_> = (0..64).map(|i| list.try_push_back(i).is_ok()).collect();
+
+            assert_eq!(results.iter().filter(|ok| **ok).count(), list.len());
+
+            results
+        };
+
+        let first = pushes(42);
+
+        assert_eq!(first, pushes(42));
+        assert!(first.iter().any(|ok| *ok));
+        assert!(first.iter().any(|ok| !*ok));
+    }
+
+    #[test]
+    fn budget() {
+        let mut list = LinkedList::new_in(FailingStore::new_in(Global, FailurePolicy::Budget(1024)));
+
+        while list.try_push_back(0u64).is_ok() {}
+
+        let full = list.len();
+
+        assert!(full > 0);
+
+        list.pop_front();
+
+        assert!(list.try_push_back(0u64).is_ok());
+        assert!(list.try_push_back(0u64).is_err());
+        assert_eq!(full, list.len());
+    }
+} // mod tests
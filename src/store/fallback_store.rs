@@ -0,0 +1,425 @@
+//! A Store combinator, allocating from a primary store and falling back to a secondary store when the former fails.
+//!
+//! This combinator is typically used to put a small, fast, store in front of a general purpose one: for example
+//! `Fallback<StackBumpStore<'_, u16>, Global>` serves the first nodes of a `LinkedList` or `SkipList` from the stack,
+//! and any further node from the heap.
+//!
+//! Each handle is tagged with the store which allocated it. A block of memory which cannot be grown in place by the
+//! primary store is moved to the fallback store, whereas a block of memory of the fallback store never moves back to
+//! the primary store.
+
+use core::{
+    alloc::{AllocError, Layout},
+    fmt,
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSharing, StoreSingle, StoreStable};
+
+/// A handle of a `Fallback`, tagged with the store which allocated the block of memory.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FallbackHandle<P, F> {
+    /// A handle of the primary store.
+    Primary(P),
+    /// A handle of the fallback store.
+    Fallback(F),
+}
+
+impl<P, F> FallbackHandle<P, F> {
+    /// Returns whether the block of memory associated to the handle was allocated by the primary store.
+    pub const fn is_primary(&self) -> bool {
+        matches!(self, Self::Primary(_))
+    }
+
+    /// Returns whether the block of memory associated to the handle was allocated by the fallback store.
+    pub const fn is_fallback(&self) -> bool {
+        matches!(self, Self::Fallback(_))
+    }
+}
+
+/// The error returned when sharing a `Fallback` fails, identifying which of its stores could not be shared.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FallbackSharingError<P, F> {
+    /// The primary store could not be shared.
+    Primary(P),
+    /// The fallback store could not be shared.
+    Fallback(F),
+}
+
+/// A combinator of `Store` or `StoreSingle`, allocating from `P` first, and from `F` whenever `P` fails.
+///
+/// Implements `Store` whenever both `P` and `F` do, and `StoreSingle` whenever both `P` and `F` do, and likewise for
+/// `StoreStable`, `StorePinning`, and `StoreSharing`.
+pub struct Fallback<P, F> {
+    primary: P,
+    fallback: F,
+}
+
+impl<P, F> Fallback<P, F> {
+    /// Creates a new instance, allocating from `primary` first, and from `fallback` whenever `primary` fails.
+    pub const fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback }
+    }
+
+    /// Returns a reference to the primary store.
+    pub const fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    /// Returns a reference to the fallback store.
+    pub const fn fallback(&self) -> &F {
+        &self.fallback
+    }
+}
+
+impl<P: Default, F: Default> Default for Fallback<P, F> {
+    fn default() -> Self {
+        Self::new(P::default(), F::default())
+    }
+}
+
+unsafe impl<P: StoreDangling, F: StoreDangling> StoreDangling for Fallback<P, F> {
+    type Handle = FallbackHandle<P::Handle, F::Handle>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        if let Ok(handle) = self.primary.dangling(alignment) {
+            return Ok(FallbackHandle::Primary(handle));
+        }
+
+        self.fallback.dangling(alignment).map(FallbackHandle::Fallback)
+    }
+}
+
+unsafe impl<P: Store, F: Store> Store for Fallback<P, F> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the primary store.
+            FallbackHandle::Primary(handle) => unsafe { self.primary.resolve(handle) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the fallback store.
+            FallbackHandle::Fallback(handle) => unsafe { self.fallback.resolve(handle) },
+        }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        if let Ok((handle, size)) = self.primary.allocate(layout) {
+            return Ok((FallbackHandle::Primary(handle), size));
+        }
+
+        let (handle, size) = self.fallback.allocate(layout)?;
+
+        Ok((FallbackHandle::Fallback(handle), size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the primary store, with `layout`.
+            FallbackHandle::Primary(handle) => unsafe { self.primary.deallocate(handle, layout) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the fallback store, with `layout`.
+            FallbackHandle::Fallback(handle) => unsafe { self.fallback.deallocate(handle, layout) },
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        let handle = match handle {
+            FallbackHandle::Primary(handle) => handle,
+            FallbackHandle::Fallback(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the fallback store.
+                let (handle, size) = unsafe { self.fallback.grow(handle, old_layout, new_layout)? };
+
+                return Ok((FallbackHandle::Fallback(handle), size));
+            }
+        };
+
+        //  Safety:
+        //  -   As per pre-conditions, `handle` was allocated by the primary store.
+        if let Ok((handle, size)) = unsafe { self.primary.grow(handle, old_layout, new_layout) } {
+            return Ok((FallbackHandle::Primary(handle), size));
+        }
+
+        let (result, size) = self.fallback.allocate(new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is still valid, as a failed `grow` leaves the block of memory untouched.
+        //  -   `result` is valid, since newly allocated.
+        let (old, new) = unsafe { (self.primary.resolve(handle), self.fallback.resolve(result)) };
+
+        //  Safety:
+        //  -   `old` is valid for `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `new` is valid for `old_layout.size()` bytes, as `new_layout.size() >= old_layout.size()`.
+        //  -   `old` and `new` point to non-overlapping areas, since they belong to different live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), old_layout.size()) };
+
+        //  Safety:
+        //  -   `handle` was allocated by the primary store, with `old_layout`, and is still valid.
+        unsafe { self.primary.deallocate(handle, old_layout) };
+
+        Ok((FallbackHandle::Fallback(result), size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        match handle {
+            FallbackHandle::Primary(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the primary store.
+                let (handle, size) = unsafe { self.primary.shrink(handle, old_layout, new_layout)? };
+
+                Ok((FallbackHandle::Primary(handle), size))
+            }
+            FallbackHandle::Fallback(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the fallback store.
+                let (handle, size) = unsafe { self.fallback.shrink(handle, old_layout, new_layout)? };
+
+                Ok((FallbackHandle::Fallback(handle), size))
+            }
+        }
+    }
+}
+
+unsafe impl<P: StoreSingle, F: StoreSingle> StoreSingle for Fallback<P, F> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the primary store.
+            FallbackHandle::Primary(handle) => unsafe { self.primary.resolve(handle) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the fallback store.
+            FallbackHandle::Fallback(handle) => unsafe { self.fallback.resolve(handle) },
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the primary store.
+            FallbackHandle::Primary(handle) => unsafe { self.primary.resolve_mut(handle) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the fallback store.
+            FallbackHandle::Fallback(handle) => unsafe { self.fallback.resolve_mut(handle) },
+        }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        if let Ok((handle, size)) = self.primary.allocate(layout) {
+            return Ok((FallbackHandle::Primary(handle), size));
+        }
+
+        let (handle, size) = self.fallback.allocate(layout)?;
+
+        Ok((FallbackHandle::Fallback(handle), size))
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the primary store, with `layout`.
+            FallbackHandle::Primary(handle) => unsafe { self.primary.deallocate(handle, layout) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the fallback store, with `layout`.
+            FallbackHandle::Fallback(handle) => unsafe { self.fallback.deallocate(handle, layout) },
+        }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        let handle = match handle {
+            FallbackHandle::Primary(handle) => handle,
+            FallbackHandle::Fallback(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the fallback store.
+                let (handle, size) = unsafe { self.fallback.grow(handle, old_layout, new_layout)? };
+
+                return Ok((FallbackHandle::Fallback(handle), size));
+            }
+        };
+
+        //  Safety:
+        //  -   As per pre-conditions, `handle` was allocated by the primary store.
+        if let Ok((handle, size)) = unsafe { self.primary.grow(handle, old_layout, new_layout) } {
+            return Ok((FallbackHandle::Primary(handle), size));
+        }
+
+        let (result, size) = self.fallback.allocate(new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is still valid, as a failed `grow` leaves the block of memory untouched.
+        //  -   `result` is valid, since newly allocated.
+        let (old, new) = unsafe { (self.primary.resolve_mut(handle), self.fallback.resolve_mut(result)) };
+
+        //  Safety:
+        //  -   `old` is valid for `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `new` is valid for `old_layout.size()` bytes, as `new_layout.size() >= old_layout.size()`.
+        //  -   `old` and `new` point to non-overlapping areas, since they belong to different live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), old_layout.size()) };
+
+        //  Safety:
+        //  -   `handle` was allocated by the primary store, with `old_layout`, and is still valid.
+        unsafe { self.primary.deallocate(handle, old_layout) };
+
+        Ok((FallbackHandle::Fallback(result), size))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        match handle {
+            FallbackHandle::Primary(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the primary store.
+                let (handle, size) = unsafe { self.primary.shrink(handle, old_layout, new_layout)? };
+
+                Ok((FallbackHandle::Primary(handle), size))
+            }
+            FallbackHandle::Fallback(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the fallback store.
+                let (handle, size) = unsafe { self.fallback.shrink(handle, old_layout, new_layout)? };
+
+                Ok((FallbackHandle::Fallback(handle), size))
+            }
+        }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the store which allocated `handle`.
+unsafe impl<P: StoreStable, F: StoreStable> StoreStable for Fallback<P, F> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the store which allocated `handle`.
+unsafe impl<P: StorePinning, F: StorePinning> StorePinning for Fallback<P, F> {}
+
+//  Safety:
+//  -   All shares wrap shares of the same primary and fallback stores, hence any handle resolves identically.
+unsafe impl<P: StoreSharing, F: StoreSharing> StoreSharing for Fallback<P, F> {
+    type SharingError = FallbackSharingError<P::SharingError, F::SharingError>;
+
+    fn is_sharing_with(&self, other: &Self) -> bool {
+        self.primary.is_sharing_with(&other.primary) && self.fallback.is_sharing_with(&other.fallback)
+    }
+
+    fn share(&self) -> Result<Self, Self::SharingError>
+    where
+        Self: Sized,
+    {
+        let primary = self.primary.share().map_err(FallbackSharingError::Primary)?;
+        let fallback = self.fallback.share().map_err(FallbackSharingError::Fallback)?;
+
+        Ok(Self::new(primary, fallback))
+    }
+}
+
+impl<P: fmt::Debug, F: fmt::Debug> fmt::Debug for Fallback<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("Fallback")
+            .field("primary", &self.primary)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::{
+        collection::{LinkedList, SkipList},
+        store::{StackBumpBlock, StackBumpStore},
+    };
+
+    use super::*;
+
+    type TestStore<'a> = Fallback<StackBumpStore<'a, u16>, Global>;
+
+    #[test]
+    fn grow_moves_to_fallback() {
+        let block = StackBumpBlock::<[u64; 4]>::new();
+        let store: TestStore<'_> = Fallback::new(block.create_store(), Global);
+
+        let (old_layout, new_layout) = (Layout::new::<[u64; 2]>(), Layout::new::<[u64; 8]>());
+
+        let (handle, _) = Store::allocate(&store, old_layout).unwrap();
+
+        assert!(handle.is_primary());
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        unsafe { Store::resolve(&store, handle).as_ptr().cast::<[u64; 2]>().write([1, 2]) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `old_layout`, and is still valid.
+        //  -   `new_layout` is larger than `old_layout`.
+        let (handle, _) = unsafe { Store::grow(&store, handle, old_layout, new_layout).unwrap() };
+
+        assert!(handle.is_fallback());
+
+        //  Safety:
+        //  -   `handle` was grown by `store`, and is still valid.
+        assert_eq!([1, 2], unsafe {
+            Store::resolve(&store, handle).as_ptr().cast::<[u64; 2]>().read()
+        });
+
+        //  Safety:
+        //  -   `handle` was grown by `store`, to `new_layout`, and is still valid.
+        unsafe { Store::deallocate(&store, handle, new_layout) };
+    }
+
+    #[test]
+    fn list_overflow() {
+        let block = StackBumpBlock::<[u64; 16]>::new();
+        let mut list = LinkedList::<u32, TestStore<'_>>::new_in(Fallback::new(block.create_store(), Global));
+
+        for i in 0..100 {
+            list.try_push_back(i).unwrap();
+        }
+
+        assert!(list.iter().copied().eq(0..100));
+    }
+
+    #[test]
+    fn skip_list_overflow() {
+        let block = StackBumpBlock::<[u64; 16]>::new();
+        let mut map = SkipList::<u32, u32, TestStore<'_>>::with_store(Fallback::new(block.create_store(), Global));
+
+        for i in 0..100 {
+            map.insert(i, i * 2);
+        }
+
+        assert!((0..100).all(|i| map.get(&i) == Some(&(i * 2))));
+    }
+} // mod tests
@@ -0,0 +1,441 @@
+//! A Store adapter, detecting the use of stale handles.
+//!
+//! Each handle is a slot index paired with a generation counter. The generation of a slot is bumped whenever its block
+//! of memory is deallocated, so that any copy of the deallocated handle is recognized as stale afterwards, even once
+//! the slot is reused.
+//!
+//! This makes it possible to build safe APIs -- such as slot maps -- atop `Store`, using `try_resolve`, and to catch
+//! handle-lifetime bugs in tests, as `Store::resolve` panics on stale handles in Debug.
+
+use core::{
+    alloc::{AllocError, Layout},
+    array,
+    cell::Cell,
+    fmt,
+    ptr::{Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable};
+
+/// A handle of a `GenerationalStore`: a slot index paired with a generation counter.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct GenerationalHandle {
+    index: u32,
+    //  For dangling handles, the alignment instead.
+    generation: u32,
+}
+
+impl GenerationalHandle {
+    /// Returns the index of the slot.
+    pub const fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the generation of the slot, at the time the handle was allocated.
+    pub const fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// An adapter of `Store`, tracking each block of memory allocated by the underlying store in one of `N` slots.
+///
+/// Generic parameters:
+///
+/// -   `S` is the underlying store.
+/// -   `N` is the maximum number of blocks of memory allocated at any one time.
+pub struct GenerationalStore<S: Store, const N: usize> {
+    //  Head of the list of free slots, or `N` if all slots are occupied.
+    free: Cell<u32>,
+    slots: [Cell<Slot<S::Handle>>; N],
+    store: S,
+}
+
+impl<S: Store, const N: usize> GenerationalStore<S, N> {
+    /// Creates a new instance, atop `store`.
+    ///
+    /// #   Panics
+    ///
+    /// If `N` is not less than `u32::MAX`.
+    pub fn new_in(store: S) -> Self {
+        assert!(N < DANGLING as usize, "{N} slots cannot be represented");
+
+        let free = Cell::new(0);
+        let slots = array::from_fn(|index| {
+            Cell::new(Slot {
+                generation: 0,
+                handle: None,
+                next: index as u32 + 1,
+            })
+        });
+
+        Self { free, slots, store }
+    }
+
+    /// Returns whether `handle` is live, that is, was allocated by `self` and not deallocated since.
+    ///
+    /// Dangling handles are never live.
+    pub fn is_live(&self, handle: GenerationalHandle) -> bool {
+        self.live_handle(handle).is_some()
+    }
+
+    /// Resolves `handle` into a pointer to the first byte of the associated block of memory, if it is live.
+    ///
+    /// Returns `None` if `handle` is stale, dangling, or was not allocated by `self`. A stale handle whose slot was
+    /// reused since is detected, as the generation of the slot is different.
+    ///
+    /// The resulting pointer is subject to the same invalidation rules as the pointers resolved by `Store::resolve`.
+    pub fn try_resolve(&self, handle: GenerationalHandle) -> Option<NonNull<u8>> {
+        let inner = self.live_handle(handle)?;
+
+        //  Safety:
+        //  -   `inner` was allocated by `self.store`, and is still valid, as the slot is occupied.
+        Some(unsafe { self.store.resolve(inner) })
+    }
+}
+
+impl<S: Store + Default, const N: usize> Default for GenerationalStore<S, N> {
+    fn default() -> Self {
+        Self::new_in(S::default())
+    }
+}
+
+unsafe impl<S: Store, const N: usize> StoreDangling for GenerationalStore<S, N> {
+    type Handle = GenerationalHandle;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let generation = u32::try_from(alignment.as_usize()).map_err(|_| AllocError)?;
+
+        //  Ensure the underlying store can resolve it later.
+        self.store.dangling(alignment)?;
+
+        Ok(GenerationalHandle {
+            index: DANGLING,
+            generation,
+        })
+    }
+}
+
+unsafe impl<S: Store, const N: usize> Store for GenerationalStore<S, N> {
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.index == DANGLING {
+            //  Safety:
+            //  -   `handle.generation` is a valid alignment, as it was created by `dangling`.
+            let alignment = unsafe { Alignment::new_unchecked(handle.generation as usize) };
+
+            //  Safety:
+            //  -   `dangling` succeeded with the same alignment when creating `handle`.
+            let inner = unsafe { self.store.dangling(alignment).unwrap_unchecked() };
+
+            //  Safety:
+            //  -   `inner` is a dangling handle of `self.store`.
+            return unsafe { self.store.resolve(inner) };
+        }
+
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        let inner = unsafe { self.checked_handle(handle, "resolve") };
+
+        //  Safety:
+        //  -   `inner` was allocated by `self.store`, and is still valid, as the slot is occupied.
+        unsafe { self.store.resolve(inner) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let index = self.free.get();
+
+        if index as usize >= N {
+            return Err(AllocError);
+        }
+
+        let (inner, size) = self.store.allocate(layout)?;
+
+        let slot = self.slots[index as usize].get();
+
+        self.free.set(slot.next);
+        self.slots[index as usize].set(Slot {
+            handle: Some(inner),
+            ..slot
+        });
+
+        let generation = slot.generation;
+
+        Ok((GenerationalHandle { index, generation }, size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        let inner = unsafe { self.checked_handle(handle, "deallocate") };
+
+        //  Safety:
+        //  -   `inner` was allocated by `self.store`, and is still valid, as the slot is occupied.
+        //  -   `layout` fits, as per pre-conditions.
+        unsafe { self.store.deallocate(inner, layout) };
+
+        let slot = Slot {
+            generation: handle.generation.wrapping_add(1),
+            handle: None,
+            next: self.free.get(),
+        };
+
+        self.slots[handle.index as usize].set(slot);
+        self.free.set(handle.index);
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        let inner = unsafe { self.checked_handle(handle, "grow") };
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.grow(inner, old_layout, new_layout)? };
+
+        self.replace(handle, inner);
+
+        Ok((handle, size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        let inner = unsafe { self.checked_handle(handle, "shrink") };
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.shrink(inner, old_layout, new_layout)? };
+
+        self.replace(handle, inner);
+
+        Ok((handle, size))
+    }
+}
+
+unsafe impl<S: Store, const N: usize> StoreSingle for GenerationalStore<S, N> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: Store + StoreStable, const N: usize> StoreStable for GenerationalStore<S, N> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: Store + StorePinning, const N: usize> StorePinning for GenerationalStore<S, N> {}
+
+impl<S: Store + fmt::Debug, const N: usize> fmt::Debug for GenerationalStore<S, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let live = self.slots.iter().filter(|slot| slot.get().handle.is_some()).count();
+
+        f.debug_struct("GenerationalStore")
+            .field("slots", &N)
+            .field("live", &live)
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Index of dangling handles.
+const DANGLING: u32 = u32::MAX;
+
+#[derive(Clone, Copy)]
+struct Slot<H> {
+    generation: u32,
+    //  Handle of the underlying store, if the slot is occupied.
+    handle: Option<H>,
+    //  Next free slot, if the slot is free.
+    next: u32,
+}
+
+impl<S: Store, const N: usize> GenerationalStore<S, N> {
+    //  Returns the handle of the underlying store, if `handle` is live.
+    fn live_handle(&self, handle: GenerationalHandle) -> Option<S::Handle> {
+        let slot = self.slots.get(handle.index as usize)?.get();
+
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        slot.handle
+    }
+
+    //  Returns the handle of the underlying store.
+    //
+    //  #   Panics
+    //
+    //  In Debug, if `handle` is not live.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must have been allocated by `self`, and still be valid.
+    unsafe fn checked_handle(&self, handle: GenerationalHandle, operation: &str) -> S::Handle {
+        let inner = self.live_handle(handle);
+
+        debug_assert!(inner.is_some(), "{operation} called with stale handle {handle:?}");
+
+        //  Safety:
+        //  -   `handle` is still valid, as per pre-conditions, hence its slot is occupied.
+        unsafe { inner.unwrap_unchecked() }
+    }
+
+    //  Replaces the handle of the underlying store associated to `handle`.
+    fn replace(&self, handle: GenerationalHandle, inner: S::Handle) {
+        let cell = &self.slots[handle.index as usize];
+
+        cell.set(Slot {
+            handle: Some(inner),
+            ..cell.get()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::collection::StoreVec;
+
+    use super::*;
+
+    type TestStore = GenerationalStore<Global, 4>;
+
+    #[test]
+    fn stale_handle() {
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (first, _) = Store::allocate(&store, layout).unwrap();
+
+        assert!(store.is_live(first));
+        assert!(store.try_resolve(first).is_some());
+
+        unsafe { Store::deallocate(&store, first, layout) };
+
+        assert!(!store.is_live(first));
+        assert!(store.try_resolve(first).is_none());
+
+        //  The slot is reused, with a different generation.
+        let (second, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(first.index(), second.index());
+        assert_ne!(first.generation(), second.generation());
+        assert!(store.try_resolve(first).is_none());
+        assert!(store.try_resolve(second).is_some());
+    }
+
+    #[test]
+    fn exhaustion() {
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let handles: Vec<_> = (0..4).map(|_| Store::allocate(&store, layout).unwrap().0).collect();
+
+        assert!(Store::allocate(&store, layout).is_err());
+
+        unsafe { Store::deallocate(&store, handles[2], layout) };
+
+        let (handle, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(2, handle.index());
+
+        for handle in [handles[0], handles[1], handle, handles[3]] {
+            unsafe { Store::deallocate(&store, handle, layout) };
+        }
+    }
+
+    #[test]
+    fn dangling() {
+        let store = TestStore::default();
+
+        let handle = store.dangling(Alignment::of::<u64>()).unwrap();
+
+        assert!(!store.is_live(handle));
+
+        let pointer = unsafe { Store::resolve(&store, handle) };
+
+        assert_eq!(0, pointer.as_ptr().addr() % 8);
+    }
+
+    #[test]
+    fn grow_keeps_handle() {
+        let mut v = StoreVec::<u32, TestStore>::new();
+
+        for i in 0..100 {
+            v.push(i);
+        }
+
+        assert_eq!(Some(&99), v.as_slice().last());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "resolve called with stale handle")]
+    fn resolve_stale() {
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (handle, _) = Store::allocate(&store, layout).unwrap();
+
+        unsafe { Store::deallocate(&store, handle, layout) };
+
+        unsafe { Store::resolve(&store, handle) };
+    }
+} // mod tests
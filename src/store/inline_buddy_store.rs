@@ -0,0 +1,809 @@
+//! A "buddy allocator" Store.
+//!
+//! This store is suitable for most containers -- such as `Box`, `BTreeMap`, `HashMap`, `List`, and `Vec` -- and
+//! reclaims the memory of deallocated blocks, with bounded fragmentation.
+//!
+//! The block of memory is split into power-of-2 sized blocks, each of which can be split into two halves -- buddies --
+//! and buddies are coalesced back as soon as both are free. Allocation and deallocation are `O(log n)`, in the number
+//! of block sizes.
+//!
+//! The state of the allocator is entirely contained within its block of memory:
+//!
+//! -   The free blocks of each size are linked together in a doubly-linked list, storing the handles to the next and
+//!     previous free blocks at the very beginning of each free block.
+//! -   A bitmap, stored at the very end of the block of memory, tracks which blocks are free, so as to be able to check
+//!     whether a buddy is free in `O(1)`.
+
+use core::{
+    alloc::{AllocError, Layout},
+    array,
+    cell::{Cell, UnsafeCell},
+    cmp, fmt,
+    mem::{self, MaybeUninit},
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::{
+    interface::{Store, StoreDangling, StoreSingle, StoreStable},
+    store::{Region, RegionKind, StoreLayoutDump},
+};
+
+/// An implementation of `Store` providing a single, inline, block of memory, managed as a buddy allocator.
+///
+/// Generic parameters:
+///
+/// -   `H` is the handle type, it must convertible to and from `usize`.
+/// -   The block of memory is aligned and sized as per `T`.
+///
+/// A small portion of the block of memory -- up to 1/16th -- is reserved for book-keeping.
+pub struct InlineBuddyStore<H, T> {
+    //  The head of the free list of each order, or the offset of the end of `memory` if the list is empty.
+    free_lists: [Cell<H>; NUMBER_ORDERS],
+    memory: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<H, T> InlineBuddyStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn new() -> Result<Self, AllocError> {
+        let end = Self::from_offset(Self::memory_layout().size())?;
+
+        let free_lists = array::from_fn(|_| Cell::new(end));
+        let memory = UnsafeCell::new(MaybeUninit::uninit());
+
+        let this = Self { free_lists, memory };
+
+        //  Safety:
+        //  -   The bitmap is within the bounds of `this.memory`.
+        unsafe { ptr::write_bytes(this.bitmap(), 0, Self::BITMAP_SIZE) };
+
+        //  Carve the usable area into the largest possible blocks: since each is a power of 2, and they are laid out in
+        //  decreasing order of size, each is aligned on its size.
+        let mut offset = 0;
+
+        while Self::USABLE_SIZE - offset >= Self::MIN_BLOCK_SIZE {
+            let remaining = Self::USABLE_SIZE - offset;
+            let order = (remaining / Self::MIN_BLOCK_SIZE).ilog2() as usize;
+
+            //  Safety:
+            //  -   The block at `offset` of `order` is within the usable area, and not part of any free list.
+            unsafe { this.push_free(order, offset) };
+
+            offset += Self::block_size(order);
+        }
+
+        Ok(this)
+    }
+}
+
+impl<H, T> Default for InlineBuddyStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn default() -> Self {
+        Self::new().expect("Size of `T` to be representable by `H`")
+    }
+}
+
+//  Cannot be const, because TryFrom is not marked #[const_trait].
+unsafe impl<H, T> StoreDangling for InlineBuddyStore<H, T>
+where
+    H: Copy + TryFrom<usize>,
+{
+    type Handle = H;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let layout = Self::memory_layout();
+
+        if alignment.as_usize() > layout.align() {
+            return Err(AllocError);
+        }
+
+        Self::from_offset(alignment.as_usize())
+    }
+}
+
+unsafe impl<H, T> Store for InlineBuddyStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let order = Self::order_of(layout)?;
+
+        let Some(available) = (order..NUMBER_ORDERS).find(|&o| !self.is_empty(o)) else {
+            return Err(AllocError);
+        };
+
+        let offset = self.pop_free(available);
+
+        debug_assert!(offset.is_some());
+
+        //  Safety:
+        //  -   The free list of order `available` is not empty.
+        let offset = unsafe { offset.unwrap_unchecked() };
+
+        //  Split the block until reaching the desired order, freeing the upper halves.
+        for split in (order..available).rev() {
+            //  Safety:
+            //  -   The upper half of the block is within the usable area, and not part of any free list.
+            unsafe { self.push_free(split, offset + Self::block_size(split)) };
+        }
+
+        Ok((Self::from_offset(offset)?, Self::block_size(order)))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   `layout` fits the block of memory associated with `handle`, as per pre-conditions.
+        let order = unsafe { Self::order_of_unchecked(layout) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        //  -   `order` is the order of the block.
+        unsafe { self.release(Self::into_offset(handle), order) };
+    }
+
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        debug_assert!(Self::into_offset(handle) <= Self::memory_layout().size());
+
+        let offset = Self::into_offset(handle);
+
+        //  Safety:
+        //  -   `offset` is within bounds of `self.memory`, as `handle` was allocated by `self` as per pre-conditions.
+        unsafe { self.at(offset) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        //  Safety:
+        //  -   `old_layout` fits the block of memory associated with `handle`, as per pre-conditions.
+        let old_order = unsafe { Self::order_of_unchecked(old_layout) };
+        let new_order = Self::order_of(new_layout)?;
+
+        let offset = Self::into_offset(handle);
+
+        if self.try_grow_in_place(offset, old_order, new_order) {
+            return Ok((handle, Self::block_size(new_order)));
+        }
+
+        self.grow_by_relocation(offset, old_layout, old_order, new_layout)
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        //  Safety:
+        //  -   `old_layout` fits the block of memory associated with `handle`, as per pre-conditions.
+        let old_order = unsafe { Self::order_of_unchecked(old_layout) };
+        let new_order = Self::order_of(new_layout)?;
+
+        debug_assert!(new_order <= old_order);
+
+        let offset = Self::into_offset(handle);
+
+        //  The upper halves can be released, their buddies are the lower halves which are still in use, hence there
+        //  is no opportunity for coalescing.
+        for split in (new_order..old_order).rev() {
+            //  Safety:
+            //  -   The upper half of the block is within the usable area, and not part of any free list.
+            unsafe { self.push_free(split, offset + Self::block_size(split)) };
+        }
+
+        Ok((handle, Self::block_size(new_order)))
+    }
+}
+
+unsafe impl<H, T> StoreSingle for InlineBuddyStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, as long as `self` doesn't move.
+unsafe impl<H, T> StoreStable for InlineBuddyStore<H, T> where H: Copy + TryFrom<usize> + TryInto<usize> {}
+
+impl<H, T> StoreLayoutDump for InlineBuddyStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn capacity(&self) -> usize {
+        Self::memory_layout().size()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        None
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        //  The order of the largest block fitting in the usable area, if any.
+        let largest = (Self::USABLE_SIZE / Self::MIN_BLOCK_SIZE).checked_ilog2().unwrap_or(0) as usize;
+
+        //  The start of the current run of used blocks, if any.
+        let mut used = None;
+        let mut offset = 0;
+
+        while offset < Self::USABLE_SIZE {
+            let free = (0..=largest).rev().find(|&order| {
+                let size = Self::block_size(order);
+
+                offset % size == 0 && offset + size <= Self::USABLE_SIZE && self.is_free(order, offset)
+            });
+
+            //  The order of the blocks in use is not tracked, hence used blocks are reported by runs.
+            let Some(order) = free else {
+                used.get_or_insert(offset);
+                offset += Self::MIN_BLOCK_SIZE;
+                continue;
+            };
+
+            if let Some(start) = used.take() {
+                visitor(Region {
+                    offset: start,
+                    size: offset - start,
+                    kind: RegionKind::Used,
+                });
+            }
+
+            visitor(Region {
+                offset,
+                size: Self::block_size(order),
+                kind: RegionKind::Free,
+            });
+
+            offset += Self::block_size(order);
+        }
+
+        if let Some(start) = used {
+            visitor(Region {
+                offset: start,
+                size: offset - start,
+                kind: RegionKind::Used,
+            });
+        }
+
+        //  The tail, too small to form a block, and the bitmap.
+        if self.capacity() > Self::USABLE_SIZE {
+            visitor(Region {
+                offset: Self::USABLE_SIZE,
+                size: self.capacity() - Self::USABLE_SIZE,
+                kind: RegionKind::Overhead,
+            });
+        }
+    }
+}
+
+impl<H, T> fmt::Debug for InlineBuddyStore<H, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let layout = Layout::new::<T>();
+
+        f.debug_struct("InlineBuddyStore")
+            .field("size", &layout.size())
+            .field("align", &layout.align())
+            .field("usable", &Self::USABLE_SIZE)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  The number of orders, one per power of 2.
+const NUMBER_ORDERS: usize = usize::BITS as usize;
+
+impl<H, T> InlineBuddyStore<H, T> {
+    //  The size of the smallest block, large enough to hold the links of the free list.
+    const MIN_BLOCK_SIZE: usize = {
+        let links = 2 * mem::size_of::<H>();
+
+        if links <= 8 {
+            8
+        } else {
+            links.next_power_of_two()
+        }
+    };
+
+    //  The size of the smallest power-of-2 area covering the entire memory, of which the usable area is a prefix.
+    const VIRTUAL_SIZE: usize = Self::memory_layout().size().next_power_of_two();
+
+    //  The size of the bitmap, in bytes: one bit per node of the complete binary tree covering the virtual area.
+    const BITMAP_SIZE: usize = {
+        let nodes = 2 * Self::VIRTUAL_SIZE / Self::MIN_BLOCK_SIZE;
+
+        (nodes + 7) / 8
+    };
+
+    //  The offset of the bitmap, at the very end of the memory.
+    const BITMAP_OFFSET: usize = Self::memory_layout().size().saturating_sub(Self::BITMAP_SIZE);
+
+    //  The size of the usable area, carved into blocks.
+    const USABLE_SIZE: usize = Self::BITMAP_OFFSET / Self::MIN_BLOCK_SIZE * Self::MIN_BLOCK_SIZE;
+
+    #[inline(always)]
+    const fn memory_layout() -> Layout {
+        Layout::new::<T>()
+    }
+
+    #[inline(always)]
+    const fn block_size(order: usize) -> usize {
+        Self::MIN_BLOCK_SIZE << order
+    }
+
+    //  Returns the order of the smallest block fitting `layout`.
+    //
+    //  Any block is at least aligned to the minimum of its size and the alignment of `memory`.
+    fn order_of(layout: Layout) -> Result<usize, AllocError> {
+        if layout.align() > Self::memory_layout().align() {
+            //  Even if the memory block was aligned for the current address of `self.memory`, moving `self` would risk
+            //  breaking this alignment.
+
+            return Err(AllocError);
+        }
+
+        let size = cmp::max(cmp::max(layout.size(), layout.align()), Self::MIN_BLOCK_SIZE);
+
+        let Some(size) = size.checked_next_power_of_two() else {
+            return Err(AllocError);
+        };
+
+        if size > Self::USABLE_SIZE {
+            return Err(AllocError);
+        }
+
+        Ok((size / Self::MIN_BLOCK_SIZE).trailing_zeros() as usize)
+    }
+
+    //  Returns the order of the smallest block fitting `layout`.
+    //
+    //  #   Safety
+    //
+    //  -   `layout` must fit a block of memory allocated by this store.
+    unsafe fn order_of_unchecked(layout: Layout) -> usize {
+        let order = Self::order_of(layout);
+
+        debug_assert!(order.is_ok());
+
+        //  Safety:
+        //  -   `layout` fits a block of memory, as per pre-conditions, and computing the order of this layout succeeded
+        //      when allocating.
+        unsafe { order.unwrap_unchecked() }
+    }
+
+    //  Returns the index of the bit associated to the block of `order` at `offset`, in the bitmap.
+    #[inline(always)]
+    const fn bit_index(order: usize, offset: usize) -> usize {
+        let size = Self::block_size(order);
+
+        Self::VIRTUAL_SIZE / size + offset / size
+    }
+
+    //  Returns a pointer to the byte at `offset` in the memory.
+    //
+    //  #   Safety
+    //
+    //  -   `offset` must be within the bounds of `self.memory`.
+    #[inline(always)]
+    unsafe fn at(&self, offset: usize) -> NonNull<u8> {
+        let pointer = self.memory.get() as *mut u8;
+
+        //  Safety:
+        //  -   `offset` is within bounds of `self.memory`, as per pre-conditions.
+        let pointer = unsafe { pointer.add(offset) };
+
+        //  Safety:
+        //  -   `pointer` is non null as `self` is non null.
+        unsafe { NonNull::new_unchecked(pointer) }
+    }
+
+    #[inline(always)]
+    fn bitmap(&self) -> *mut u8 {
+        //  Safety:
+        //  -   `Self::BITMAP_OFFSET` is within the bounds of `self.memory`.
+        unsafe { self.at(Self::BITMAP_OFFSET).as_ptr() }
+    }
+
+    //  Returns whether the block of `order` at `offset` is free.
+    fn is_free(&self, order: usize, offset: usize) -> bool {
+        let index = Self::bit_index(order, offset);
+
+        //  Safety:
+        //  -   `index / 8` is within the bitmap, since the bitmap covers the virtual area.
+        let byte = unsafe { *self.bitmap().add(index / 8) };
+
+        byte & (1 << (index % 8)) != 0
+    }
+
+    //  Marks the block of `order` at `offset` as free, or not.
+    fn set_free(&self, order: usize, offset: usize, free: bool) {
+        let index = Self::bit_index(order, offset);
+
+        //  Safety:
+        //  -   `index / 8` is within the bitmap, since the bitmap covers the virtual area.
+        let byte = unsafe { &mut *self.bitmap().add(index / 8) };
+
+        if free {
+            *byte |= 1 << (index % 8);
+        } else {
+            *byte &= !(1 << (index % 8));
+        }
+    }
+}
+
+impl<H, T> InlineBuddyStore<H, T>
+where
+    H: TryFrom<usize>,
+{
+    #[inline(always)]
+    fn from_offset(offset: usize) -> Result<H, AllocError> {
+        debug_assert!(offset <= Self::memory_layout().size());
+
+        offset.try_into().map_err(|_| AllocError)
+    }
+}
+
+impl<H, T> InlineBuddyStore<H, T>
+where
+    H: TryInto<usize>,
+{
+    #[inline(always)]
+    fn into_offset(handle: H) -> usize {
+        let offset = handle.try_into();
+
+        debug_assert!(offset.is_ok());
+
+        //  Safety:
+        //  -   `handle` was created from `usize`, hence converting back always succeeds.
+        unsafe { offset.unwrap_unchecked() }
+    }
+}
+
+impl<H, T> InlineBuddyStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    //  The offset marking the end of a free list.
+    const END: usize = Self::memory_layout().size();
+
+    fn is_empty(&self, order: usize) -> bool {
+        Self::into_offset(self.free_lists[order].get()) == Self::END
+    }
+
+    //  Reads the (next, previous) links of the free block at `offset`.
+    fn links(&self, offset: usize) -> (usize, usize) {
+        //  Safety:
+        //  -   `offset` is the offset of a free block, hence within bounds.
+        let pointer = unsafe { self.at(offset) }.as_ptr() as *const H;
+
+        //  Safety:
+        //  -   `pointer` is valid for reads of 2 `H`, as each block is at least `Self::MIN_BLOCK_SIZE` bytes.
+        //  -   `pointer` points to initialized `H`, as written by `push_free` or `set_next`/`set_prev`.
+        let (next, prev) = unsafe { (ptr::read_unaligned(pointer), ptr::read_unaligned(pointer.add(1))) };
+
+        (Self::into_offset(next), Self::into_offset(prev))
+    }
+
+    //  Writes the next, or previous, link of the free block at `offset`.
+    fn set_link(&self, offset: usize, index: usize, link: usize) {
+        debug_assert!(index < 2);
+
+        //  Safety:
+        //  -   `offset` is the offset of a free block, hence within bounds.
+        let pointer = unsafe { self.at(offset) }.as_ptr() as *mut H;
+
+        //  All offsets were checked to be representable by `H` on construction.
+        let Ok(link) = Self::from_offset(link) else {
+            unreachable!("All offsets within memory to be representable")
+        };
+
+        //  Safety:
+        //  -   `pointer` is valid for writes of 2 `H`, as each block is at least `Self::MIN_BLOCK_SIZE` bytes.
+        //  -   `pointer` is exclusive, as the block of memory is free.
+        unsafe { ptr::write_unaligned(pointer.add(index), link) };
+    }
+
+    //  Pushes the block of `order` at `offset` at the front of the free list of `order`.
+    //
+    //  #   Safety
+    //
+    //  -   The block must be within the usable area.
+    //  -   The block must not be in use, nor be part of any free list.
+    unsafe fn push_free(&self, order: usize, offset: usize) {
+        debug_assert!(offset + Self::block_size(order) <= Self::USABLE_SIZE);
+        debug_assert!(!self.is_free(order, offset));
+
+        let next = Self::into_offset(self.free_lists[order].get());
+
+        self.set_link(offset, 0, next);
+        self.set_link(offset, 1, Self::END);
+
+        if next != Self::END {
+            self.set_link(next, 1, offset);
+        }
+
+        self.set_head(order, offset);
+        self.set_free(order, offset, true);
+    }
+
+    //  Pops the block at the front of the free list of `order`, if any.
+    fn pop_free(&self, order: usize) -> Option<usize> {
+        if self.is_empty(order) {
+            return None;
+        }
+
+        let head = Self::into_offset(self.free_lists[order].get());
+
+        self.remove_free(order, head);
+
+        Some(head)
+    }
+
+    //  Removes the free block of `order` at `offset` from its free list.
+    fn remove_free(&self, order: usize, offset: usize) {
+        debug_assert!(self.is_free(order, offset));
+
+        let (next, prev) = self.links(offset);
+
+        if prev == Self::END {
+            self.set_head(order, next);
+        } else {
+            self.set_link(prev, 0, next);
+        }
+
+        if next != Self::END {
+            self.set_link(next, 1, prev);
+        }
+
+        self.set_free(order, offset, false);
+    }
+
+    fn set_head(&self, order: usize, offset: usize) {
+        let Ok(head) = Self::from_offset(offset) else {
+            unreachable!("All offsets within memory to be representable")
+        };
+
+        self.free_lists[order].set(head);
+    }
+
+    //  Returns the offset of the buddy of the block of `order` at `offset`, if it exists.
+    fn buddy(order: usize, offset: usize) -> Option<usize> {
+        let size = Self::block_size(order);
+        let buddy = offset ^ size;
+
+        (buddy + size <= Self::USABLE_SIZE).then_some(buddy)
+    }
+
+    //  Releases the block of `order` at `offset`, coalescing it with its buddies as far as possible.
+    //
+    //  #   Safety
+    //
+    //  -   The block must be in use.
+    unsafe fn release(&self, mut offset: usize, mut order: usize) {
+        while let Some(buddy) = Self::buddy(order, offset) {
+            if !self.is_free(order, buddy) {
+                break;
+            }
+
+            self.remove_free(order, buddy);
+
+            offset = cmp::min(offset, buddy);
+            order += 1;
+        }
+
+        //  Safety:
+        //  -   The block is within the usable area, and not part of any free list.
+        unsafe { self.push_free(order, offset) };
+    }
+
+    //  Attempts to grow the block of `old_order` at `offset` into a block of `new_order` at the same offset, which
+    //  requires that all the upper buddies be free.
+    fn try_grow_in_place(&self, offset: usize, old_order: usize, new_order: usize) -> bool {
+        if offset % Self::block_size(new_order) != 0 {
+            return false;
+        }
+
+        let all_free = (old_order..new_order).all(|order| {
+            let buddy = offset + Self::block_size(order);
+
+            Self::buddy(order, offset) == Some(buddy) && self.is_free(order, buddy)
+        });
+
+        if !all_free {
+            return false;
+        }
+
+        for order in old_order..new_order {
+            self.remove_free(order, offset + Self::block_size(order));
+        }
+
+        true
+    }
+
+    //  Slow part of `grow`.
+    #[inline(never)]
+    fn grow_by_relocation(
+        &self,
+        offset: usize,
+        old_layout: Layout,
+        old_order: usize,
+        new_layout: Layout,
+    ) -> Result<(H, usize), AllocError> {
+        let (result, new_size) = Store::allocate(self, new_layout)?;
+
+        //  Safety:
+        //  -   `offset` is the offset of a valid block, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (new, old) = unsafe { (Store::resolve(self, result), self.at(offset)) };
+
+        //  Safety:
+        //  -   `old` is valid for `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `new` is valid for `old_layout.size()` bytes, since it is valid for `new_layout.size()` bytes and as per
+        //      pre-conditions `new_layout.size() >= old_layout.size()`.
+        //  -   `old` and `new` are at least 1-byte aligned.
+        //  -   `old` and `new` point to non-overlapping areas, since both are blocks in use.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), old_layout.size()) };
+
+        //  Safety:
+        //  -   The block is in use.
+        unsafe { self.release(offset, old_order) };
+
+        Ok((result, new_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //  1024 bytes, of which 32 are reserved for the bitmap, hence the usable area is composed of blocks of 512, 256,
+    //  128, 64, and 32 bytes.
+    type TestStore = InlineBuddyStore<u16, [u64; 128]>;
+
+    #[test]
+    fn geometry() {
+        assert_eq!(8, TestStore::MIN_BLOCK_SIZE);
+        assert_eq!(32, TestStore::BITMAP_SIZE);
+        assert_eq!(992, TestStore::USABLE_SIZE);
+    }
+
+    #[test]
+    fn coalescing() {
+        let store = TestStore::default();
+        let small = Layout::new::<u64>();
+        let large = Layout::new::<[u64; 64]>();
+
+        let (first, _) = Store::allocate(&store, large).unwrap();
+
+        unsafe { Store::deallocate(&store, first, large) };
+
+        let smalls: Vec<_> = (0..64).map(|_| Store::allocate(&store, small).unwrap().0).collect();
+
+        //  The large block is split.
+        Store::allocate(&store, large).unwrap_err();
+
+        for handle in smalls {
+            unsafe { Store::deallocate(&store, handle, small) };
+        }
+
+        //  The large block is coalesced.
+        let (second, size) = Store::allocate(&store, large).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(512, size);
+    }
+
+    #[test]
+    fn grow_in_place() {
+        let store = TestStore::default();
+        let old_layout = Layout::new::<[u8; 8]>();
+        let new_layout = Layout::new::<[u8; 32]>();
+
+        let (handle, _) = Store::allocate(&store, old_layout).unwrap();
+
+        unsafe { Store::resolve(&store, handle).as_ptr().write(42) };
+
+        let (grown, size) = unsafe { Store::grow(&store, handle, old_layout, new_layout).unwrap() };
+
+        assert_eq!(handle, grown);
+        assert_eq!(32, size);
+        assert_eq!(42, unsafe { Store::resolve(&store, grown).as_ptr().read() });
+    }
+
+    #[test]
+    fn grow_by_relocation() {
+        let store = TestStore::default();
+        let old_layout = Layout::new::<[u8; 8]>();
+        let new_layout = Layout::new::<[u8; 16]>();
+
+        let (handle, _) = Store::allocate(&store, old_layout).unwrap();
+        let (_blocker, _) = Store::allocate(&store, old_layout).unwrap();
+
+        unsafe { Store::resolve(&store, handle).as_ptr().write(42) };
+
+        let (grown, size) = unsafe { Store::grow(&store, handle, old_layout, new_layout).unwrap() };
+
+        assert_ne!(handle, grown);
+        assert_eq!(16, size);
+        assert_eq!(42, unsafe { Store::resolve(&store, grown).as_ptr().read() });
+    }
+
+    #[test]
+    fn shrink_releases() {
+        let store = TestStore::default();
+        let large = Layout::new::<[u64; 64]>();
+        let small = Layout::new::<u64>();
+
+        let (handle, _) = Store::allocate(&store, large).unwrap();
+        let (handle, size) = unsafe { Store::shrink(&store, handle, large, small).unwrap() };
+
+        assert_eq!(8, size);
+
+        unsafe { Store::deallocate(&store, handle, small) };
+
+        Store::allocate(&store, large).unwrap();
+    }
+} // mod tests
@@ -12,7 +12,13 @@ use core::{
     ptr::{self, Alignment, NonNull},
 };
 
-use crate::interface::{Store, StoreDangling, StoreSingle, StoreStable};
+use crate::{
+    interface::{Store, StoreDangling, StoreSingle, StoreStable},
+    store::{
+        layout_dump::{visit_watermarked, Region},
+        BumpCheckpoint, StoreLayoutDump,
+    },
+};
 
 /// An implementation of `Store` providing a single, inline, block of memory.
 ///
@@ -39,6 +45,40 @@ where
     }
 }
 
+impl<H, T> InlineBumpStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    /// Returns a checkpoint of the current watermark, to rewind to later.
+    pub fn checkpoint(&self) -> BumpCheckpoint {
+        BumpCheckpoint::new(Self::into_offset(self.watermark.get()))
+    }
+
+    /// Rewinds the store to `checkpoint`, releasing all the blocks of memory allocated since.
+    ///
+    /// All the handles allocated since `checkpoint` was taken are invalidated, as well as any pointer resolved from
+    /// them. Only rewinding forward, to a checkpoint above the current watermark, is a no-op. A checkpoint taken from
+    /// another store is not told apart, and rewinds this store all the same.
+    pub fn rewind(&mut self, checkpoint: BumpCheckpoint) {
+        let watermark = checkpoint.watermark();
+
+        if watermark >= Self::into_offset(self.watermark.get()) {
+            return;
+        }
+
+        if let Ok(watermark) = Self::from_offset(watermark) {
+            self.watermark.set(watermark);
+        }
+    }
+
+    /// Resets the store, releasing all the blocks of memory.
+    ///
+    /// All the handles allocated by this store are invalidated, as well as any pointer resolved from them.
+    pub fn reset(&mut self) {
+        self.rewind(BumpCheckpoint::new(0));
+    }
+}
+
 impl<H, T> Default for InlineBumpStore<H, T>
 where
     H: TryFrom<usize>,
@@ -77,8 +117,15 @@ where
         Ok((result, layout.size()))
     }
 
-    #[inline(always)]
-    unsafe fn deallocate(&self, _handle: Self::Handle, _layout: Layout) {}
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  If `handle` points to the last allocation, its memory block can be reclaimed by lowering the watermark.
+        let offset = Self::into_offset(handle);
+        let watermark = Self::into_offset(self.watermark.get());
+
+        if offset + layout.size() == watermark {
+            self.watermark.set(handle);
+        }
+    }
 
     #[inline(always)]
     unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
@@ -113,7 +160,8 @@ where
             let watermark = Self::into_offset(self.watermark.get());
 
             if offset + old_layout.size() == watermark
-                && new_layout.align() <= old_layout.align()
+                && offset % new_layout.align() == 0
+                && new_layout.align() <= Self::memory_layout().align()
                 && offset + new_layout.size() <= Self::memory_layout().size()
             {
                 let new_watermark = Self::from_offset(watermark - old_layout.size() + new_layout.size())?;
@@ -126,18 +174,28 @@ where
         self.grow_by_relocation(handle, old_layout, new_layout)
     }
 
-    #[inline(always)]
     unsafe fn shrink(
         &self,
         handle: Self::Handle,
         old_layout: Layout,
-        _new_layout: Layout,
+        new_layout: Layout,
     ) -> Result<(Self::Handle, usize), AllocError> {
         debug_assert!(
-            _new_layout.size() >= old_layout.size(),
-            "{_new_layout:?} must have a smaller size than {old_layout:?}"
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
         );
 
+        //  As an optimization, if `handle` points to the last allocation, the tail of its memory block may be
+        //  reclaimed.
+        let offset = Self::into_offset(handle);
+
+        if offset + old_layout.size() == Self::into_offset(self.watermark.get()) {
+            let new_watermark = Self::from_offset(offset + new_layout.size())?;
+            self.watermark.set(new_watermark);
+
+            return Ok((handle, new_layout.size()));
+        }
+
         Ok((handle, old_layout.size()))
     }
 }
@@ -164,8 +222,11 @@ where
         <Self as Store>::allocate(self, layout)
     }
 
-    #[inline(always)]
-    unsafe fn deallocate(&mut self, _handle: Self::Handle, _layout: Layout) {}
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
 
     unsafe fn grow(
         &mut self,
@@ -195,6 +256,26 @@ where
 //  -   `self.resolve(handle)` always returns the same address, as long as `self` doesn't move.
 unsafe impl<H, T> StoreStable for InlineBumpStore<H, T> where H: Copy + TryFrom<usize> + TryInto<usize> {}
 
+impl<H, T> StoreLayoutDump for InlineBumpStore<H, T>
+where
+    H: Copy + TryInto<usize>,
+{
+    fn capacity(&self) -> usize {
+        Self::memory_layout().size()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        Some(Self::into_offset(self.watermark.get()))
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        let watermark = Self::into_offset(self.watermark.get());
+
+        //  The boundaries of the blocks of memory are not tracked, hence all memory below the watermark is used.
+        visit_watermarked(watermark, self.capacity(), visitor);
+    }
+}
+
 impl<H, T> fmt::Debug for InlineBumpStore<H, T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         let layout = Layout::new::<T>();
@@ -310,3 +391,90 @@ where
         Ok((result, new_layout.size()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestStore = InlineBumpStore<u8, [u64; 4]>;
+
+    #[test]
+    fn rewind() {
+        let mut store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (first, _) = Store::allocate(&store, layout).unwrap();
+
+        let checkpoint = store.checkpoint();
+
+        let (second, _) = Store::allocate(&store, layout).unwrap();
+        Store::allocate(&store, layout).unwrap();
+
+        store.rewind(checkpoint);
+
+        let (third, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(second, third);
+
+        store.reset();
+
+        let (fourth, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(first, fourth);
+    }
+
+    #[test]
+    fn reclaim_last() {
+        let store = TestStore::default();
+        let layout = Layout::new::<u64>();
+
+        let (first, _) = Store::allocate(&store, layout).unwrap();
+        let (second, _) = Store::allocate(&store, layout).unwrap();
+
+        //  Not the last allocation, nothing to reclaim.
+        unsafe { Store::deallocate(&store, first, layout) };
+
+        unsafe { Store::deallocate(&store, second, layout) };
+
+        let (third, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(second, third);
+    }
+
+    #[test]
+    fn grow_shrink_in_place() {
+        let store = TestStore::default();
+        let (small, large) = (Layout::new::<u32>(), Layout::new::<[u32; 6]>());
+
+        let (first, _) = Store::allocate(&store, small).unwrap();
+        let (second, _) = Store::allocate(&store, small).unwrap();
+
+        let (second, size) = unsafe { Store::grow(&store, second, small, large).unwrap() };
+
+        assert_eq!(4, second);
+        assert_eq!(24, size);
+
+        let (second, size) = unsafe { Store::shrink(&store, second, large, small).unwrap() };
+
+        assert_eq!(4, second);
+        assert_eq!(4, size);
+
+        //  Not the last allocation, hence relocated.
+        let (first, _) = unsafe { Store::grow(&store, first, small, Layout::new::<u64>()).unwrap() };
+
+        assert_eq!(8, first);
+    }
+
+    #[test]
+    fn vec_growth() {
+        use crate::collection::StoreVec;
+
+        let mut v = StoreVec::<u8, TestStore>::new_in(TestStore::default());
+
+        for i in 0..32 {
+            v.push(i);
+        }
+
+        assert_eq!(32, v.len());
+    }
+} // mod tests
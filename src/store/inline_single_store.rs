@@ -93,7 +93,7 @@ unsafe impl<T> const StoreSingle for InlineSingleStore<T> {
         _new_layout: Layout,
     ) -> Result<(Self::Handle, usize), AllocError> {
         debug_assert!(
-            _new_layout.size() >= _old_layout.size(),
+            _new_layout.size() <= _old_layout.size(),
             "_new_layout must have a smaller size than _old_layout"
         );
 
@@ -187,3 +187,24 @@ impl<T> InlineSingleStore<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shrink() {
+        let mut store = InlineSingleStore::<[u64; 4]>::new();
+
+        let (old_layout, new_layout) = (Layout::new::<[u64; 4]>(), Layout::new::<u64>());
+
+        let (handle, _) = StoreSingle::allocate(&mut store, old_layout).unwrap();
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `old_layout`, and is still valid.
+        //  -   `new_layout` is smaller than `old_layout`.
+        let (_, size) = unsafe { StoreSingle::shrink(&mut store, handle, old_layout, new_layout).unwrap() };
+
+        assert_eq!(32, size);
+    }
+} // mod tests
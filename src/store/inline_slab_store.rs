@@ -0,0 +1,630 @@
+//! A simple "slab allocator" Store.
+//!
+//! This store is suitable for most containers -- such as `Box`, `BTreeMap`, `HashMap`, `List`, and `Vec` -- and unlike
+//! `InlineBumpStore` it reuses the memory of deallocated blocks, making it suitable for long-lived containers with a
+//! lot of churn, such as `LinkedList` or `SkipList`.
+//!
+//! Blocks are rounded up to a power-of-2 size class, and each size class maintains an intrusive free list of its
+//! deallocated blocks. Blocks never move from one size class to another, hence a workload whose distribution of sizes
+//! shifts over time may still exhaust the store.
+
+use core::{
+    alloc::{AllocError, Layout},
+    array,
+    cell::{Cell, UnsafeCell},
+    cmp, fmt,
+    mem::{self, MaybeUninit},
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::{
+    interface::{Store, StoreDangling, StoreSingle, StoreStable},
+    store::{Region, RegionKind, StoreLayoutDump},
+};
+
+/// An implementation of `Store` providing a single, inline, block of memory, carved into size classes.
+///
+/// Generic parameters:
+///
+/// -   `H` is the handle type, it must convertible to and from `usize`.
+/// -   The block of memory is aligned and sized as per `T`.
+pub struct InlineSlabStore<H, T> {
+    watermark: Cell<H>,
+    //  The head of the free list of each size class, or the offset of the end of `memory` if the list is empty.
+    //
+    //  The free blocks of a size class are linked together by storing the handle of the next free block at the very
+    //  beginning of each free block.
+    free_lists: [Cell<H>; NUMBER_CLASSES],
+    memory: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<H, T> InlineSlabStore<H, T>
+where
+    H: Copy + TryFrom<usize>,
+{
+    fn new() -> Result<Self, AllocError> {
+        let end = Self::from_offset(Self::memory_layout().size())?;
+
+        let watermark = Cell::new(Self::from_offset(0)?);
+        let free_lists = array::from_fn(|_| Cell::new(end));
+        let memory = UnsafeCell::new(MaybeUninit::uninit());
+
+        Ok(Self {
+            watermark,
+            free_lists,
+            memory,
+        })
+    }
+}
+
+impl<H, T> Default for InlineSlabStore<H, T>
+where
+    H: Copy + TryFrom<usize>,
+{
+    fn default() -> Self {
+        Self::new().expect("Size of `T` to be representable by `H`")
+    }
+}
+
+//  Cannot be const, because TryFrom is not marked #[const_trait].
+unsafe impl<H, T> StoreDangling for InlineSlabStore<H, T>
+where
+    H: Copy + TryFrom<usize>,
+{
+    type Handle = H;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let layout = Self::memory_layout();
+
+        if alignment.as_usize() > layout.align() {
+            return Err(AllocError);
+        }
+
+        Self::from_offset(alignment.as_usize())
+    }
+}
+
+unsafe impl<H, T> Store for InlineSlabStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let (class, size) = Self::size_class(layout)?;
+
+        if let Some(handle) = self.pop_free(class) {
+            return Ok((handle, size));
+        }
+
+        let (result, new_watermark) = Self::compute_offset(self.watermark.get(), size)?;
+        self.watermark.set(new_watermark);
+
+        Ok((result, size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        let class = Self::size_class(layout);
+
+        debug_assert!(class.is_ok());
+
+        //  Safety:
+        //  -   `layout` fits the block of memory associated with `handle`, as per pre-conditions, and computing the
+        //      size class of this layout succeeded when allocating.
+        let (class, _) = unsafe { class.unwrap_unchecked() };
+
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        //  -   `class` is the size class of the block of memory associated with `handle`.
+        unsafe { self.push_free(class, handle) };
+    }
+
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        debug_assert!(Self::into_offset(handle) <= Self::memory_layout().size());
+
+        let offset = Self::into_offset(handle);
+        let pointer = self.memory.get() as *mut u8;
+
+        //  Safety:
+        //  -   `offset` is within bounds of `self.memory`, as `handle` was allocated by `self` as per pre-conditions.
+        let pointer = unsafe { pointer.add(offset) };
+
+        //  Safety:
+        //  -   `pointer` is non null as `self` is non null.
+        unsafe { NonNull::new_unchecked(pointer) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.resize(handle, old_layout, new_layout, old_layout.size()) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.resize(handle, old_layout, new_layout, new_layout.size()) }
+    }
+}
+
+unsafe impl<H, T> StoreSingle for InlineSlabStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, as long as `self` doesn't move.
+unsafe impl<H, T> StoreStable for InlineSlabStore<H, T> where H: Copy + TryFrom<usize> + TryInto<usize> {}
+
+impl<H, T> StoreLayoutDump for InlineSlabStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn capacity(&self) -> usize {
+        Self::memory_layout().size()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        Some(Self::into_offset(self.watermark.get()))
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        let watermark = Self::into_offset(self.watermark.get());
+
+        let mut offset = 0;
+
+        //  The boundaries of the blocks of memory in use are not tracked, hence any memory below the watermark which is
+        //  not part of a free list is used.
+        while let Some((free, size)) = self.next_free(offset) {
+            if free > offset {
+                visitor(Region {
+                    offset,
+                    size: free - offset,
+                    kind: RegionKind::Used,
+                });
+            }
+
+            visitor(Region {
+                offset: free,
+                size,
+                kind: RegionKind::Free,
+            });
+
+            offset = free + size;
+        }
+
+        if watermark > offset {
+            visitor(Region {
+                offset,
+                size: watermark - offset,
+                kind: RegionKind::Used,
+            });
+        }
+
+        if self.capacity() > watermark {
+            visitor(Region {
+                offset: watermark,
+                size: self.capacity() - watermark,
+                kind: RegionKind::Free,
+            });
+        }
+    }
+}
+
+impl<H, T> fmt::Debug for InlineSlabStore<H, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let layout = Layout::new::<T>();
+
+        f.debug_struct("InlineSlabStore")
+            .field("size", &layout.size())
+            .field("align", &layout.align())
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  The number of size classes, one per power of 2.
+const NUMBER_CLASSES: usize = usize::BITS as usize;
+
+impl<H, T> InlineSlabStore<H, T> {
+    #[inline(always)]
+    const fn memory_layout() -> Layout {
+        Layout::new::<T>()
+    }
+
+    //  Returns the size class index, and the size of the blocks of this class, for a given layout.
+    //
+    //  Each block must be large enough to store the link to the next free block, and any block of a given size class
+    //  is at least aligned to the minimum of its size and the alignment of `memory`.
+    fn size_class(layout: Layout) -> Result<(usize, usize), AllocError> {
+        let memory = Self::memory_layout();
+
+        if layout.align() > memory.align() {
+            //  Even if the memory block was aligned for the current address of `self.memory`, moving `self` would risk
+            //  breaking this alignment.
+
+            return Err(AllocError);
+        }
+
+        let minimum = cmp::max(mem::size_of::<H>(), 1);
+        let size = cmp::max(cmp::max(layout.size(), layout.align()), minimum);
+
+        let Some(size) = size.checked_next_power_of_two() else {
+            return Err(AllocError);
+        };
+
+        if size > memory.size() {
+            return Err(AllocError);
+        }
+
+        Ok((size.trailing_zeros() as usize, size))
+    }
+}
+
+impl<H, T> InlineSlabStore<H, T>
+where
+    H: TryFrom<usize>,
+{
+    #[inline(always)]
+    fn from_offset(offset: usize) -> Result<H, AllocError> {
+        debug_assert!(offset <= Self::memory_layout().size());
+
+        offset.try_into().map_err(|_| AllocError)
+    }
+}
+
+impl<H, T> InlineSlabStore<H, T>
+where
+    H: TryInto<usize>,
+{
+    #[inline(always)]
+    fn into_offset(handle: H) -> usize {
+        let offset = handle.try_into();
+
+        debug_assert!(offset.is_ok());
+
+        //  Safety:
+        //  -   `handle` was created from `usize`, hence converting back always succeeds.
+        unsafe { offset.unwrap_unchecked() }
+    }
+}
+
+impl<H, T> InlineSlabStore<H, T>
+where
+    H: TryFrom<usize> + TryInto<usize>,
+{
+    //  Returns the offset and new watermark of a newly carved block of `size` bytes, `size` being a power of 2.
+    fn compute_offset(watermark: H, size: usize) -> Result<(H, H), AllocError> {
+        debug_assert!(size.is_power_of_two());
+
+        let watermark = Self::into_offset(watermark);
+        let memory = Self::memory_layout();
+
+        let aligned = {
+            //  Since the alignment is always a power of 2, aligning to the next multiple of the alignment can be done
+            //  with this one simple trick.
+            let alignment_mask = cmp::min(size, memory.align()) - 1;
+
+            (watermark + alignment_mask) & !alignment_mask
+        };
+
+        let new_watermark = aligned + size;
+
+        if new_watermark > memory.size() {
+            return Err(AllocError);
+        }
+
+        let aligned = Self::from_offset(aligned)?;
+        let new_watermark = Self::from_offset(new_watermark)?;
+
+        Ok((aligned, new_watermark))
+    }
+}
+
+impl<H, T> InlineSlabStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    //  Pops the first free block of the size class, if any.
+    fn pop_free(&self, class: usize) -> Option<H> {
+        let head = self.free_lists[class].get();
+
+        if Self::into_offset(head) == Self::memory_layout().size() {
+            return None;
+        }
+
+        //  Safety:
+        //  -   `head` was allocated by `self`, then deallocated, and is part of the free list.
+        let pointer = unsafe { Store::resolve(self, head) };
+
+        //  Safety:
+        //  -   `pointer` is valid for reads of `H`, as each block of memory is at least `mem::size_of::<H>()` bytes.
+        //  -   `pointer` points to an initialized `H`, as written by `push_free`.
+        let next = unsafe { ptr::read_unaligned(pointer.as_ptr() as *const H) };
+
+        self.free_lists[class].set(next);
+
+        Some(head)
+    }
+
+    //  Pushes the block of memory associated with `handle` to the front of the free list of the size class.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must have been allocated by `self`.
+    //  -   `handle` must still be valid.
+    //  -   `class` must be the size class of the block of memory associated with `handle`.
+    unsafe fn push_free(&self, class: usize, handle: H) {
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        let pointer = unsafe { Store::resolve(self, handle) };
+
+        let next = self.free_lists[class].get();
+
+        //  Safety:
+        //  -   `pointer` is valid for writes of `H`, as each block of memory is at least `mem::size_of::<H>()` bytes.
+        //  -   `pointer` is exclusive, as the block of memory is being deallocated.
+        unsafe { ptr::write_unaligned(pointer.as_ptr() as *mut H, next) };
+
+        self.free_lists[class].set(handle);
+    }
+
+    //  Returns the offset and size of the free block with the lowest offset greater than or equal to `from`, if any.
+    //
+    //  This walks all free lists, and is therefore only suitable for diagnostics.
+    fn next_free(&self, from: usize) -> Option<(usize, usize)> {
+        let end = Self::memory_layout().size();
+
+        let mut result: Option<(usize, usize)> = None;
+
+        for (class, head) in self.free_lists.iter().enumerate() {
+            let mut handle = head.get();
+
+            while Self::into_offset(handle) != end {
+                let offset = Self::into_offset(handle);
+
+                if offset >= from && result.map_or(true, |(lowest, _)| offset < lowest) {
+                    result = Some((offset, 1 << class));
+                }
+
+                //  Safety:
+                //  -   `handle` was allocated by `self`, then deallocated, and is part of the free list.
+                let pointer = unsafe { Store::resolve(self, handle) };
+
+                //  Safety:
+                //  -   `pointer` is valid for reads of `H`, as each block of memory is at least `mem::size_of::<H>()`
+                //      bytes.
+                //  -   `pointer` points to an initialized `H`, as written by `push_free`.
+                handle = unsafe { ptr::read_unaligned(pointer.as_ptr() as *const H) };
+            }
+        }
+
+        result
+    }
+
+    //  Common part of `grow` and `shrink`, `copy` being the number of bytes to preserve.
+    //
+    //  #   Safety
+    //
+    //  -   As per `grow` or `shrink`.
+    //  -   `copy` must be less than or equal to both `old_layout.size()` and `new_layout.size()`.
+    unsafe fn resize(
+        &self,
+        handle: H,
+        old_layout: Layout,
+        new_layout: Layout,
+        copy: usize,
+    ) -> Result<(H, usize), AllocError> {
+        debug_assert!(copy <= old_layout.size() && copy <= new_layout.size());
+
+        let old_class = Self::size_class(old_layout);
+
+        debug_assert!(old_class.is_ok());
+
+        //  Safety:
+        //  -   `old_layout` fits the block of memory associated with `handle`, as per pre-conditions, and computing the
+        //      size class of this layout succeeded when allocating.
+        let (old_class, old_size) = unsafe { old_class.unwrap_unchecked() };
+
+        let (new_class, new_size) = Self::size_class(new_layout)?;
+
+        //  Within the same size class, the block of memory already fits.
+        if old_class == new_class {
+            return Ok((handle, new_size));
+        }
+
+        let result = self.resize_by_relocation(handle, old_class, new_layout, copy);
+
+        //  A block of memory of a larger size class also fits, and is sufficiently aligned, hence shrinking never
+        //  fails, even if the smaller size class is exhausted.
+        if result.is_err() && new_class < old_class {
+            return Ok((handle, old_size));
+        }
+
+        result
+    }
+
+    //  Slow part of `resize`.
+    #[inline(never)]
+    fn resize_by_relocation(
+        &self,
+        handle: H,
+        old_class: usize,
+        new_layout: Layout,
+        copy: usize,
+    ) -> Result<(H, usize), AllocError> {
+        let (result, new_size) = Store::allocate(self, new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (new, old) = unsafe { (Store::resolve(self, result), Store::resolve(self, handle)) };
+
+        //  Safety:
+        //  -   `old` is valid for `copy` bytes, as per pre-conditions.
+        //  -   `new` is valid for `copy` bytes, as per pre-conditions.
+        //  -   `old` and `new` are at least 1-byte aligned.
+        //  -   `old` and `new` point to non-overlapping areas, since both are live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), copy) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        //  -   `old_class` is the size class of the block of memory associated with `handle`.
+        unsafe { self.push_free(old_class, handle) };
+
+        Ok((result, new_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collection::LinkedList;
+
+    use super::*;
+
+    type TestStore = InlineSlabStore<u16, [u64; 16]>;
+
+    #[test]
+    fn reuse_after_deallocate() {
+        let store = TestStore::default();
+        let layout = Layout::new::<[u64; 4]>();
+
+        let (first, size) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(32, size);
+
+        unsafe { Store::deallocate(&store, first, layout) };
+
+        let (second, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn shrink_when_full() {
+        let store = TestStore::default();
+        let (old_layout, new_layout) = (Layout::new::<[u64; 8]>(), Layout::new::<u64>());
+
+        let (first, _) = Store::allocate(&store, old_layout).unwrap();
+        let (_second, _) = Store::allocate(&store, old_layout).unwrap();
+
+        Store::allocate(&store, new_layout).unwrap_err();
+
+        let (shrunk, size) = unsafe { Store::shrink(&store, first, old_layout, new_layout).unwrap() };
+
+        assert_eq!(first, shrunk);
+        assert_eq!(64, size);
+    }
+
+    #[test]
+    fn exhaustion() {
+        let store = TestStore::default();
+        let layout = Layout::new::<[u64; 8]>();
+
+        Store::allocate(&store, layout).unwrap();
+        Store::allocate(&store, layout).unwrap();
+        Store::allocate(&store, layout).unwrap_err();
+    }
+
+    #[test]
+    fn grow_preserves_content() {
+        let store = TestStore::default();
+        let old_layout = Layout::new::<[u8; 3]>();
+        let new_layout = Layout::new::<[u8; 20]>();
+
+        let (handle, _) = Store::allocate(&store, old_layout).unwrap();
+
+        unsafe { ptr::copy_nonoverlapping([1u8, 2, 3].as_ptr(), Store::resolve(&store, handle).as_ptr(), 3) };
+
+        let (handle, size) = unsafe { Store::grow(&store, handle, old_layout, new_layout).unwrap() };
+
+        assert_eq!(32, size);
+
+        let content = unsafe { *(Store::resolve(&store, handle).as_ptr() as *const [u8; 3]) };
+
+        assert_eq!([1, 2, 3], content);
+    }
+
+    #[test]
+    fn list_churn() {
+        let mut list: LinkedList<u32, TestStore> = LinkedList::new();
+
+        for i in 0..1_000 {
+            list.try_push_back(i).unwrap();
+            list.try_push_back(i + 1).unwrap();
+
+            assert_eq!(Some(i), list.pop_back().map(|n| n - 1));
+            assert_eq!(Some(i), list.pop_back());
+        }
+
+        assert!(list.is_empty());
+    }
+} // mod tests
@@ -0,0 +1,901 @@
+//! A "Two-Level Segregated Fit" Store.
+//!
+//! This store is suitable for most containers -- such as `Box`, `BTreeMap`, `HashMap`, `List`, and `Vec` -- and
+//! reclaims the memory of deallocated blocks, with a constant worst-case time for all operations, making it suitable
+//! for real-time contexts.
+//!
+//! The block of memory is carved into blocks, each preceeded by a header containing its size, whether it is free, and
+//! the offset of the physically previous block, so that free blocks can be coalesced with both their neighbours.
+//!
+//! The free blocks are segregated by size into a two-level array of free lists, and a two-level bitmap tracks which of
+//! the free lists are non-empty, allowing to find a suitable free block in `O(1)`.
+//!
+//! The state of the allocator is entirely contained within its block of memory:
+//!
+//! -   The headers of the blocks precede each block.
+//! -   The free blocks of each size class are linked together in a doubly-linked list, storing the handles to the next
+//!     and previous free blocks at the very beginning of each free block.
+//! -   The heads of the free lists and the second-level bitmap are stored at the very end of the block of memory.
+
+use core::{
+    alloc::{AllocError, Layout},
+    cell::{Cell, UnsafeCell},
+    cmp, fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::{
+    interface::{Store, StoreDangling, StoreSingle, StoreStable},
+    store::{Region, RegionKind, StoreLayoutDump},
+};
+
+/// An implementation of `Store` providing a single, inline, block of memory, managed as a TLSF allocator.
+///
+/// Generic parameters:
+///
+/// -   `H` is the handle type, it must convertible to and from `usize`.
+/// -   The block of memory is aligned and sized as per `T`.
+///
+/// Each block is preceeded by a header, and the size of blocks is rounded up to a multiple of the granule, which is
+/// the larger of twice the size of `H` and the alignment of `T`.
+pub struct InlineTlsfStore<H, T> {
+    //  The first-level bitmap, bit `i` is set if any of the free lists of the first-level `i` is non-empty.
+    first_level: Cell<usize>,
+    memory: UnsafeCell<MaybeUninit<T>>,
+    _marker: PhantomData<fn(H) -> H>,
+}
+
+impl<H, T> InlineTlsfStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn new() -> Result<Self, AllocError> {
+        let _ = Self::from_offset(Self::memory_layout().size())?;
+
+        //  The control structures must fit within memory, or they would be written out of its bounds.
+        if Self::CONTROL_SIZE > Self::memory_layout().size() {
+            return Err(AllocError);
+        }
+
+        let first_level = Cell::new(0);
+        let memory = UnsafeCell::new(MaybeUninit::uninit());
+        let _marker = PhantomData;
+
+        let this = Self {
+            first_level,
+            memory,
+            _marker,
+        };
+
+        for first in 0..Self::FIRST_LEVEL_COUNT {
+            this.set_second_level(first, 0);
+
+            for second in 0..SECOND_LEVEL_COUNT {
+                this.set_head(first, second, Self::END);
+            }
+        }
+
+        if Self::POOL_SIZE >= 2 * Self::GRANULE {
+            let block = Self::GRANULE;
+            let size = Self::POOL_SIZE - Self::GRANULE;
+
+            this.set_previous(block, Self::END);
+            this.set_size(block, size, true);
+            this.insert(block, size);
+        }
+
+        Ok(this)
+    }
+}
+
+impl<H, T> Default for InlineTlsfStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn default() -> Self {
+        Self::new().expect("Size of `T` to be representable by `H`, and large enough for the control structures")
+    }
+}
+
+//  Cannot be const, because TryFrom is not marked #[const_trait].
+unsafe impl<H, T> StoreDangling for InlineTlsfStore<H, T>
+where
+    H: Copy + TryFrom<usize>,
+{
+    type Handle = H;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let layout = Self::memory_layout();
+
+        if alignment.as_usize() > layout.align() {
+            return Err(AllocError);
+        }
+
+        Self::from_offset(alignment.as_usize())
+    }
+}
+
+unsafe impl<H, T> Store for InlineTlsfStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let size = Self::adjust_size(layout)?;
+
+        let Some((block, block_size)) = self.find_free(size) else {
+            return Err(AllocError);
+        };
+
+        debug_assert!(block_size >= size);
+
+        self.remove(block, block_size);
+
+        let block_size = self.split(block, block_size, size);
+
+        Ok((Self::from_offset(block)?, block_size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, _layout: Layout) {
+        let block = Self::into_offset(handle);
+        let (_, size, _) = self.header(block);
+
+        debug_assert!(size >= _layout.size());
+
+        self.release(block, size);
+    }
+
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        debug_assert!(Self::into_offset(handle) <= Self::memory_layout().size());
+
+        let offset = Self::into_offset(handle);
+
+        //  Safety:
+        //  -   `offset` is within bounds of `self.memory`, as `handle` was allocated by `self` as per pre-conditions.
+        unsafe { self.at(offset) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        let new_size = Self::adjust_size(new_layout)?;
+
+        let block = Self::into_offset(handle);
+        let (_, size, _) = self.header(block);
+
+        if size >= new_size {
+            return Ok((handle, size));
+        }
+
+        //  As an optimization, if the next block is free and large enough, growth may occur _in place_.
+        if let Some(next) = Self::next_block(block, size) {
+            let (_, next_size, next_free) = self.header(next);
+            let merged_size = size + Self::GRANULE + next_size;
+
+            if next_free && merged_size >= new_size {
+                self.remove(next, next_size);
+
+                if let Some(after) = Self::next_block(block, merged_size) {
+                    self.set_previous(after, block);
+                }
+
+                let size = self.split(block, merged_size, new_size);
+
+                return Ok((handle, size));
+            }
+        }
+
+        self.grow_by_relocation(handle, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        let new_size = Self::adjust_size(new_layout)?;
+
+        let block = Self::into_offset(handle);
+        let (_, size, _) = self.header(block);
+
+        let size = self.split(block, size, new_size);
+
+        Ok((handle, size))
+    }
+}
+
+unsafe impl<H, T> StoreSingle for InlineTlsfStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, as long as `self` doesn't move.
+unsafe impl<H, T> StoreStable for InlineTlsfStore<H, T> where H: Copy + TryFrom<usize> + TryInto<usize> {}
+
+impl<H, T> StoreLayoutDump for InlineTlsfStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn capacity(&self) -> usize {
+        Self::memory_layout().size()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        None
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        let mut block = (Self::POOL_SIZE >= 2 * Self::GRANULE).then_some(Self::GRANULE);
+
+        //  The blocks tile the pool, each preceeded by its header.
+        while let Some(offset) = block {
+            let (_, size, free) = self.header(offset);
+
+            visitor(Region {
+                offset: offset - Self::GRANULE,
+                size: Self::GRANULE,
+                kind: RegionKind::Overhead,
+            });
+
+            visitor(Region {
+                offset,
+                size,
+                kind: if free { RegionKind::Free } else { RegionKind::Used },
+            });
+
+            block = Self::next_block(offset, size);
+        }
+
+        //  The tail of the pool, if not a whole granule, and the control structures.
+        let pool = if Self::POOL_SIZE >= 2 * Self::GRANULE {
+            Self::POOL_SIZE
+        } else {
+            0
+        };
+
+        if self.capacity() > pool {
+            visitor(Region {
+                offset: pool,
+                size: self.capacity() - pool,
+                kind: RegionKind::Overhead,
+            });
+        }
+    }
+}
+
+impl<H, T> fmt::Debug for InlineTlsfStore<H, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let layout = Layout::new::<T>();
+
+        f.debug_struct("InlineTlsfStore")
+            .field("size", &layout.size())
+            .field("align", &layout.align())
+            .field("pool", &Self::POOL_SIZE)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  The number of free lists per first-level, as a power of 2.
+const SECOND_LEVEL_LOG2: usize = 3;
+
+//  The number of free lists per first-level.
+const SECOND_LEVEL_COUNT: usize = 1 << SECOND_LEVEL_LOG2;
+
+impl<H, T> InlineTlsfStore<H, T> {
+    //  The granule of the blocks: all blocks and headers are sized as a multiple of this granule.
+    //
+    //  It must be large enough to hold 2 handles -- for the header and the links of the free lists -- and sufficiently
+    //  aligned that all blocks are aligned as per `T`.
+    const GRANULE: usize = {
+        let links = 2 * mem::size_of::<H>();
+        let align = mem::align_of::<T>();
+
+        if links > align {
+            links.next_power_of_two()
+        } else {
+            align
+        }
+    };
+
+    //  The number of first-levels required to cover the entire memory.
+    const FIRST_LEVEL_COUNT: usize = Self::mapping_insert(Self::memory_layout().size()).0 + 1;
+
+    //  The size of the heads of the free lists and of the second-level bitmaps.
+    const CONTROL_SIZE: usize = Self::FIRST_LEVEL_COUNT * (SECOND_LEVEL_COUNT * mem::size_of::<H>() + 1);
+
+    //  The offset of the heads of the free lists, followed by the second-level bitmaps, at the end of memory.
+    //
+    //  If memory is too small for the control structures, `new` rejects the geometry.
+    const CONTROL_OFFSET: usize = Self::memory_layout().size().saturating_sub(Self::CONTROL_SIZE);
+
+    //  The size of the pool, carved into blocks, which starts at the beginning of memory.
+    const POOL_SIZE: usize = Self::CONTROL_OFFSET / Self::GRANULE * Self::GRANULE;
+
+    //  The offset marking the end of a free list, or the absence of a previous block.
+    const END: usize = Self::memory_layout().size();
+
+    #[inline(always)]
+    const fn memory_layout() -> Layout {
+        Layout::new::<T>()
+    }
+
+    //  Returns the size of the block to allocate for `layout`.
+    fn adjust_size(layout: Layout) -> Result<usize, AllocError> {
+        if layout.align() > Self::memory_layout().align() {
+            //  Even if the memory block was aligned for the current address of `self.memory`, moving `self` would risk
+            //  breaking this alignment.
+
+            return Err(AllocError);
+        }
+
+        let size = cmp::max(layout.size(), 1);
+        let size = (size - 1) / Self::GRANULE * Self::GRANULE + Self::GRANULE;
+
+        if size > Self::POOL_SIZE {
+            return Err(AllocError);
+        }
+
+        Ok(size)
+    }
+
+    //  Returns the indexes of the free list in which a free block of `size` bytes belongs.
+    const fn mapping_insert(size: usize) -> (usize, usize) {
+        let units = size / Self::GRANULE;
+
+        if units < SECOND_LEVEL_COUNT {
+            return (0, units);
+        }
+
+        let log2 = units.ilog2() as usize;
+
+        let first = log2 - SECOND_LEVEL_LOG2 + 1;
+        let second = (units >> (log2 - SECOND_LEVEL_LOG2)) - SECOND_LEVEL_COUNT;
+
+        (first, second)
+    }
+
+    //  Returns the indexes of the first free list in which all free blocks are at least `size` bytes.
+    fn mapping_search(size: usize) -> (usize, usize) {
+        let units = size / Self::GRANULE;
+
+        if units < SECOND_LEVEL_COUNT {
+            return (0, units);
+        }
+
+        let log2 = units.ilog2() as usize;
+        let round = (1 << (log2 - SECOND_LEVEL_LOG2)) - 1;
+
+        Self::mapping_insert((units + round) * Self::GRANULE)
+    }
+
+    //  Returns the offset of the block following the block at `offset` of `size`, if any.
+    fn next_block(offset: usize, size: usize) -> Option<usize> {
+        let next = offset + size + Self::GRANULE;
+
+        (next <= Self::POOL_SIZE).then_some(next)
+    }
+
+    //  Returns a pointer to the byte at `offset` in the memory.
+    //
+    //  #   Safety
+    //
+    //  -   `offset` must be within the bounds of `self.memory`.
+    #[inline(always)]
+    unsafe fn at(&self, offset: usize) -> NonNull<u8> {
+        let pointer = self.memory.get() as *mut u8;
+
+        //  Safety:
+        //  -   `offset` is within bounds of `self.memory`, as per pre-conditions.
+        let pointer = unsafe { pointer.add(offset) };
+
+        //  Safety:
+        //  -   `pointer` is non null as `self` is non null.
+        unsafe { NonNull::new_unchecked(pointer) }
+    }
+
+    fn second_level(&self, first: usize) -> u8 {
+        debug_assert!(first < Self::FIRST_LEVEL_COUNT);
+
+        let offset = Self::CONTROL_OFFSET + Self::FIRST_LEVEL_COUNT * SECOND_LEVEL_COUNT * mem::size_of::<H>() + first;
+
+        //  Safety:
+        //  -   `offset` is within the control area, at the end of memory.
+        //  -   The bitmap was initialized on construction.
+        unsafe { self.at(offset).as_ptr().read() }
+    }
+
+    fn set_second_level(&self, first: usize, bitmap: u8) {
+        debug_assert!(first < Self::FIRST_LEVEL_COUNT);
+
+        let offset = Self::CONTROL_OFFSET + Self::FIRST_LEVEL_COUNT * SECOND_LEVEL_COUNT * mem::size_of::<H>() + first;
+
+        //  Safety:
+        //  -   `offset` is within the control area, at the end of memory.
+        unsafe { self.at(offset).as_ptr().write(bitmap) };
+    }
+
+    //  Returns the indexes of the first non-empty free list at or after `first` and `second`, if any.
+    fn find_suitable(&self, first: usize, second: usize) -> Option<(usize, usize)> {
+        if first >= Self::FIRST_LEVEL_COUNT {
+            return None;
+        }
+
+        let second_map = self.second_level(first) & (u8::MAX << second);
+
+        if second_map != 0 {
+            return Some((first, second_map.trailing_zeros() as usize));
+        }
+
+        let first_mask = usize::MAX.checked_shl(first as u32 + 1).unwrap_or(0);
+        let first_map = self.first_level.get() & first_mask;
+
+        if first_map == 0 {
+            return None;
+        }
+
+        let first = first_map.trailing_zeros() as usize;
+        let second = self.second_level(first).trailing_zeros() as usize;
+
+        Some((first, second))
+    }
+}
+
+impl<H, T> InlineTlsfStore<H, T>
+where
+    H: TryFrom<usize>,
+{
+    #[inline(always)]
+    fn from_offset(offset: usize) -> Result<H, AllocError> {
+        debug_assert!(offset <= Self::memory_layout().size());
+
+        offset.try_into().map_err(|_| AllocError)
+    }
+}
+
+impl<H, T> InlineTlsfStore<H, T>
+where
+    H: TryInto<usize>,
+{
+    #[inline(always)]
+    fn into_offset(handle: H) -> usize {
+        let offset = handle.try_into();
+
+        debug_assert!(offset.is_ok());
+
+        //  Safety:
+        //  -   `handle` was created from `usize`, hence converting back always succeeds.
+        unsafe { offset.unwrap_unchecked() }
+    }
+}
+
+impl<H, T> InlineTlsfStore<H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    //  Reads the `H` at `offset`.
+    fn read(&self, offset: usize) -> usize {
+        //  Safety:
+        //  -   `offset` is within bounds, as all callers only pass offsets to headers, blocks, and control.
+        let pointer = unsafe { self.at(offset) }.as_ptr() as *const H;
+
+        //  Safety:
+        //  -   `pointer` is valid for reads of `H`.
+        //  -   `pointer` points to an initialized `H`, as all reads are preceeded by writes.
+        let value = unsafe { ptr::read_unaligned(pointer) };
+
+        Self::into_offset(value)
+    }
+
+    //  Writes the `H` at `offset`.
+    fn write(&self, offset: usize, value: usize) {
+        //  Safety:
+        //  -   `offset` is within bounds, as all callers only pass offsets to headers, blocks, and control.
+        let pointer = unsafe { self.at(offset) }.as_ptr() as *mut H;
+
+        //  All values written are offsets or sizes within memory, which were checked to be representable on
+        //  construction.
+        let Ok(value) = Self::from_offset(value) else {
+            unreachable!("All offsets within memory to be representable")
+        };
+
+        //  Safety:
+        //  -   `pointer` is valid for writes of `H`.
+        //  -   `pointer` is not in use by any block.
+        unsafe { ptr::write_unaligned(pointer, value) };
+    }
+
+    //  Returns the (previous block, size, is free) triplet stored in the header of the block at `offset`.
+    fn header(&self, offset: usize) -> (usize, usize, bool) {
+        let header = offset - Self::GRANULE;
+
+        let previous = self.read(header);
+        let size = self.read(header + mem::size_of::<H>());
+
+        (previous, size & !1, size & 1 != 0)
+    }
+
+    fn set_previous(&self, offset: usize, previous: usize) {
+        self.write(offset - Self::GRANULE, previous);
+    }
+
+    fn set_size(&self, offset: usize, size: usize, free: bool) {
+        debug_assert!(size % Self::GRANULE == 0);
+
+        self.write(offset - Self::GRANULE + mem::size_of::<H>(), size | (free as usize));
+    }
+
+    fn head(&self, first: usize, second: usize) -> usize {
+        self.read(Self::head_offset(first, second))
+    }
+
+    fn set_head(&self, first: usize, second: usize, offset: usize) {
+        self.write(Self::head_offset(first, second), offset);
+    }
+
+    fn head_offset(first: usize, second: usize) -> usize {
+        Self::CONTROL_OFFSET + (first * SECOND_LEVEL_COUNT + second) * mem::size_of::<H>()
+    }
+
+    //  Returns the offset and size of a free block of at least `size` bytes, if any.
+    fn find_free(&self, size: usize) -> Option<(usize, usize)> {
+        let (first, second) = Self::mapping_search(size);
+
+        if let Some((first, second)) = self.find_suitable(first, second) {
+            let block = self.head(first, second);
+            let (_, block_size, _) = self.header(block);
+
+            return Some((block, block_size));
+        }
+
+        //  The search rounds `size` up to the next free list so that any block of this free list is suitable, hence
+        //  the blocks of the free list `size` itself belongs to are never considered. As a last resort, and to keep the
+        //  worst-case constant, only the head of this free list is checked.
+        let (first, second) = Self::mapping_insert(size);
+
+        if first >= Self::FIRST_LEVEL_COUNT {
+            return None;
+        }
+
+        let block = self.head(first, second);
+
+        if block == Self::END {
+            return None;
+        }
+
+        let (_, block_size, _) = self.header(block);
+
+        (block_size >= size).then_some((block, block_size))
+    }
+
+    //  Inserts the free block at `offset` of `size` at the front of its free list.
+    fn insert(&self, offset: usize, size: usize) {
+        let (first, second) = Self::mapping_insert(size);
+
+        let next = self.head(first, second);
+
+        self.write(offset, next);
+        self.write(offset + mem::size_of::<H>(), Self::END);
+
+        if next != Self::END {
+            self.write(next + mem::size_of::<H>(), offset);
+        }
+
+        self.set_head(first, second, offset);
+
+        self.set_second_level(first, self.second_level(first) | (1 << second));
+        self.first_level.set(self.first_level.get() | (1 << first));
+    }
+
+    //  Removes the free block at `offset` of `size` from its free list.
+    fn remove(&self, offset: usize, size: usize) {
+        let (first, second) = Self::mapping_insert(size);
+
+        let next = self.read(offset);
+        let previous = self.read(offset + mem::size_of::<H>());
+
+        if previous == Self::END {
+            self.set_head(first, second, next);
+        } else {
+            self.write(previous, next);
+        }
+
+        if next != Self::END {
+            self.write(next + mem::size_of::<H>(), previous);
+        }
+
+        if self.head(first, second) != Self::END {
+            return;
+        }
+
+        let second_level = self.second_level(first) & !(1 << second);
+
+        self.set_second_level(first, second_level);
+
+        if second_level == 0 {
+            self.first_level.set(self.first_level.get() & !(1 << first));
+        }
+    }
+
+    //  Releases the block at `offset` of `size`, coalescing it with its neighbours, if free.
+    fn release(&self, mut offset: usize, mut size: usize) {
+        let (previous, _, _) = self.header(offset);
+
+        if previous != Self::END {
+            let (_, previous_size, previous_free) = self.header(previous);
+
+            if previous_free {
+                self.remove(previous, previous_size);
+
+                offset = previous;
+                size += previous_size + Self::GRANULE;
+            }
+        }
+
+        if let Some(next) = Self::next_block(offset, size) {
+            let (_, next_size, next_free) = self.header(next);
+
+            if next_free {
+                self.remove(next, next_size);
+
+                size += next_size + Self::GRANULE;
+            }
+        }
+
+        self.set_size(offset, size, true);
+
+        if let Some(next) = Self::next_block(offset, size) {
+            self.set_previous(next, offset);
+        }
+
+        self.insert(offset, size);
+    }
+
+    //  Splits the block in use at `offset` of `size`, keeping only `target` bytes, if the remainder is large enough
+    //  to form a block on its own.
+    //
+    //  Returns the new size of the block, which is marked as in use.
+    fn split(&self, offset: usize, size: usize, target: usize) -> usize {
+        debug_assert!(size >= target);
+
+        if size - target < 2 * Self::GRANULE {
+            self.set_size(offset, size, false);
+
+            return size;
+        }
+
+        self.set_size(offset, target, false);
+
+        let remainder = offset + target + Self::GRANULE;
+        let remainder_size = size - target - Self::GRANULE;
+
+        self.set_previous(remainder, offset);
+        self.set_size(remainder, remainder_size, false);
+
+        self.release(remainder, remainder_size);
+
+        target
+    }
+
+    //  Slow part of `grow`.
+    #[inline(never)]
+    fn grow_by_relocation(&self, handle: H, old_layout: Layout, new_layout: Layout) -> Result<(H, usize), AllocError> {
+        let (result, new_size) = Store::allocate(self, new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (new, old) = unsafe { (Store::resolve(self, result), Store::resolve(self, handle)) };
+
+        //  Safety:
+        //  -   `old` is valid for `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `new` is valid for `old_layout.size()` bytes, since it is valid for `new_layout.size()` bytes and as per
+        //      pre-conditions `new_layout.size() >= old_layout.size()`.
+        //  -   `old` and `new` are at least 1-byte aligned.
+        //  -   `old` and `new` point to non-overlapping areas, since both are blocks in use.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), old_layout.size()) };
+
+        let (_, size, _) = self.header(Self::into_offset(handle));
+
+        self.release(Self::into_offset(handle), size);
+
+        Ok((result, new_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collection::{LinkedList, StoreVec};
+
+    use super::*;
+
+    type TestStore = InlineTlsfStore<u16, [u64; 128]>;
+
+    #[test]
+    fn geometry() {
+        assert_eq!(8, TestStore::GRANULE);
+        assert_eq!(6, TestStore::FIRST_LEVEL_COUNT);
+        assert_eq!(920, TestStore::POOL_SIZE);
+    }
+
+    #[test]
+    fn control_overflow() {
+        //  The control structures require 17 bytes.
+        InlineTlsfStore::<u16, [u8; 16]>::new().unwrap_err();
+        InlineTlsfStore::<u16, ()>::new().unwrap_err();
+
+        InlineTlsfStore::<u16, [u8; 17]>::new().unwrap();
+    }
+
+    #[test]
+    fn coalescing() {
+        let store = TestStore::default();
+        let layout = Layout::new::<[u64; 8]>();
+        let whole = Layout::from_size_align(TestStore::POOL_SIZE - TestStore::GRANULE, 8).unwrap();
+
+        let (a, _) = Store::allocate(&store, layout).unwrap();
+        let (b, _) = Store::allocate(&store, layout).unwrap();
+        let (c, _) = Store::allocate(&store, layout).unwrap();
+
+        Store::allocate(&store, whole).unwrap_err();
+
+        unsafe { Store::deallocate(&store, b, layout) };
+        unsafe { Store::deallocate(&store, a, layout) };
+        unsafe { Store::deallocate(&store, c, layout) };
+
+        let (whole, size) = Store::allocate(&store, whole).unwrap();
+
+        assert_eq!(a, whole);
+        assert_eq!(TestStore::POOL_SIZE - TestStore::GRANULE, size);
+    }
+
+    #[test]
+    fn grow_in_place() {
+        let store = TestStore::default();
+        let old_layout = Layout::new::<[u8; 8]>();
+        let new_layout = Layout::new::<[u8; 64]>();
+
+        let (handle, _) = Store::allocate(&store, old_layout).unwrap();
+
+        unsafe { Store::resolve(&store, handle).as_ptr().write(42) };
+
+        let (grown, size) = unsafe { Store::grow(&store, handle, old_layout, new_layout).unwrap() };
+
+        assert_eq!(handle, grown);
+        assert_eq!(64, size);
+        assert_eq!(42, unsafe { Store::resolve(&store, grown).as_ptr().read() });
+    }
+
+    #[test]
+    fn grow_by_relocation() {
+        let store = TestStore::default();
+        let old_layout = Layout::new::<[u8; 8]>();
+        let new_layout = Layout::new::<[u8; 64]>();
+
+        let (handle, _) = Store::allocate(&store, old_layout).unwrap();
+        let (_blocker, _) = Store::allocate(&store, old_layout).unwrap();
+
+        unsafe { Store::resolve(&store, handle).as_ptr().write(42) };
+
+        let (grown, size) = unsafe { Store::grow(&store, handle, old_layout, new_layout).unwrap() };
+
+        assert_ne!(handle, grown);
+        assert_eq!(64, size);
+        assert_eq!(42, unsafe { Store::resolve(&store, grown).as_ptr().read() });
+
+        //  The original block was released, and can be reused.
+        let (reused, _) = Store::allocate(&store, old_layout).unwrap();
+
+        assert_eq!(handle, reused);
+    }
+
+    #[test]
+    fn shrink_in_place() {
+        let store = TestStore::default();
+        let old_layout = Layout::new::<[u8; 64]>();
+        let new_layout = Layout::new::<[u8; 8]>();
+
+        let (handle, _) = Store::allocate(&store, old_layout).unwrap();
+        let (shrunk, size) = unsafe { Store::shrink(&store, handle, old_layout, new_layout).unwrap() };
+
+        assert_eq!(handle, shrunk);
+        assert_eq!(8, size);
+
+        let (next, _) = Store::allocate(&store, new_layout).unwrap();
+
+        assert_eq!(Into::<usize>::into(handle) + 16, next.into());
+    }
+
+    #[test]
+    fn vec_growth() {
+        let mut vec: StoreVec<u32, TestStore> = StoreVec::new();
+
+        for i in 0..100 {
+            vec.push(i);
+        }
+
+        assert_eq!(100, vec.len());
+        assert_eq!(Some(&99), vec.get(99));
+    }
+
+    #[test]
+    fn list_churn() {
+        let mut list: LinkedList<u32, TestStore> = LinkedList::new();
+
+        for i in 0..1_000 {
+            list.try_push_back(i).unwrap();
+            list.try_push_back(i + 1).unwrap();
+
+            assert_eq!(Some(i + 1), list.pop_back());
+            assert_eq!(Some(i), list.pop_back());
+        }
+
+        assert!(list.is_empty());
+    }
+} // mod tests
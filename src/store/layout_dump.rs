@@ -0,0 +1,446 @@
+//! A view of the occupancy of the memory of a store, for diagnosing exhaustion and fragmentation.
+//!
+//! When a collection fails to allocate, the occupancy of its store explains why: either the memory is genuinely full,
+//! or it is fragmented into gaps too small for the requested layout, or too much of it is spent on book-keeping.
+//!
+//! `StoreLayoutDump` exposes this occupancy as data, region by region, and `LayoutDump` draws it as an ASCII map:
+//!
+//! ```text
+//! capacity: 1024 bytes, used: 384 bytes, free: 640 bytes in 2 regions, largest: 512 bytes, overhead: 0 bytes
+//! watermark: 512 bytes
+//! ################........########................................
+//!                                 ^
+//! ```
+//!
+//! Where each character represents `capacity / 64` bytes, rounded up:
+//!
+//! -   `#` is entirely used.
+//! -   `.` is entirely free.
+//! -   `=` is entirely overhead, that is book-keeping of the store.
+//! -   `+` is a mix of the above.
+//!
+//! And `^` points to the watermark, if any.
+
+use core::fmt;
+
+/// The kind of a region of memory of a store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RegionKind {
+    /// The region is allocated, or otherwise unavailable for allocation, such as padding.
+    Used,
+    /// The region is available for allocation.
+    Free,
+    /// The region is used by the store for its own book-keeping.
+    Overhead,
+}
+
+/// A region of memory of a store, as reported by `StoreLayoutDump::visit_regions`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Region {
+    /// The offset of the region, from the start of the memory of the store.
+    pub offset: usize,
+    /// The size of the region, in bytes.
+    pub size: usize,
+    /// The kind of the region.
+    pub kind: RegionKind,
+}
+
+/// A summary of the regions of memory of a store, as computed by `StoreLayoutDump::summary`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct LayoutSummary {
+    /// The size of the memory of the store, in bytes.
+    pub capacity: usize,
+    /// Number of bytes used.
+    pub used_bytes: usize,
+    /// Number of bytes free.
+    pub free_bytes: usize,
+    /// Number of bytes used for book-keeping.
+    pub overhead_bytes: usize,
+    /// Number of free regions.
+    pub free_regions: usize,
+    /// Size of the largest free region, in bytes.
+    pub largest_free_region: usize,
+    /// Watermark of the store, if any.
+    pub watermark: Option<usize>,
+}
+
+impl LayoutSummary {
+    /// Returns the fraction of the free bytes which are not part of the largest free region.
+    ///
+    /// A value of 0 means that all free bytes are contiguous, whereas a value close to 1 means that free bytes are
+    /// scattered across many small regions.
+    pub fn fragmentation(&self) -> f64 {
+        if self.free_bytes == 0 {
+            return 0.0;
+        }
+
+        1.0 - self.largest_free_region as f64 / self.free_bytes as f64
+    }
+}
+
+/// A store able to describe the occupancy of its memory.
+///
+/// The memory of the store is described as a sequence of regions, whose offsets are relative to the start of the
+/// memory of the store.
+pub trait StoreLayoutDump {
+    /// Returns the size of the memory of the store, in bytes.
+    fn capacity(&self) -> usize;
+
+    /// Returns the watermark of the store, if any.
+    ///
+    /// The watermark is the offset past which no block of memory has been allocated since the store was created, or
+    /// last reset.
+    fn watermark(&self) -> Option<usize>;
+
+    /// Calls `visitor` with each region of the memory of the store.
+    ///
+    /// The regions are visited in increasing order of offsets, and cover the entire memory of the store, without
+    /// overlap. Adjacent used regions may or may not be merged, depending on whether the store tracks the boundaries of
+    /// its blocks of memory.
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region));
+
+    /// Returns a summary of the regions of the memory of the store.
+    fn summary(&self) -> LayoutSummary {
+        let mut summary = LayoutSummary {
+            capacity: self.capacity(),
+            watermark: self.watermark(),
+            ..LayoutSummary::default()
+        };
+
+        self.visit_regions(&mut |region| match region.kind {
+            RegionKind::Used => summary.used_bytes += region.size,
+            RegionKind::Free => {
+                summary.free_bytes += region.size;
+                summary.free_regions += 1;
+                summary.largest_free_region = summary.largest_free_region.max(region.size);
+            }
+            RegionKind::Overhead => summary.overhead_bytes += region.size,
+        });
+
+        summary
+    }
+
+    /// Returns a dump of the memory of the store, whose `Display` implementation draws an ASCII occupancy map.
+    fn dump(&self) -> LayoutDump<'_, Self> {
+        LayoutDump { store: self }
+    }
+}
+
+/// A dump of the memory of a store, whose `Display` implementation draws an ASCII occupancy map.
+///
+/// See the module documentation for the format.
+pub struct LayoutDump<'a, S: ?Sized> {
+    store: &'a S,
+}
+
+impl<'a, S: ?Sized + StoreLayoutDump> fmt::Display for LayoutDump<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let summary = self.store.summary();
+
+        writeln!(
+            f,
+            "capacity: {} bytes, used: {} bytes, free: {} bytes in {} regions, largest: {} bytes, overhead: {} bytes",
+            summary.capacity,
+            summary.used_bytes,
+            summary.free_bytes,
+            summary.free_regions,
+            summary.largest_free_region,
+            summary.overhead_bytes,
+        )?;
+
+        if let Some(watermark) = summary.watermark {
+            writeln!(f, "watermark: {watermark} bytes")?;
+        }
+
+        if summary.capacity == 0 {
+            return Ok(());
+        }
+
+        let cell = (summary.capacity + WIDTH - 1) / WIDTH;
+        let columns = (summary.capacity + cell - 1) / cell;
+
+        //  Number of bytes of each kind, per column.
+        let mut cells = [[0usize; 3]; WIDTH];
+
+        self.store.visit_regions(&mut |region| {
+            let (mut start, end) = (region.offset, region.offset + region.size);
+
+            while start < end && start < summary.capacity {
+                let column = start / cell;
+                let next = end.min((column + 1) * cell);
+
+                cells[column][region.kind as usize] += next - start;
+
+                start = next;
+            }
+        });
+
+        for (column, bytes) in cells[..columns].iter().enumerate() {
+            let size = cell.min(summary.capacity - column * cell);
+
+            let glyph = match *bytes {
+                [used, _, _] if used == size => '#',
+                [_, free, _] if free == size => '.',
+                [_, _, overhead] if overhead == size => '=',
+                _ => '+',
+            };
+
+            write!(f, "{glyph}")?;
+        }
+
+        writeln!(f)?;
+
+        if let Some(watermark) = summary.watermark {
+            let column = (watermark / cell).min(columns - 1);
+
+            writeln!(f, "{:>width$}", '^', width = column + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, S: ?Sized + StoreLayoutDump> fmt::Debug for LayoutDump<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("LayoutDump")
+            .field("summary", &self.store.summary())
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  The maximum number of columns of the occupancy map.
+const WIDTH: usize = 64;
+
+//  Visits the regions of a store whose memory is used up to `watermark`, and free beyond.
+pub(crate) fn visit_watermarked(watermark: usize, capacity: usize, visitor: &mut dyn FnMut(Region)) {
+    if watermark > 0 {
+        visitor(Region {
+            offset: 0,
+            size: watermark,
+            kind: RegionKind::Used,
+        });
+    }
+
+    if capacity > watermark {
+        visitor(Region {
+            offset: watermark,
+            size: capacity - watermark,
+            kind: RegionKind::Free,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+
+    use core::alloc::Layout;
+
+    use crate::{
+        interface::Store,
+        store::{InlineBuddyStore, InlineBumpStore, InlineSlabStore, InlineTlsfStore, StackBumpBlock},
+    };
+
+    use super::*;
+
+    //  Checks that the regions of `store` are in order, and cover its entire memory, then returns its summary.
+    fn check_regions<S: StoreLayoutDump>(store: &S) -> LayoutSummary {
+        let mut end = 0;
+
+        store.visit_regions(&mut |region| {
+            assert_eq!(end, region.offset, "{region:?}");
+            assert_ne!(0, region.size, "{region:?}");
+
+            end = region.offset + region.size;
+        });
+
+        assert_eq!(store.capacity(), end);
+
+        let summary = store.summary();
+
+        assert_eq!(
+            summary.capacity,
+            summary.used_bytes + summary.free_bytes + summary.overhead_bytes
+        );
+
+        summary
+    }
+
+    struct Fixed(&'static [Region]);
+
+    impl StoreLayoutDump for Fixed {
+        fn capacity(&self) -> usize {
+            self.0.iter().map(|region| region.size).sum()
+        }
+
+        fn watermark(&self) -> Option<usize> {
+            Some(96)
+        }
+
+        fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+            self.0.iter().copied().for_each(visitor);
+        }
+    }
+
+    const fn region(offset: usize, size: usize, kind: RegionKind) -> Region {
+        Region { offset, size, kind }
+    }
+
+    const REGIONS: &[Region] = &[
+        region(0, 32, RegionKind::Used),
+        region(32, 16, RegionKind::Free),
+        region(48, 48, RegionKind::Used),
+        region(96, 24, RegionKind::Free),
+        region(120, 8, RegionKind::Overhead),
+    ];
+
+    #[test]
+    fn summary() {
+        let summary = Fixed(REGIONS).summary();
+
+        assert_eq!(128, summary.capacity);
+        assert_eq!(80, summary.used_bytes);
+        assert_eq!(40, summary.free_bytes);
+        assert_eq!(8, summary.overhead_bytes);
+        assert_eq!(2, summary.free_regions);
+        assert_eq!(24, summary.largest_free_region);
+        assert_eq!(Some(96), summary.watermark);
+        assert_eq!(0.4, summary.fragmentation());
+    }
+
+    #[test]
+    fn display() {
+        let dump = Fixed(REGIONS).dump().to_string();
+
+        let mut lines = dump.lines().skip(2);
+
+        //  Each column represents 2 bytes.
+        let map = "################........########################............====";
+
+        assert_eq!(Some(map), lines.next(), "{dump}");
+        assert_eq!(
+            Some("                                                ^"),
+            lines.next(),
+            "{dump}"
+        );
+    }
+
+    #[test]
+    fn inline_bump() {
+        let store = InlineBumpStore::<u16, [u8; 256]>::default();
+
+        for _ in 0..3 {
+            store.allocate(Layout::new::<[u8; 32]>()).unwrap();
+        }
+
+        let summary = check_regions(&store);
+
+        assert_eq!(
+            (96, 160, 1),
+            (summary.used_bytes, summary.free_bytes, summary.free_regions)
+        );
+        assert_eq!(Some(96), summary.watermark);
+
+        let dump = store.dump().to_string();
+
+        //  Each column represents 4 bytes.
+        let map = "########################........................................";
+
+        assert_eq!(Some(map), dump.lines().nth(2), "{dump}");
+    }
+
+    #[test]
+    fn stack_bump() {
+        let block = StackBumpBlock::<[u64; 16]>::new();
+        let store = block.create_store::<u16>();
+
+        store.allocate(Layout::new::<[u64; 8]>()).unwrap();
+
+        let summary = check_regions(&store);
+
+        assert_eq!((64, 64), (summary.used_bytes, summary.free_bytes));
+        assert_eq!(Some(64), summary.watermark);
+    }
+
+    #[test]
+    fn slab() {
+        let store = InlineSlabStore::<u16, [u64; 64]>::default();
+
+        let layout = Layout::new::<[u64; 4]>();
+        let handles: [_; 4] = core::array::from_fn(|_| store.allocate(layout).unwrap().0);
+
+        //  Safety:
+        //  -   `handles[1]` was allocated by `store`, with `layout`, and is still valid.
+        unsafe { store.deallocate(handles[1], layout) };
+
+        let summary = check_regions(&store);
+
+        assert_eq!((96, 416), (summary.used_bytes, summary.free_bytes));
+        assert_eq!((2, 384), (summary.free_regions, summary.largest_free_region));
+        assert_eq!(Some(128), summary.watermark);
+    }
+
+    #[test]
+    fn buddy() {
+        let store = InlineBuddyStore::<u16, [u64; 64]>::default();
+
+        let empty = check_regions(&store);
+
+        assert_eq!(0, empty.used_bytes);
+
+        let small = Layout::new::<[u64; 4]>();
+        let large = Layout::new::<[u64; 8]>();
+
+        let (first, _) = store.allocate(small).unwrap();
+        let (second, _) = store.allocate(large).unwrap();
+
+        let summary = check_regions(&store);
+
+        assert_eq!(96, summary.used_bytes);
+        assert_eq!(empty.overhead_bytes, summary.overhead_bytes);
+
+        //  Safety:
+        //  -   `first` and `second` were allocated by `store`, with `small` and `large`, and are still valid.
+        unsafe {
+            store.deallocate(first, small);
+            store.deallocate(second, large);
+        }
+
+        assert_eq!(empty, check_regions(&store));
+    }
+
+    #[test]
+    fn tlsf() {
+        let store = InlineTlsfStore::<u16, [u64; 64]>::default();
+
+        let empty = check_regions(&store);
+
+        assert_eq!((0, 1), (empty.used_bytes, empty.free_regions));
+
+        let layout = Layout::new::<[u64; 4]>();
+
+        let handles: [_; 3] = core::array::from_fn(|_| store.allocate(layout).unwrap().0);
+
+        //  Safety:
+        //  -   `handles[1]` was allocated by `store`, with `layout`, and is still valid.
+        unsafe { store.deallocate(handles[1], layout) };
+
+        let summary = check_regions(&store);
+
+        assert_eq!((64, 2), (summary.used_bytes, summary.free_regions));
+        assert_eq!(32, summary.free_bytes - summary.largest_free_region);
+
+        //  Safety:
+        //  -   `handles[0]` and `handles[2]` were allocated by `store`, with `layout`, and are still valid.
+        unsafe {
+            store.deallocate(handles[0], layout);
+            store.deallocate(handles[2], layout);
+        }
+
+        assert_eq!(empty, check_regions(&store));
+    }
+} // mod tests
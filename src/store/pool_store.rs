@@ -0,0 +1,516 @@
+//! A lock-free "object pool" Store, handing out fixed-size slots to multiple threads.
+//!
+//! This store is suitable for node-based containers -- such as `LinkedList` or `SkipList` -- whose nodes all share the
+//! same layout, and in particular for concurrent node-based data-structures built on `TypedHandle`: allocating and
+//! deallocating a slot is a single compare-and-swap on the head of a free list, without any lock.
+//!
+//! The slots are allocated in one block from an `Allocator` on creation, and the pool never grows. The free list is
+//! threaded through a separate array of links, rather than through the slots themselves, so that a thread racing to
+//! pop a slot never reads memory being written to by the thread which won the race. Its head is tagged with a counter
+//! incremented on every update, guarding against the ABA problem.
+
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    fmt,
+    marker::PhantomData,
+    ptr::{Alignment, NonNull},
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+};
+
+use crate::{
+    alloc,
+    interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable},
+};
+
+/// A handle of a `PoolStore`, the index of a slot.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PoolHandle(u32);
+
+impl PoolHandle {
+    //  Index of the dangling handle, which is never the index of a slot.
+    const DANGLING: u32 = u32::MAX;
+}
+
+/// An implementation of `Store` handing out fixed-size slots from a lock-free free list, suitable for use by multiple
+/// threads.
+///
+/// Generic parameters:
+///
+/// -   Each slot is aligned and sized as per `T`, any layout which does not fit a slot fails with `AllocError`.
+/// -   `A` is the allocator of the slots.
+///
+/// The handles are indices, and remain valid until the store is dropped, even if the store itself is moved.
+pub struct PoolStore<T, A: Allocator> {
+    //  Index of the first free slot, or `EMPTY`, in the low 32 bits, and tag in the high 32 bits.
+    head: AtomicU64,
+    //  Block of memory holding the `capacity` slots, followed by the `capacity` links of the free list.
+    memory: NonNull<u8>,
+    //  Link of each slot to the next free slot, or `EMPTY`; only meaningful while the slot is free.
+    links: NonNull<AtomicU32>,
+    capacity: u32,
+    allocator: A,
+    _marker: PhantomData<fn(T) -> T>,
+}
+
+impl<T, A: Allocator> PoolStore<T, A> {
+    /// The maximum number of slots of a pool.
+    pub const MAX_CAPACITY: usize = (u32::MAX - 1) as usize;
+
+    /// Creates a new instance, with `capacity` slots allocated by `allocator`.
+    ///
+    /// Calls `handle_alloc_error` if the allocation fails.
+    ///
+    /// #   Panics
+    ///
+    /// If `capacity` exceeds `MAX_CAPACITY`.
+    #[track_caller]
+    pub fn with_capacity_in(capacity: usize, allocator: A) -> Self {
+        let Ok(this) = Self::try_with_capacity_in(capacity, allocator) else {
+            alloc::handle_alloc_error(Self::memory_layout(capacity).map_or(Layout::new::<T>(), |(layout, _)| layout))
+        };
+
+        this
+    }
+
+    /// Attempts to create a new instance, with `capacity` slots allocated by `allocator`.
+    ///
+    /// #   Errors
+    ///
+    /// Returns `AllocError` if the slots cannot be allocated.
+    ///
+    /// #   Panics
+    ///
+    /// If `capacity` exceeds `MAX_CAPACITY`.
+    pub fn try_with_capacity_in(capacity: usize, allocator: A) -> Result<Self, AllocError> {
+        assert!(capacity <= Self::MAX_CAPACITY, "{capacity} slots cannot be represented");
+
+        let (layout, links_offset) = Self::memory_layout(capacity)?;
+
+        let memory = allocator.allocate(layout)?.as_non_null_ptr();
+
+        //  Safety:
+        //  -   `links_offset` is within the bounds of `memory`, as computed by `memory_layout`.
+        let links: NonNull<AtomicU32> = unsafe { NonNull::new_unchecked(memory.as_ptr().add(links_offset)) }.cast();
+
+        //  Initially, each slot links to the next, and the last to none.
+        for index in 0..capacity {
+            let next = if index + 1 == capacity { EMPTY } else { index as u32 + 1 };
+
+            //  Safety:
+            //  -   `index` is within the bounds of the `capacity` links.
+            //  -   `links` is suitably aligned for `AtomicU32`, as computed by `memory_layout`.
+            unsafe { links.as_ptr().add(index).write(AtomicU32::new(next)) };
+        }
+
+        let head = AtomicU64::new(pack(0, if capacity == 0 { EMPTY } else { 0 }));
+        let capacity = capacity as u32;
+        let _marker = PhantomData;
+
+        Ok(Self {
+            head,
+            memory,
+            links,
+            capacity,
+            allocator,
+            _marker,
+        })
+    }
+
+    /// Returns the number of slots of the pool.
+    pub const fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// Returns a reference to the underlying allocator.
+    pub const fn allocator(&self) -> &A {
+        &self.allocator
+    }
+}
+
+impl<T, A: Allocator> Drop for PoolStore<T, A> {
+    fn drop(&mut self) {
+        let capacity = self.capacity as usize;
+
+        //  Safety:
+        //  -   The layout was successfully computed on creation, with the same capacity.
+        let (layout, _) = unsafe { Self::memory_layout(capacity).unwrap_unchecked() };
+
+        //  Safety:
+        //  -   `self.memory` was allocated by `self.allocator`, with `layout`.
+        unsafe { self.allocator.deallocate(self.memory, layout) };
+    }
+}
+
+unsafe impl<T, A: Allocator> StoreDangling for PoolStore<T, A> {
+    type Handle = PoolHandle;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        if alignment.as_usize() > Self::slot_layout().align() {
+            return Err(AllocError);
+        }
+
+        Ok(PoolHandle(PoolHandle::DANGLING))
+    }
+}
+
+unsafe impl<T, A: Allocator> Store for PoolStore<T, A> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.0 == PoolHandle::DANGLING {
+            return NonNull::<T>::dangling().cast();
+        }
+
+        debug_assert!(handle.0 < self.capacity);
+
+        //  Safety:
+        //  -   `handle.0` is less than `self.capacity`, as `handle` was allocated by `self` as per pre-conditions, and
+        //      therefore the offset is within the bounds of the slots.
+        unsafe { NonNull::new_unchecked(self.memory.as_ptr().add(handle.0 as usize * Self::slot_layout().size())) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        if !Self::fits_slot(layout) {
+            return Err(AllocError);
+        }
+
+        let index = self.pop().ok_or(AllocError)?;
+
+        Ok((PoolHandle(index), Self::slot_layout().size()))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, _layout: Layout) {
+        debug_assert!(handle.0 < self.capacity);
+
+        //  Safety:
+        //  -   `handle` was allocated by `self`, and is still valid, as per pre-conditions.
+        unsafe { self.push(handle.0) };
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        //  Within a slot, the block of memory already fits.
+        if !Self::fits_slot(new_layout) {
+            return Err(AllocError);
+        }
+
+        Ok((handle, Self::slot_layout().size()))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        //  The alignment may still increase beyond that of a slot.
+        if !Self::fits_slot(new_layout) {
+            return Err(AllocError);
+        }
+
+        Ok((handle, Self::slot_layout().size()))
+    }
+}
+
+unsafe impl<T, A: Allocator> StoreSingle for PoolStore<T, A> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, as the slots are allocated by `self.allocator`.
+unsafe impl<T, A: Allocator> StoreStable for PoolStore<T, A> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, even if `self` is moved, as the slots are allocated by
+//      `self.allocator`.
+unsafe impl<T, A: Allocator> StorePinning for PoolStore<T, A> {}
+
+//  Safety:
+//  -   The store owns its block of memory, which it only accesses through atomics.
+unsafe impl<T, A: Allocator + Send> Send for PoolStore<T, A> {}
+
+//  Safety:
+//  -   The free list is only ever accessed atomically, and each slot is handed out to a single allocation at a time.
+//  -   The slots are never read nor written by the store itself, only through the pointers resolved by its users.
+unsafe impl<T, A: Allocator + Sync> Sync for PoolStore<T, A> {}
+
+impl<T, A: Allocator + fmt::Debug> fmt::Debug for PoolStore<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let layout = Self::slot_layout();
+
+        f.debug_struct("PoolStore")
+            .field("size", &layout.size())
+            .field("align", &layout.align())
+            .field("capacity", &self.capacity)
+            .field("allocator", &self.allocator)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Index marking the end of the free list.
+const EMPTY: u32 = u32::MAX;
+
+//  Packs the tag and index of the head of the free list.
+const fn pack(tag: u32, index: u32) -> u64 {
+    ((tag as u64) << 32) | index as u64
+}
+
+//  Unpacks the tag and index of the head of the free list.
+const fn unpack(head: u64) -> (u32, u32) {
+    ((head >> 32) as u32, head as u32)
+}
+
+impl<T, A: Allocator> PoolStore<T, A> {
+    #[inline(always)]
+    const fn slot_layout() -> Layout {
+        Layout::new::<T>()
+    }
+
+    //  Returns the layout of the block of memory of a pool of `capacity` slots, and the offset of its links.
+    fn memory_layout(capacity: usize) -> Result<(Layout, usize), AllocError> {
+        let slots = Layout::array::<T>(capacity).map_err(|_| AllocError)?;
+        let links = Layout::array::<AtomicU32>(capacity).map_err(|_| AllocError)?;
+
+        slots.extend(links).map_err(|_| AllocError)
+    }
+
+    #[inline(always)]
+    const fn fits_slot(layout: Layout) -> bool {
+        let slot = Self::slot_layout();
+
+        layout.align() <= slot.align() && layout.size() <= slot.size()
+    }
+
+    //  Returns the link of the slot at `index`.
+    //
+    //  #   Safety
+    //
+    //  -   `index` must be less than `self.capacity`.
+    unsafe fn link(&self, index: u32) -> &AtomicU32 {
+        debug_assert!(index < self.capacity);
+
+        //  Safety:
+        //  -   `index` is within the bounds of the links, as per pre-conditions.
+        //  -   The links were initialized on creation, and live as long as `self`.
+        unsafe { &*self.links.as_ptr().add(index as usize) }
+    }
+
+    //  Pops the first free slot, if any, returning its index.
+    fn pop(&self) -> Option<u32> {
+        let mut head = self.head.load(Ordering::Acquire);
+
+        loop {
+            let (tag, index) = unpack(head);
+
+            if index == EMPTY {
+                return None;
+            }
+
+            //  The link may be stale if another thread pops `index` concurrently, in which case the tag of the head
+            //  differs by the time of the exchange, and the exchange fails.
+            //
+            //  Safety:
+            //  -   `index` is less than `self.capacity`, as only indices of slots are ever pushed.
+            let next = unsafe { self.link(index) }.load(Ordering::Relaxed);
+
+            //  Acquire the writes of the thread which deallocated the slot, prior to reusing it.
+            match self.head.compare_exchange_weak(
+                head,
+                pack(tag.wrapping_add(1), next),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(index),
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    //  Pushes the slot at `index` to the front of the free list.
+    //
+    //  #   Safety
+    //
+    //  -   `index` must be less than `self.capacity`.
+    //  -   The slot at `index` must be allocated, and not be used any longer.
+    unsafe fn push(&self, index: u32) {
+        //  Safety:
+        //  -   `index` is less than `self.capacity`, as per pre-conditions.
+        let link = unsafe { self.link(index) };
+
+        let mut head = self.head.load(Ordering::Relaxed);
+
+        loop {
+            let (tag, next) = unpack(head);
+
+            link.store(next, Ordering::Relaxed);
+
+            //  Release the writes to the slot, and its link, to the thread which will allocate it next.
+            match self.head.compare_exchange_weak(
+                head,
+                pack(tag.wrapping_add(1), index),
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{alloc::Global, thread};
+
+    use crate::{collection::LinkedList, extension::typed::TypedHandle};
+
+    use super::*;
+
+    type TestStore = PoolStore<[u64; 2], Global>;
+
+    #[test]
+    fn exhaustion() {
+        let store = TestStore::with_capacity_in(2, Global);
+        let layout = Layout::new::<u64>();
+
+        let (first, size) = Store::allocate(&store, layout).unwrap();
+
+        assert_eq!(16, size);
+
+        let (second, _) = Store::allocate(&store, layout).unwrap();
+
+        Store::allocate(&store, layout).unwrap_err();
+
+        //  Safety:
+        //  -   `second` was allocated by `store`, with `layout`, and is still valid.
+        unsafe { Store::deallocate(&store, second, layout) };
+
+        let (third, _) = Store::allocate(&store, layout).unwrap();
+
+        assert_ne!(first, third);
+        assert_eq!(second, third);
+    }
+
+    #[test]
+    fn oversized() {
+        let store = TestStore::with_capacity_in(2, Global);
+
+        Store::allocate(&store, Layout::new::<[u64; 3]>()).unwrap_err();
+        Store::allocate(&store, Layout::new::<u128>().align_to(32).unwrap()).unwrap_err();
+
+        let (handle, _) = Store::allocate(&store, Layout::new::<u64>()).unwrap();
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with the layout of `u64`, and is still valid.
+        unsafe { Store::grow(&store, handle, Layout::new::<u64>(), Layout::new::<[u64; 4]>()).unwrap_err() };
+    }
+
+    #[test]
+    fn concurrent_churn() {
+        let store = TestStore::with_capacity_in(16, Global);
+
+        thread::scope(|scope| {
+            for t in 0..4u64 {
+                let store = &store;
+
+                scope.spawn(move || {
+                    for i in 0..1_000 {
+                        let handles: Vec<_> = (0..4)
+                            .map(|n| TypedHandle::try_new(t * 1_000_000 + i * 4 + n, store).unwrap())
+                            .collect();
+
+                        for (n, handle) in (0..).zip(handles.iter()) {
+                            //  Safety:
+                            //  -   `handle` was allocated by `store`, and is still valid.
+                            assert_eq!(t * 1_000_000 + i * 4 + n, unsafe { *handle.resolve(store) });
+                        }
+
+                        for handle in handles {
+                            //  Safety:
+                            //  -   `handle` was allocated by `store`, and is still valid.
+                            unsafe { handle.deallocate(store) };
+                        }
+                    }
+                });
+            }
+        });
+
+        for _ in 0..16 {
+            Store::allocate(&store, Layout::new::<u64>()).unwrap();
+        }
+    }
+
+    #[test]
+    fn list() {
+        let mut list = LinkedList::<u32, PoolStore<[u64; 2], Global>>::new_in(TestStore::with_capacity_in(64, Global));
+
+        for i in 0..64 {
+            list.try_push_back(i).unwrap();
+        }
+
+        list.try_push_back(64).unwrap_err();
+
+        assert!(list.iter().copied().eq(0..64));
+    }
+} // mod tests
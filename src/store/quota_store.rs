@@ -0,0 +1,541 @@
+//! A Store adapter, enforcing a byte quota shared by all the stores drawing from the same budget.
+//!
+//! This store is meant to put hard memory limits on tenants sharing a process: each tenant is given a `QuotaBudget`,
+//! and all its collections are created with stores drawing from this budget. Any allocation, or growth, which would
+//! exceed the budget fails with `AllocError`, regardless of whether the underlying store could satisfy it.
+//!
+//! The usage is tracked per share -- each store created from the budget, and each store created by `share` -- so that
+//! the budget can report which part of a tenant consumes its quota. A block of memory is accounted to the share which
+//! allocated it, even when deallocated, grown, or shrunk through another share of the same sharing set.
+
+use core::{
+    alloc::{AllocError, Layout},
+    array,
+    cell::Cell,
+    fmt,
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSharing, StoreSingle, StoreStable};
+
+/// The budget of bytes that the `QuotaStore`s created from it draw from.
+///
+/// Generic parameters:
+///
+/// -   `N` is the maximum number of shares whose usage is tracked individually, the usage of any further share is
+///     accounted together with the last.
+pub struct QuotaBudget<const N: usize = 8> {
+    limit: usize,
+    used: Cell<usize>,
+    shares: Cell<usize>,
+    usages: [Cell<usize>; N],
+}
+
+impl<const N: usize> QuotaBudget<N> {
+    /// Creates a new budget, allowing up to `limit` bytes to be allocated at any one time.
+    pub fn new(limit: usize) -> Self {
+        assert!(N > 0, "At least one share must be tracked");
+        assert!(N <= u32::MAX as usize, "{N} shares cannot be represented");
+
+        let usages = array::from_fn(|_| Cell::new(0));
+
+        Self {
+            limit,
+            used: Cell::new(0),
+            shares: Cell::new(0),
+            usages,
+        }
+    }
+
+    /// Creates a new store, atop `store`, drawing from this budget.
+    ///
+    /// The new store is a new share of the budget, even if `store` is shared with the underlying store of another
+    /// `QuotaStore` drawing from this budget.
+    pub fn create_store<S>(&self, store: S) -> QuotaStore<'_, S, N> {
+        let share = self.register();
+
+        QuotaStore {
+            budget: self,
+            share,
+            store,
+        }
+    }
+
+    /// Returns the maximum number of bytes allocated at any one time.
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of bytes currently allocated, across all shares.
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// Returns the number of bytes which may still be allocated, across all shares.
+    pub fn remaining(&self) -> usize {
+        self.limit - self.used.get()
+    }
+
+    /// Returns the number of shares created so far.
+    pub fn shares(&self) -> usize {
+        self.shares.get()
+    }
+
+    /// Returns the number of bytes currently allocated by the share with the given `id`.
+    ///
+    /// The ids of the shares are assigned in order of creation, starting from 0, see `QuotaStore::id`. The usage of all
+    /// the shares whose id is greater than or equal to `N - 1` is accounted together, and the usage of ids which were
+    /// never assigned is 0.
+    pub fn usage(&self, id: usize) -> usize {
+        self.usages[id.min(N - 1)].get()
+    }
+}
+
+impl<const N: usize> fmt::Debug for QuotaBudget<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let shares = self.shares.get().min(N);
+
+        f.debug_struct("QuotaBudget")
+            .field("limit", &self.limit)
+            .field("used", &self.used.get())
+            .field("usages", &&self.usages[..shares])
+            .finish()
+    }
+}
+
+/// A handle of a `QuotaStore`, wrapping the handle of the underlying store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct QuotaHandle<H> {
+    handle: H,
+    //  Index, in the budget, of the usage of the share which allocated the block of memory.
+    share: u32,
+}
+
+/// An adapter of `Store` or `StoreSingle`, failing any allocation which would exceed its `QuotaBudget`.
+///
+/// Implements `Store` whenever `S` does, and `StoreSingle` whenever `S` does, and `StoreSharing` whenever `S` does, in
+/// which case the shares draw from the same budget.
+///
+/// The number of bytes are computed from the layouts passed to the store, rather than from the sizes it returned.
+pub struct QuotaStore<'a, S, const N: usize = 8> {
+    budget: &'a QuotaBudget<N>,
+    share: u32,
+    store: S,
+}
+
+impl<'a, S, const N: usize> QuotaStore<'a, S, N> {
+    /// Returns the id of this share of the budget.
+    pub fn id(&self) -> usize {
+        self.share as usize
+    }
+
+    /// Returns the number of bytes currently allocated by this share.
+    pub fn usage(&self) -> usize {
+        self.budget.usages[self.share as usize].get()
+    }
+
+    /// Returns a reference to the budget.
+    pub const fn budget(&self) -> &'a QuotaBudget<N> {
+        self.budget
+    }
+
+    /// Returns a reference to the underlying store.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+unsafe impl<'a, S: StoreDangling, const N: usize> StoreDangling for QuotaStore<'a, S, N> {
+    type Handle = QuotaHandle<S::Handle>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let handle = self.store.dangling(alignment)?;
+
+        Ok(QuotaHandle {
+            handle,
+            share: self.share,
+        })
+    }
+}
+
+unsafe impl<'a, S: Store, const N: usize> Store for QuotaStore<'a, S, N> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        self.budget.reserve(layout.size())?;
+
+        let Ok((handle, size)) = self.store.allocate(layout) else {
+            self.budget.release(layout.size());
+
+            return Err(AllocError);
+        };
+
+        self.budget.charge(self.share, layout.size());
+
+        let handle = QuotaHandle {
+            handle,
+            share: self.share,
+        };
+
+        Ok((handle, size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle.handle, layout) };
+
+        self.budget.credit(handle.share, layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        let additional = new_layout.size() - old_layout.size();
+
+        self.budget.reserve(additional)?;
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let Ok((inner, size)) = (unsafe { self.store.grow(handle.handle, old_layout, new_layout) }) else {
+            self.budget.release(additional);
+
+            return Err(AllocError);
+        };
+
+        self.budget.charge(handle.share, additional);
+
+        let handle = QuotaHandle {
+            handle: inner,
+            share: handle.share,
+        };
+
+        Ok((handle, size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.shrink(handle.handle, old_layout, new_layout)? };
+
+        self.budget.credit(handle.share, old_layout.size() - new_layout.size());
+
+        let handle = QuotaHandle {
+            handle: inner,
+            share: handle.share,
+        };
+
+        Ok((handle, size))
+    }
+}
+
+unsafe impl<'a, S: StoreSingle, const N: usize> StoreSingle for QuotaStore<'a, S, N> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve_mut(handle.handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        self.budget.reserve(layout.size())?;
+
+        let Ok((handle, size)) = self.store.allocate(layout) else {
+            self.budget.release(layout.size());
+
+            return Err(AllocError);
+        };
+
+        self.budget.charge(self.share, layout.size());
+
+        let handle = QuotaHandle {
+            handle,
+            share: self.share,
+        };
+
+        Ok((handle, size))
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle.handle, layout) };
+
+        self.budget.credit(handle.share, layout.size());
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        let additional = new_layout.size() - old_layout.size();
+
+        self.budget.reserve(additional)?;
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let Ok((inner, size)) = (unsafe { self.store.grow(handle.handle, old_layout, new_layout) }) else {
+            self.budget.release(additional);
+
+            return Err(AllocError);
+        };
+
+        self.budget.charge(handle.share, additional);
+
+        let handle = QuotaHandle {
+            handle: inner,
+            share: handle.share,
+        };
+
+        Ok((handle, size))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.shrink(handle.handle, old_layout, new_layout)? };
+
+        self.budget.credit(handle.share, old_layout.size() - new_layout.size());
+
+        let handle = QuotaHandle {
+            handle: inner,
+            share: handle.share,
+        };
+
+        Ok((handle, size))
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<'a, S: StoreStable, const N: usize> StoreStable for QuotaStore<'a, S, N> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<'a, S: StorePinning, const N: usize> StorePinning for QuotaStore<'a, S, N> {}
+
+//  Safety:
+//  -   All shares wrap shares of the same underlying store, and handles carry the share they are accounted to.
+unsafe impl<'a, S: StoreSharing, const N: usize> StoreSharing for QuotaStore<'a, S, N> {
+    type SharingError = S::SharingError;
+
+    fn is_sharing_with(&self, other: &Self) -> bool {
+        ptr::eq(self.budget, other.budget) && self.store.is_sharing_with(&other.store)
+    }
+
+    fn share(&self) -> Result<Self, Self::SharingError>
+    where
+        Self: Sized,
+    {
+        let store = self.store.share()?;
+
+        Ok(self.budget.create_store(store))
+    }
+}
+
+impl<'a, S: fmt::Debug, const N: usize> fmt::Debug for QuotaStore<'a, S, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("QuotaStore")
+            .field("budget", &self.budget)
+            .field("share", &self.share)
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+impl<const N: usize> QuotaBudget<N> {
+    //  Registers a new share, returning the index of its usage.
+    fn register(&self) -> u32 {
+        let id = self.shares.get();
+
+        self.shares.set(id + 1);
+
+        id.min(N - 1) as u32
+    }
+
+    //  Reserves `size` bytes from the budget, prior to allocating them.
+    fn reserve(&self, size: usize) -> Result<(), AllocError> {
+        if size > self.remaining() {
+            return Err(AllocError);
+        }
+
+        self.used.set(self.used.get() + size);
+
+        Ok(())
+    }
+
+    //  Releases `size` bytes to the budget, after a failed allocation.
+    fn release(&self, size: usize) {
+        self.used.set(self.used.get() - size);
+    }
+
+    //  Accounts `size` reserved bytes to the usage of `share`.
+    fn charge(&self, share: u32, size: usize) {
+        let usage = &self.usages[share as usize];
+
+        usage.set(usage.get() + size);
+    }
+
+    //  Returns `size` bytes of the usage of `share` to the budget.
+    fn credit(&self, share: u32, size: usize) {
+        let usage = &self.usages[share as usize];
+
+        usage.set(usage.get().saturating_sub(size));
+
+        self.release(size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::{collection::SkipList, extension::typed::TypedHandle, store::StackBumpBlock};
+
+    use super::*;
+
+    #[test]
+    fn limit() {
+        let budget = QuotaBudget::<1>::new(100);
+        let store = budget.create_store(Global);
+
+        let first = TypedHandle::<[u8; 64], _>::try_allocate(&store).unwrap();
+
+        assert_eq!(
+            Err(AllocError),
+            TypedHandle::<[u8; 64], _>::try_allocate(&store).map(|_| ())
+        );
+        assert_eq!((64, 36), (budget.used(), budget.remaining()));
+
+        let second = TypedHandle::<[u8; 32], _>::try_allocate(&store).unwrap();
+
+        assert_eq!(96, store.usage());
+
+        //  Safety:
+        //  -   `first` and `second` were allocated by `store`, and are still valid.
+        unsafe {
+            first.deallocate(&store);
+            second.deallocate(&store);
+        }
+
+        assert_eq!((0, 100), (budget.used(), budget.remaining()));
+    }
+
+    #[test]
+    fn grow() {
+        let budget = QuotaBudget::<1>::new(100);
+        let store = budget.create_store(Global);
+
+        let mut handle = TypedHandle::<[u8], _>::allocate_slice(16, &store);
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        //  -   `128 > 16`.
+        assert_eq!(Err(AllocError), unsafe { handle.try_grow(128, &store) });
+        assert_eq!(16, budget.used());
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        //  -   `100 > 16`.
+        unsafe { handle.grow(100, &store) };
+
+        assert_eq!(0, budget.remaining());
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        //  -   `10 < 100`.
+        unsafe { handle.shrink(10, &store) };
+
+        assert_eq!(10, budget.used());
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        unsafe { handle.deallocate(&store) };
+
+        assert_eq!(0, budget.used());
+    }
+
+    #[test]
+    fn shares() {
+        let block = StackBumpBlock::<[u64; 64]>::new();
+        let budget = QuotaBudget::<4>::new(256);
+
+        let first = budget.create_store(block.create_store::<u16>());
+        let second = first.share().unwrap();
+
+        assert!(first.is_sharing_with(&second));
+        assert_eq!((0, 1, 2), (first.id(), second.id(), budget.shares()));
+
+        let a = TypedHandle::new([1u64; 4], &first);
+        let b = TypedHandle::new([2u64; 8], &second);
+
+        assert_eq!((32, 64), (first.usage(), second.usage()));
+        assert_eq!(96, budget.used());
+
+        //  Safety:
+        //  -   `a` was allocated by `first`, which shares with `second`, and is still valid.
+        unsafe { a.deallocate(&second) };
+
+        assert_eq!((0, 64), (budget.usage(0), budget.usage(1)));
+
+        //  Safety:
+        //  -   `b` was allocated by `second`, and is still valid.
+        unsafe { b.deallocate(&second) };
+
+        assert_eq!(0, budget.used());
+    }
+
+    #[test]
+    fn tenants() {
+        let (alice, bob) = (QuotaBudget::<1>::new(4096), QuotaBudget::<1>::new(4096));
+
+        let mut small = SkipList::with_store(alice.create_store(Global));
+        let mut large = SkipList::with_store(bob.create_store(Global));
+
+        for i in 0..4 {
+            small.insert(i, i);
+        }
+
+        for i in 0..32 {
+            large.insert(i, i);
+        }
+
+        assert!(alice.used() < bob.used());
+        assert!(bob.used() <= bob.limit());
+
+        drop((small, large));
+
+        assert_eq!((0, 0), (alice.used(), bob.used()));
+    }
+} // mod tests
@@ -0,0 +1,692 @@
+//! A Store adapter, recording a trace of the calls made to the underlying store, and a replayer of such traces.
+//!
+//! This store is meant to capture a real workload once, and then check whether other stores can handle it, without
+//! rebuilding the application: the trace is replayed against any store by a `TraceReplayer`.
+//!
+//! The trace is a text, with one line per successful call, of the form:
+//!
+//! -   `A <id> <size> <align>` for `allocate`.
+//! -   `D <id> <size> <align>` for `deallocate`.
+//! -   `G <id> <old size> <old align> <new size> <new align>` for `grow`.
+//! -   `S <id> <old size> <old align> <new size> <new align>` for `shrink`.
+//!
+//! The `id` of a block of memory is assigned on allocation, and preserved by `grow` and `shrink`.
+
+use core::{
+    alloc::{AllocError, Layout},
+    cell::{Cell, Ref, RefCell},
+    fmt::{self, Write},
+    ptr::{Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable};
+
+/// A handle of a `RecordingStore`, wrapping the handle of the underlying store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RecordedHandle<H> {
+    handle: H,
+    //  Identifier of the block of memory in the trace, or `DANGLING`.
+    id: u64,
+}
+
+impl<H> RecordedHandle<H> {
+    /// Returns the identifier of the block of memory in the trace.
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// An adapter of `Store` or `StoreSingle`, recording a trace of the calls made to the underlying store in `W`.
+///
+/// Implements `Store` whenever `S` does, and `StoreSingle` whenever `S` does.
+pub struct RecordingStore<S, W> {
+    next_id: Cell<u64>,
+    //  Set if writing to `writer` failed, or `writer` was borrowed, in which case the trace is incomplete.
+    truncated: Cell<bool>,
+    writer: RefCell<W>,
+    store: S,
+}
+
+impl<S, W: Write> RecordingStore<S, W> {
+    /// Creates a new instance, atop `store`, writing the trace to `writer`.
+    pub const fn new_in(store: S, writer: W) -> Self {
+        Self {
+            next_id: Cell::new(0),
+            truncated: Cell::new(false),
+            writer: RefCell::new(writer),
+            store,
+        }
+    }
+
+    /// Returns whether writing to the writer failed, or the writer was borrowed while recording, in which case the
+    /// trace is incomplete.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated.get()
+    }
+
+    /// Returns a reference to the writer.
+    ///
+    /// The calls made while the reference is alive are not recorded, and truncate the trace.
+    pub fn writer(&self) -> Ref<'_, W> {
+        self.writer.borrow()
+    }
+
+    /// Returns a reference to the underlying store.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the underlying store and writer.
+    pub fn into_parts(self) -> (S, W) {
+        (self.store, self.writer.into_inner())
+    }
+}
+
+impl<S: Default, W: Write + Default> Default for RecordingStore<S, W> {
+    fn default() -> Self {
+        Self::new_in(S::default(), W::default())
+    }
+}
+
+unsafe impl<S: StoreDangling, W> StoreDangling for RecordingStore<S, W> {
+    type Handle = RecordedHandle<S::Handle>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let handle = self.store.dangling(alignment)?;
+
+        Ok(RecordedHandle { handle, id: DANGLING })
+    }
+}
+
+unsafe impl<S: Store, W: Write> Store for RecordingStore<S, W> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let (handle, size) = self.store.allocate(layout)?;
+
+        Ok((self.record_allocate(handle, layout), size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        self.record('D', format_args!("{} {} {}", handle.id, layout.size(), layout.align()));
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle.handle, layout) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.grow(handle.handle, old_layout, new_layout)? };
+
+        Ok((self.record_resize('G', handle, inner, old_layout, new_layout), size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.shrink(handle.handle, old_layout, new_layout)? };
+
+        Ok((self.record_resize('S', handle, inner, old_layout, new_layout), size))
+    }
+}
+
+unsafe impl<S: StoreSingle, W: Write> StoreSingle for RecordingStore<S, W> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve_mut(handle.handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let (handle, size) = self.store.allocate(layout)?;
+
+        Ok((self.record_allocate(handle, layout), size))
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.record('D', format_args!("{} {} {}", handle.id, layout.size(), layout.align()));
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle.handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.grow(handle.handle, old_layout, new_layout)? };
+
+        Ok((self.record_resize('G', handle, inner, old_layout, new_layout), size))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.shrink(handle.handle, old_layout, new_layout)? };
+
+        Ok((self.record_resize('S', handle, inner, old_layout, new_layout), size))
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: StoreStable, W> StoreStable for RecordingStore<S, W> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: StorePinning, W> StorePinning for RecordingStore<S, W> {}
+
+impl<S: fmt::Debug, W> fmt::Debug for RecordingStore<S, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("RecordingStore")
+            .field("next_id", &self.next_id.get())
+            .field("truncated", &self.truncated.get())
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+/// The outcome of replaying a trace.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Number of operations replayed.
+    pub operations: u64,
+    /// Number of calls to allocate, grow, or shrink which failed.
+    pub failures: u64,
+    /// Number of operations skipped, as they applied to a block of memory whose allocation failed.
+    pub skipped: u64,
+    /// Number of blocks of memory still allocated at the end of the trace, deallocated by the replayer.
+    pub leaked: u64,
+    /// Maximum number of bytes allocated at any one time.
+    pub peak_bytes: usize,
+    /// Maximum, at any one time, of the span of addresses from the start of the lowest block of memory to the end of
+    /// the highest block of memory.
+    pub peak_footprint: usize,
+}
+
+impl ReplayReport {
+    /// Returns the fragmentation, in `[0, 1]`, as the fraction of the peak footprint not accounted for by the peak
+    /// number of bytes allocated.
+    ///
+    /// This is only a rough estimate, meaningful for stores handing out blocks from a single region of memory.
+    pub fn fragmentation(&self) -> f64 {
+        if self.peak_footprint == 0 {
+            return 0.0;
+        }
+
+        1.0 - (self.peak_bytes as f64 / self.peak_footprint as f64).min(1.0)
+    }
+}
+
+/// An error encountered while replaying a trace.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TraceError {
+    /// The line, numbered from 1, cannot be parsed.
+    Malformed {
+        /// Line of the error.
+        line: usize,
+    },
+    /// The line, numbered from 1, refers to a block of memory which was not allocated.
+    UnknownId {
+        /// Line of the error.
+        line: usize,
+        /// Identifier of the block of memory.
+        id: u64,
+    },
+    /// The line, numbered from 1, allocates a block of memory under the identifier of a block of memory still live.
+    DuplicateId {
+        /// Line of the error.
+        line: usize,
+        /// Identifier of the block of memory.
+        id: u64,
+    },
+    /// The line, numbered from 1, allocates a block of memory while all the slots of the replayer are in use.
+    TooManyBlocks {
+        /// Line of the error.
+        line: usize,
+    },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::Malformed { line } => write!(f, "line {line}: malformed"),
+            Self::UnknownId { line, id } => write!(f, "line {line}: unknown block #{id}"),
+            Self::DuplicateId { line, id } => write!(f, "line {line}: block #{id} is still live"),
+            Self::TooManyBlocks { line } => write!(f, "line {line}: too many live blocks"),
+        }
+    }
+}
+
+/// A replayer of the traces recorded by a `RecordingStore`, against any store.
+///
+/// Generic parameters:
+///
+/// -   `S` is the store to replay the traces against.
+/// -   `N` is the maximum number of live blocks of memory at any one time in the traces.
+pub struct TraceReplayer<S: Store, const N: usize = 256> {
+    store: S,
+}
+
+impl<S: Store, const N: usize> TraceReplayer<S, N> {
+    /// Creates a new instance, replaying against `store`.
+    pub const fn new_in(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Returns a reference to the store.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Replays `trace` against the store.
+    ///
+    /// Failures of the store do not interrupt the replay, they are counted instead, and the operations applying to a
+    /// block of memory whose allocation failed are skipped.
+    ///
+    /// #   Errors
+    ///
+    /// If the trace cannot be parsed, in which case the blocks of memory allocated so far are deallocated.
+    pub fn replay(&mut self, trace: &str) -> Result<ReplayReport, TraceError> {
+        let mut blocks: [Option<Block<S::Handle>>; N] = [None; N];
+        let mut report = ReplayReport::default();
+
+        let result = trace
+            .lines()
+            .enumerate()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .try_for_each(|(index, text)| self.replay_line(index + 1, text, &mut blocks, &mut report));
+
+        for block in blocks.iter_mut().filter_map(Option::take) {
+            if let Some(handle) = block.handle {
+                report.leaked += u64::from(result.is_ok());
+
+                //  Safety:
+                //  -   `handle` was allocated by `self.store`, with `block.layout`.
+                unsafe { self.store.deallocate(handle, block.layout) };
+            }
+        }
+
+        result.map(|()| report)
+    }
+
+    /// Returns the store.
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+}
+
+impl<S: Store + fmt::Debug, const N: usize> fmt::Debug for TraceReplayer<S, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("TraceReplayer").field("store", &self.store).finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Identifier of dangling handles.
+const DANGLING: u64 = u64::MAX;
+
+impl<S, W: Write> RecordingStore<S, W> {
+    //  Records a line of the trace.
+    fn record(&self, operation: char, arguments: fmt::Arguments<'_>) {
+        let Ok(mut writer) = self.writer.try_borrow_mut() else {
+            self.truncated.set(true);
+            return;
+        };
+
+        if writeln!(writer, "{operation} {arguments}").is_err() {
+            self.truncated.set(true);
+        }
+    }
+
+    //  Records the allocation of `handle`.
+    fn record_allocate<H>(&self, handle: H, layout: Layout) -> RecordedHandle<H> {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+
+        self.record('A', format_args!("{id} {} {}", layout.size(), layout.align()));
+
+        RecordedHandle { handle, id }
+    }
+
+    //  Records the resize of `handle` into `inner`.
+    fn record_resize<H>(
+        &self,
+        operation: char,
+        handle: RecordedHandle<H>,
+        inner: H,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> RecordedHandle<H> {
+        self.record(
+            operation,
+            format_args!(
+                "{} {} {} {} {}",
+                handle.id,
+                old_layout.size(),
+                old_layout.align(),
+                new_layout.size(),
+                new_layout.align()
+            ),
+        );
+
+        RecordedHandle {
+            handle: inner,
+            id: handle.id,
+        }
+    }
+}
+
+//  A live block of memory of a trace being replayed.
+#[derive(Clone, Copy)]
+struct Block<H> {
+    id: u64,
+    //  `None` if the allocation failed.
+    handle: Option<H>,
+    layout: Layout,
+}
+
+impl<S: Store, const N: usize> TraceReplayer<S, N> {
+    fn replay_line(
+        &self,
+        line: usize,
+        text: &str,
+        blocks: &mut [Option<Block<S::Handle>>; N],
+        report: &mut ReplayReport,
+    ) -> Result<(), TraceError> {
+        let malformed = TraceError::Malformed { line };
+
+        let mut words = text.split_ascii_whitespace();
+
+        let operation = words.next().ok_or(malformed)?;
+
+        let mut number = || -> Result<u64, TraceError> { words.next().and_then(|w| w.parse().ok()).ok_or(malformed) };
+
+        let id = number()?;
+        let first = layout(number()?, number()?).ok_or(malformed)?;
+
+        let find = |blocks: &[Option<Block<S::Handle>>; N]| {
+            blocks
+                .iter()
+                .position(|block| block.is_some_and(|block| block.id == id))
+                .ok_or(TraceError::UnknownId { line, id })
+        };
+
+        report.operations += 1;
+
+        match operation {
+            "A" => {
+                if find(blocks).is_ok() {
+                    return Err(TraceError::DuplicateId { line, id });
+                }
+
+                let index = blocks
+                    .iter()
+                    .position(Option::is_none)
+                    .ok_or(TraceError::TooManyBlocks { line })?;
+
+                let handle = self.store.allocate(first).ok().map(|(handle, _)| handle);
+
+                report.failures += u64::from(handle.is_none());
+
+                blocks[index] = Some(Block {
+                    id,
+                    handle,
+                    layout: first,
+                });
+            }
+            "D" => {
+                let index = find(blocks)?;
+
+                //  Safety:
+                //  -   `index` was just found.
+                let block = unsafe { blocks[index].take().unwrap_unchecked() };
+
+                match block.handle {
+                    //  Safety:
+                    //  -   `handle` was allocated by `self.store`, with `block.layout`.
+                    Some(handle) => unsafe { self.store.deallocate(handle, block.layout) },
+                    None => report.skipped += 1,
+                }
+            }
+            "G" | "S" => {
+                let second = layout(number()?, number()?).ok_or(malformed)?;
+
+                let index = find(blocks)?;
+
+                //  Safety:
+                //  -   `index` was just found.
+                let block = unsafe { blocks[index].as_mut().unwrap_unchecked() };
+
+                let Some(handle) = block.handle else {
+                    report.skipped += 1;
+                    return Ok(());
+                };
+
+                //  The current layout is used, rather than the one recorded, as previous resizes may have failed.
+                let result = if second.size() >= block.layout.size() {
+                    //  Safety:
+                    //  -   `handle` was allocated by `self.store`, with `block.layout`.
+                    //  -   `second` is at least as large as `block.layout`.
+                    unsafe { self.store.grow(handle, block.layout, second) }
+                } else {
+                    //  Safety:
+                    //  -   `handle` was allocated by `self.store`, with `block.layout`.
+                    //  -   `second` is smaller than `block.layout`.
+                    unsafe { self.store.shrink(handle, block.layout, second) }
+                };
+
+                match result {
+                    Ok((handle, _)) => {
+                        block.handle = Some(handle);
+                        block.layout = second;
+                    }
+                    Err(_) => report.failures += 1,
+                }
+            }
+            _ => return Err(malformed),
+        }
+
+        if words.next().is_some() {
+            return Err(malformed);
+        }
+
+        self.measure(blocks, report);
+
+        Ok(())
+    }
+
+    //  Updates the peak bytes and footprint of `report`.
+    fn measure(&self, blocks: &[Option<Block<S::Handle>>; N], report: &mut ReplayReport) {
+        let mut bytes = 0;
+        let mut low = usize::MAX;
+        let mut high = 0;
+
+        for block in blocks.iter().flatten() {
+            let Some(handle) = block.handle else { continue };
+
+            //  Safety:
+            //  -   `handle` was allocated by `self.store`, and is still valid.
+            let address = unsafe { self.store.resolve(handle) }.as_ptr() as usize;
+
+            bytes += block.layout.size();
+            low = low.min(address);
+            high = high.max(address + block.layout.size());
+        }
+
+        report.peak_bytes = report.peak_bytes.max(bytes);
+        report.peak_footprint = report.peak_footprint.max(high.saturating_sub(low));
+    }
+}
+
+fn layout(size: u64, align: u64) -> Option<Layout> {
+    Layout::from_size_align(size.try_into().ok()?, align.try_into().ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::{
+        collection::{LinkedList, StoreVec},
+        store::{InlineBumpStore, InlineSlabStore},
+    };
+
+    use super::*;
+
+    //  Records a workload mixing a vector and a list.
+    fn workload() -> String {
+        let mut trace = String::new();
+
+        {
+            let mut list = LinkedList::new_in(RecordingStore::new_in(Global, &mut trace));
+
+            for i in 0..16u64 {
+                list.try_push_back(i).unwrap();
+            }
+
+            for _ in 0..8 {
+                list.pop_front();
+            }
+        }
+
+        let mut v = StoreVec::<u32, _>::new_in(RecordingStore::new_in(Global, String::new()));
+
+        for i in 0..100 {
+            v.push(i);
+        }
+
+        //  The vector is not dropped, hence its block of memory is leaked in the trace.
+        let vector = v.store().writer().clone();
+
+        trace + &vector
+    }
+
+    #[test]
+    fn record() {
+        let store = RecordingStore::new_in(Global, String::new());
+
+        let (handle, _) = Store::allocate(&store, Layout::new::<u32>()).unwrap();
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with a `u32` layout, and is still valid.
+        //  -   `[u32; 4]` is larger than `u32`.
+        let (handle, _) =
+            unsafe { Store::grow(&store, handle, Layout::new::<u32>(), Layout::new::<[u32; 4]>()) }.unwrap();
+
+        //  Safety:
+        //  -   `handle` was grown by `store`, to a `[u32; 4]` layout, and is still valid.
+        unsafe { Store::deallocate(&store, handle, Layout::new::<[u32; 4]>()) };
+
+        assert_eq!("A 0 4 4\nG 0 4 4 16 4\nD 0 16 4\n", *store.writer());
+    }
+
+    #[test]
+    fn record_while_writer_borrowed() {
+        let store = RecordingStore::new_in(Global, String::new());
+
+        let writer = store.writer();
+
+        let (handle, _) = Store::allocate(&store, Layout::new::<u32>()).unwrap();
+
+        assert!(writer.is_empty());
+
+        drop(writer);
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with a `u32` layout, and is still valid.
+        unsafe { Store::deallocate(&store, handle, Layout::new::<u32>()) };
+
+        assert!(store.is_truncated());
+        assert_eq!("D 0 4 4\n", *store.writer());
+    }
+
+    #[test]
+    fn replay() {
+        let trace = workload();
+
+        let report = TraceReplayer::<_>::new_in(Global).replay(&trace).unwrap();
+
+        assert_eq!(trace.lines().count() as u64, report.operations);
+        assert_eq!(0, report.failures);
+        assert_eq!(0, report.skipped);
+        assert_eq!(1, report.leaked);
+        assert!(report.peak_bytes >= 400);
+
+        let report = TraceReplayer::<_>::new_in(InlineSlabStore::<u16, [u64; 512]>::default())
+            .replay(&trace)
+            .unwrap();
+
+        assert_eq!(0, report.failures);
+        assert!(report.fragmentation() < 1.0);
+
+        //  Too small to hold the vector.
+        let report = TraceReplayer::<_>::new_in(InlineBumpStore::<u16, [u64; 32]>::default())
+            .replay(&trace)
+            .unwrap();
+
+        assert!(report.failures > 0);
+        assert!(report.skipped > 0);
+    }
+
+    #[test]
+    fn malformed() {
+        let mut replayer = TraceReplayer::<_, 4>::new_in(Global);
+
+        assert_eq!(
+            Err(TraceError::Malformed { line: 2 }),
+            replayer.replay("A 0 4 4\nX 0 4 4\n")
+        );
+        assert_eq!(Err(TraceError::Malformed { line: 1 }), replayer.replay("A 0 4 3\n"));
+        assert_eq!(
+            Err(TraceError::UnknownId { line: 2, id: 1 }),
+            replayer.replay("A 0 4 4\nD 1 4 4\n")
+        );
+        assert_eq!(
+            Err(TraceError::DuplicateId { line: 2, id: 0 }),
+            replayer.replay("A 0 4 4\nA 0 8 8\n")
+        );
+        assert_eq!(
+            Err(TraceError::TooManyBlocks { line: 5 }),
+            replayer.replay("A 0 1 1\nA 1 1 1\nA 2 1 1\nA 3 1 1\nA 4 1 1\n")
+        );
+    }
+} // mod tests
@@ -0,0 +1,839 @@
+//! A Store adapter, poisoning memory and surrounding each block of memory with red zones.
+//!
+//! This store is meant for testing collections, and unsafe code in general, without sanitizers: it fills fresh blocks
+//! of memory with `RedZoneStore::FRESH`, freed blocks of memory with `RedZoneStore::FREED`, and surrounds each block
+//! of memory with red zones filled with `RedZoneStore::CANARY`.
+//!
+//! The red zones are checked on `deallocate`, `grow`, and `shrink`, as well as on demand with `verify`, catching
+//! off-by-one writes, for example through `StoreVec::spare_capacity_mut` or `TypedHandle::resolve_raw`.
+
+use core::{
+    alloc::{AllocError, Layout},
+    array,
+    cell::Cell,
+    cmp, fmt,
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable};
+
+/// A handle of a `RedZoneStore`, wrapping the handle of the underlying store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RedZoneHandle<H> {
+    handle: H,
+    //  Index of the entry of the block of memory, or `DANGLING`.
+    index: u32,
+}
+
+/// An adapter of `Store` or `StoreSingle`, poisoning memory and surrounding each block of memory with red zones.
+///
+/// Implements `Store` whenever `S` does, and `StoreSingle` whenever `S` does.
+///
+/// Generic parameters:
+///
+/// -   `S` is the underlying store.
+/// -   `N` is the maximum number of live handles at any one time, allocations fail beyond.
+///
+/// #   Panics
+///
+/// On `deallocate`, `grow`, `shrink`, and `verify` if a red zone was overwritten.
+pub struct RedZoneStore<S: StoreDangling, const N: usize = 64> {
+    entries: [Cell<Option<Entry<S::Handle>>>; N],
+    store: S,
+}
+
+impl<S: StoreDangling, const N: usize> RedZoneStore<S, N> {
+    /// The byte written over freshly allocated memory.
+    pub const FRESH: u8 = 0xCD;
+
+    /// The byte written over freed memory, prior to returning it to the underlying store.
+    pub const FREED: u8 = 0xDD;
+
+    /// The byte written in the red zones before and after each block of memory.
+    pub const CANARY: u8 = 0xFD;
+
+    /// The minimum size of each red zone.
+    pub const RED_ZONE: usize = 16;
+
+    /// Creates a new instance, atop `store`.
+    pub fn new_in(store: S) -> Self {
+        assert!(N < DANGLING as usize, "{N} entries cannot be represented");
+
+        let entries = array::from_fn(|_| Cell::new(None));
+
+        Self { entries, store }
+    }
+
+    /// Returns the number of live handles.
+    pub fn live(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.get().is_some()).count()
+    }
+
+    /// Returns a reference to the underlying store.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<S: Store, const N: usize> RedZoneStore<S, N> {
+    /// Checks the red zones of all live blocks of memory.
+    ///
+    /// #   Panics
+    ///
+    /// If any red zone was overwritten.
+    #[track_caller]
+    pub fn verify(&self) {
+        for index in 0..N {
+            Self::check(&self.entries, &mut &self.store, index, "verify");
+        }
+    }
+}
+
+impl<S: StoreDangling + Default, const N: usize> Default for RedZoneStore<S, N> {
+    fn default() -> Self {
+        Self::new_in(S::default())
+    }
+}
+
+unsafe impl<S: StoreDangling, const N: usize> StoreDangling for RedZoneStore<S, N> {
+    type Handle = RedZoneHandle<S::Handle>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let handle = self.store.dangling(alignment)?;
+
+        Ok(RedZoneHandle {
+            handle,
+            index: DANGLING,
+        })
+    }
+}
+
+unsafe impl<S: Store, const N: usize> Store for RedZoneStore<S, N> {
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   `handle.handle` was allocated by `self.store`, and is still valid, as per pre-conditions.
+        let pointer = unsafe { self.store.resolve(handle.handle) };
+
+        //  Safety:
+        //  -   `pointer` was resolved from `handle`, which is valid, as per pre-conditions.
+        unsafe { self.locate(pointer, handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        Self::allocate_in(&self.entries, &mut &self.store, layout)
+    }
+
+    #[track_caller]
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Self::deallocate_in(&self.entries, &mut &self.store, handle, layout) }
+    }
+
+    #[track_caller]
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Self::grow_in(&self.entries, &mut &self.store, handle, old_layout, new_layout) }
+    }
+
+    #[track_caller]
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Self::shrink_in(&self.entries, &mut &self.store, handle, old_layout, new_layout) }
+    }
+}
+
+unsafe impl<S: StoreSingle, const N: usize> StoreSingle for RedZoneStore<S, N> {
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   `handle.handle` was allocated by `self.store`, and is still valid, as per pre-conditions.
+        let pointer = unsafe { self.store.resolve(handle.handle) };
+
+        //  Safety:
+        //  -   `pointer` was resolved from `handle`, which is valid, as per pre-conditions.
+        unsafe { self.locate(pointer, handle) }
+    }
+
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   `handle.handle` was allocated by `self.store`, and is still valid, as per pre-conditions.
+        let pointer = unsafe { self.store.resolve_mut(handle.handle) };
+
+        //  Safety:
+        //  -   `pointer` was resolved from `handle`, which is valid, as per pre-conditions.
+        unsafe { self.locate(pointer, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        Self::allocate_in(&self.entries, &mut &mut self.store, layout)
+    }
+
+    #[track_caller]
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Self::deallocate_in(&self.entries, &mut &mut self.store, handle, layout) }
+    }
+
+    #[track_caller]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Self::grow_in(&self.entries, &mut &mut self.store, handle, old_layout, new_layout) }
+    }
+
+    #[track_caller]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { Self::shrink_in(&self.entries, &mut &mut self.store, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves at a fixed offset of the same block of memory as the underlying store.
+unsafe impl<S: StoreDangling + StoreStable, const N: usize> StoreStable for RedZoneStore<S, N> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves at a fixed offset of the same block of memory as the underlying store.
+unsafe impl<S: StoreDangling + StorePinning, const N: usize> StorePinning for RedZoneStore<S, N> {}
+
+impl<S: StoreDangling + fmt::Debug, const N: usize> fmt::Debug for RedZoneStore<S, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("RedZoneStore")
+            .field("live", &self.live())
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Index of dangling handles.
+const DANGLING: u32 = u32::MAX;
+
+//  Record of a live block of memory.
+//
+//  The inner block of memory is made of `offset` bytes of red zone, `layout.size()` bytes of user memory, and
+//  `RED_ZONE` bytes of red zone.
+#[derive(Clone, Copy)]
+struct Entry<H> {
+    handle: H,
+    offset: usize,
+    layout: Layout,
+}
+
+//  Records of the live blocks of memory, indexed by `RedZoneHandle::index`.
+type Entries<H> = [Cell<Option<Entry<H>>>];
+
+//  Operations of the underlying store, either a `Store` through `&S`, or a `StoreSingle` through `&mut S`, so that the
+//  entries and the underlying store may be borrowed separately.
+//
+//  The pre-conditions of each method are those of the matching method of `Store` and `StoreSingle`.
+trait Inner {
+    type Handle: Copy;
+
+    unsafe fn resolve(&mut self, handle: Self::Handle) -> NonNull<u8>;
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError>;
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout);
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError>;
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError>;
+}
+
+impl<'a, S: Store> Inner for &'a S {
+    type Handle = S::Handle;
+
+    unsafe fn resolve(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { S::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        S::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { S::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { S::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { S::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+impl<'a, S: StoreSingle> Inner for &'a mut S {
+    type Handle = S::Handle;
+
+    unsafe fn resolve(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { S::resolve_mut(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        S::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { S::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { S::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { S::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+impl<S: StoreDangling, const N: usize> RedZoneStore<S, N> {
+    //  Returns the offset of the user memory for a given alignment: a multiple of the alignment, large enough for a red
+    //  zone.
+    fn offset_of(align: usize) -> usize {
+        cmp::max(Self::RED_ZONE, align)
+    }
+
+    //  Returns the layout of the inner block of memory.
+    fn inner_layout(offset: usize, layout: Layout) -> Result<Layout, AllocError> {
+        let size = offset
+            .checked_add(layout.size())
+            .and_then(|size| size.checked_add(Self::RED_ZONE))
+            .ok_or(AllocError)?;
+
+        Layout::from_size_align(size, layout.align()).map_err(|_| AllocError)
+    }
+
+    //  Returns a pointer to the user memory of `handle`, given the `pointer` to its inner block of memory.
+    //
+    //  #   Safety
+    //
+    //  -   `handle` must be valid, and `pointer` must have been resolved from `handle.handle`.
+    unsafe fn locate(&self, pointer: NonNull<u8>, handle: RedZoneHandle<S::Handle>) -> NonNull<u8> {
+        if handle.index == DANGLING {
+            return pointer;
+        }
+
+        let entry = self.entries[handle.index as usize].get();
+
+        debug_assert!(entry.is_some(), "handle #{} is not live", handle.index);
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions, hence its entry is occupied.
+        let entry = unsafe { entry.unwrap_unchecked() };
+
+        //  Safety:
+        //  -   `entry.offset` is within the block of memory, as the block is `entry.offset + size + RED_ZONE` bytes.
+        unsafe { NonNull::new_unchecked(pointer.as_ptr().add(entry.offset)) }
+    }
+
+    //  Implementation of `allocate`.
+    fn allocate_in<I: Inner<Handle = S::Handle>>(
+        entries: &Entries<S::Handle>,
+        store: &mut I,
+        layout: Layout,
+    ) -> Result<(RedZoneHandle<S::Handle>, usize), AllocError> {
+        let Some(index) = entries.iter().position(|entry| entry.get().is_none()) else {
+            return Err(AllocError);
+        };
+
+        let offset = Self::offset_of(layout.align());
+        let (handle, _) = store.allocate(Self::inner_layout(offset, layout)?)?;
+
+        let entry = Entry { handle, offset, layout };
+
+        entries[index].set(Some(entry));
+
+        //  Safety:
+        //  -   `entry` was just allocated, and is thus valid.
+        unsafe { Self::paint(store, entry, 0) };
+
+        let handle = RedZoneHandle {
+            handle,
+            index: index as u32,
+        };
+
+        Ok((handle, layout.size()))
+    }
+
+    //  Implementation of `deallocate`.
+    //
+    //  #   Safety
+    //
+    //  -   As per the pre-conditions of `deallocate`, `store` being the underlying store.
+    #[track_caller]
+    unsafe fn deallocate_in<I: Inner<Handle = S::Handle>>(
+        entries: &Entries<S::Handle>,
+        store: &mut I,
+        handle: RedZoneHandle<S::Handle>,
+        layout: Layout,
+    ) {
+        let index = handle.index as usize;
+
+        Self::check(entries, store, index, "deallocate");
+
+        let Some(entry) = entries[index].take() else {
+            return;
+        };
+
+        debug_assert_eq!(layout, entry.layout);
+
+        //  Safety:
+        //  -   `entry.layout` fits, as it was computed on allocation.
+        let inner = unsafe { Self::inner_layout(entry.offset, entry.layout).unwrap_unchecked() };
+
+        //  Safety:
+        //  -   `entry.handle` is valid, as per pre-conditions.
+        let pointer = unsafe { store.resolve(entry.handle) };
+
+        //  Safety:
+        //  -   `pointer` is valid for writes of `inner.size()` bytes, as the block of memory is still allocated.
+        unsafe { ptr::write_bytes(pointer.as_ptr(), Self::FREED, inner.size()) };
+
+        //  Safety:
+        //  -   `entry.handle` is valid, and `inner` fits, as per pre-conditions.
+        unsafe { store.deallocate(entry.handle, inner) };
+    }
+
+    //  Implementation of `grow`.
+    //
+    //  #   Safety
+    //
+    //  -   As per the pre-conditions of `grow`, `store` being the underlying store.
+    #[track_caller]
+    unsafe fn grow_in<I: Inner<Handle = S::Handle>>(
+        entries: &Entries<S::Handle>,
+        store: &mut I,
+        handle: RedZoneHandle<S::Handle>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(RedZoneHandle<S::Handle>, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        let index = handle.index as usize;
+
+        Self::check(entries, store, index, "grow");
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions, hence its entry is occupied.
+        let entry = unsafe { entries[index].get().unwrap_unchecked() };
+
+        //  Never decrease the offset, so that the inner block of memory never shrinks.
+        let offset = cmp::max(entry.offset, Self::offset_of(new_layout.align()));
+
+        let new_inner = Self::inner_layout(offset, new_layout)?;
+
+        //  Safety:
+        //  -   `entry.layout` fits, as it was computed on allocation.
+        let old_inner = unsafe { Self::inner_layout(entry.offset, entry.layout).unwrap_unchecked() };
+
+        //  Safety:
+        //  -   `entry.handle` is valid, as per pre-conditions.
+        //  -   `old_inner` fits the block of memory, as it was used to allocate it.
+        //  -   `new_inner.size()` is greater than or equal to `old_inner.size()`, as both offset and size are.
+        let (inner_handle, _) = unsafe { store.grow(entry.handle, old_inner, new_inner)? };
+
+        //  Safety:
+        //  -   `inner_handle` was just returned by `grow`, and is thus valid.
+        let pointer = unsafe { store.resolve(inner_handle) };
+
+        if offset != entry.offset {
+            //  Safety:
+            //  -   Both source and destination are within the block of memory of `new_inner.size()` bytes.
+            unsafe {
+                ptr::copy(
+                    pointer.as_ptr().add(entry.offset),
+                    pointer.as_ptr().add(offset),
+                    entry.layout.size(),
+                )
+            };
+        }
+
+        let new_entry = Entry {
+            handle: inner_handle,
+            offset,
+            layout: new_layout,
+        };
+
+        entries[index].set(Some(new_entry));
+
+        //  Safety:
+        //  -   `new_entry` was just grown, and is thus valid.
+        unsafe { Self::paint(store, new_entry, entry.layout.size()) };
+
+        let handle = RedZoneHandle {
+            handle: inner_handle,
+            index: handle.index,
+        };
+
+        Ok((handle, new_layout.size()))
+    }
+
+    //  Implementation of `shrink`.
+    //
+    //  #   Safety
+    //
+    //  -   As per the pre-conditions of `shrink`, `store` being the underlying store.
+    #[track_caller]
+    unsafe fn shrink_in<I: Inner<Handle = S::Handle>>(
+        entries: &Entries<S::Handle>,
+        store: &mut I,
+        handle: RedZoneHandle<S::Handle>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(RedZoneHandle<S::Handle>, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        let index = handle.index as usize;
+
+        Self::check(entries, store, index, "shrink");
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions, hence its entry is occupied.
+        let entry = unsafe { entries[index].get().unwrap_unchecked() };
+
+        //  Safety:
+        //  -   `entry.layout` fits, as it was computed on allocation.
+        let old_inner = unsafe { Self::inner_layout(entry.offset, entry.layout).unwrap_unchecked() };
+
+        let new_entry = if entry.offset % new_layout.align() == 0 {
+            let new_inner = Self::inner_layout(entry.offset, new_layout)?;
+
+            //  Safety:
+            //  -   `entry.handle` is valid, as per pre-conditions.
+            //  -   `old_inner` fits the block of memory, as it was used to allocate it.
+            //  -   `new_inner.size()` is less than or equal to `old_inner.size()`, as the offset is identical.
+            let (inner_handle, _) = unsafe { store.shrink(entry.handle, old_inner, new_inner)? };
+
+            Entry {
+                handle: inner_handle,
+                offset: entry.offset,
+                layout: new_layout,
+            }
+        } else {
+            //  The alignment increased beyond the offset, the block of memory must be moved to a new offset, which
+            //  a shrink of the inner block of memory cannot accommodate.
+            let offset = Self::offset_of(new_layout.align());
+
+            let (inner_handle, _) = store.allocate(Self::inner_layout(offset, new_layout)?)?;
+
+            //  Safety:
+            //  -   `inner_handle` was just allocated, and is thus valid.
+            let new_pointer = unsafe { store.resolve(inner_handle) };
+
+            //  Safety:
+            //  -   `entry.handle` is valid, as per pre-conditions.
+            let old_pointer = unsafe { store.resolve(entry.handle) };
+
+            //  Safety:
+            //  -   The source is valid for reads of `new_layout.size()` bytes, as it is larger.
+            //  -   The destination is valid for writes of `new_layout.size()` bytes, as it was just allocated.
+            //  -   Source and destination do not overlap, as the destination was just allocated.
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    old_pointer.as_ptr().add(entry.offset),
+                    new_pointer.as_ptr().add(offset),
+                    new_layout.size(),
+                )
+            };
+
+            //  Safety:
+            //  -   `old_pointer` is valid for writes of `old_inner.size()` bytes, as it is still allocated.
+            unsafe { ptr::write_bytes(old_pointer.as_ptr(), Self::FREED, old_inner.size()) };
+
+            //  Safety:
+            //  -   `entry.handle` is valid, and `old_inner` fits, as per pre-conditions.
+            unsafe { store.deallocate(entry.handle, old_inner) };
+
+            Entry {
+                handle: inner_handle,
+                offset,
+                layout: new_layout,
+            }
+        };
+
+        entries[index].set(Some(new_entry));
+
+        //  Safety:
+        //  -   `new_entry` was just shrunk, and is thus valid.
+        unsafe { Self::paint(store, new_entry, new_layout.size()) };
+
+        let handle = RedZoneHandle {
+            handle: new_entry.handle,
+            index: handle.index,
+        };
+
+        Ok((handle, new_layout.size()))
+    }
+
+    //  Fills the red zones of `entry` with `CANARY`, and its user memory from `initialized` onwards with `FRESH`.
+    //
+    //  #   Safety
+    //
+    //  -   `entry.handle` must be valid.
+    unsafe fn paint<I: Inner<Handle = S::Handle>>(store: &mut I, entry: Entry<S::Handle>, initialized: usize) {
+        //  Safety:
+        //  -   `entry.handle` is valid, as per pre-conditions.
+        let pointer = unsafe { store.resolve(entry.handle) }.as_ptr();
+
+        let size = entry.layout.size();
+
+        //  Safety:
+        //  -   All writes are within the block of memory of `entry.offset + size + RED_ZONE` bytes.
+        unsafe {
+            ptr::write_bytes(pointer, Self::CANARY, entry.offset);
+            ptr::write_bytes(pointer.add(entry.offset + initialized), Self::FRESH, size - initialized);
+            ptr::write_bytes(pointer.add(entry.offset + size), Self::CANARY, Self::RED_ZONE);
+        }
+    }
+
+    //  Checks the red zones of the entry at `index`, if any.
+    //
+    //  #   Panics
+    //
+    //  If a red zone was overwritten.
+    #[track_caller]
+    fn check<I: Inner<Handle = S::Handle>>(entries: &Entries<S::Handle>, store: &mut I, index: usize, operation: &str) {
+        let Some(entry) = entries.get(index).and_then(Cell::get) else {
+            return;
+        };
+
+        //  Safety:
+        //  -   `entry.handle` is valid, as the entry is live.
+        let pointer = unsafe { store.resolve(entry.handle) }.as_ptr();
+
+        let size = entry.layout.size();
+
+        //  Safety:
+        //  -   Both red zones are within the block of memory of `entry.offset + size + RED_ZONE` bytes.
+        let (before, after) = unsafe {
+            (
+                &*ptr::slice_from_raw_parts(pointer, entry.offset),
+                &*ptr::slice_from_raw_parts(pointer.add(entry.offset + size), Self::RED_ZONE),
+            )
+        };
+
+        if let Some(position) = before.iter().rposition(|byte| *byte != Self::CANARY) {
+            panic!(
+                "RedZoneStore: {operation} found the red zone before block #{index} ({:?}) overwritten, {} byte(s) \
+                 before the start",
+                entry.layout,
+                entry.offset - position
+            );
+        }
+
+        if let Some(position) = after.iter().position(|byte| *byte != Self::CANARY) {
+            panic!(
+                "RedZoneStore: {operation} found the red zone after block #{index} ({:?}) overwritten, {} byte(s) \
+                 after the end",
+                entry.layout, position
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::{
+        collection::{LinkedList, StoreVec},
+        extension::typed::TypedHandle,
+        store::InlineSingleStore,
+    };
+
+    use super::*;
+
+    type TestStore = RedZoneStore<Global>;
+
+    #[test]
+    fn collections() {
+        let mut v = StoreVec::<String, TestStore>::new();
+
+        for i in 0..50 {
+            v.push(i.to_string());
+        }
+
+        assert!(v.as_slice().iter().enumerate().all(|(i, s)| *s == i.to_string()));
+
+        v.store().verify();
+
+        let mut list = LinkedList::<u64, TestStore>::new();
+
+        for i in 0..10 {
+            list.try_push_back(i).unwrap();
+        }
+
+        list.store().verify();
+
+        assert!(list.iter().copied().eq(0..10));
+    }
+
+    #[test]
+    fn fresh() {
+        let store = TestStore::default();
+
+        let handle = TypedHandle::<[u8; 8], _>::allocate(&store);
+
+        assert_eq!([TestStore::FRESH; 8], unsafe { *handle.resolve(&store) });
+
+        unsafe { handle.deallocate(&store) };
+    }
+
+    #[test]
+    fn over_aligned() {
+        #[repr(align(64))]
+        struct Aligned(u8);
+
+        let store = TestStore::default();
+
+        let handle = TypedHandle::new(Aligned(3), &store);
+
+        assert_eq!(0, unsafe { handle.resolve_raw(&store) }.as_ptr() as usize % 64);
+        assert_eq!(3, unsafe { handle.resolve(&store) }.0);
+
+        store.verify();
+
+        unsafe { handle.deallocate(&store) };
+    }
+
+    #[test]
+    #[should_panic(expected = "red zone after block #0")]
+    fn spare_capacity_overrun() {
+        let mut v = StoreVec::<u8, TestStore>::with_capacity(4);
+
+        v.push(1);
+
+        let spare = v.spare_capacity_mut();
+
+        //  Off-by-one: writes one past the end of the spare capacity.
+        unsafe {
+            spare
+                .as_mut_ptr()
+                .add(spare.len())
+                .write(core::mem::MaybeUninit::new(0))
+        };
+
+        drop(v);
+    }
+
+    #[test]
+    #[should_panic(expected = "red zone after block #0")]
+    fn single_overrun() {
+        let mut v = StoreVec::<u8, RedZoneStore<InlineSingleStore<[u64; 16]>, 1>>::new();
+
+        for i in 0..20 {
+            v.push(i);
+        }
+
+        assert!(v.as_slice().iter().copied().eq(0..20));
+
+        let spare = v.spare_capacity_mut();
+
+        //  Off-by-one: writes one past the end of the spare capacity.
+        //
+        //  Safety:
+        //  -   The write lands in the red zone after the block of memory, which is within the inline block of memory.
+        unsafe {
+            spare
+                .as_mut_ptr()
+                .add(spare.len())
+                .write(core::mem::MaybeUninit::new(0))
+        };
+
+        drop(v);
+    }
+
+    #[test]
+    #[should_panic(expected = "red zone before block #0")]
+    fn raw_underrun() {
+        let store = TestStore::default();
+
+        let handle = TypedHandle::new(0u32, &store);
+
+        unsafe { handle.resolve_raw(&store).cast::<u8>().as_ptr().sub(1).write(0) };
+
+        store.verify();
+    }
+} // mod tests
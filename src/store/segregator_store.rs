@@ -0,0 +1,536 @@
+//! A Store combinator, routing allocations to one of two stores depending on their size.
+//!
+//! This combinator lets a single container, or a single store value, benefit from specialized stores: for example
+//! `Segregator<64, InlineSlabStore<u16, [u64; 512]>, Global>` serves the small nodes of a `LinkedList` or `SkipList`
+//! from a slab, and the large buffers of a `StoreVec` from the heap.
+//!
+//! Each handle is tagged with the store which allocated it. A block of memory is moved from one store to the other
+//! whenever `grow` or `shrink` crosses the threshold, with the exception that a block of memory which cannot be moved
+//! to the small store on `shrink` is shrunk in place in the large store instead.
+//!
+//! An allocation failing in the store it is routed to is not retried in the other, use `Fallback` for this purpose.
+
+use core::{
+    alloc::{AllocError, Layout},
+    fmt,
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSharing, StoreSingle, StoreStable};
+
+/// A handle of a `Segregator`, tagged with the store which allocated the block of memory.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SegregatorHandle<S, L> {
+    /// A handle of the small store.
+    Small(S),
+    /// A handle of the large store.
+    Large(L),
+}
+
+impl<S, L> SegregatorHandle<S, L> {
+    /// Returns whether the block of memory associated to the handle was allocated by the small store.
+    pub const fn is_small(&self) -> bool {
+        matches!(self, Self::Small(_))
+    }
+
+    /// Returns whether the block of memory associated to the handle was allocated by the large store.
+    pub const fn is_large(&self) -> bool {
+        matches!(self, Self::Large(_))
+    }
+}
+
+/// The error returned when sharing a `Segregator` fails, identifying which of its stores could not be shared.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SegregatorSharingError<S, L> {
+    /// The small store could not be shared.
+    Small(S),
+    /// The large store could not be shared.
+    Large(L),
+}
+
+/// A combinator of `Store` or `StoreSingle`, routing allocations of at most `THRESHOLD` bytes to `Small`, and any
+/// larger allocation to `Large`.
+///
+/// Implements `Store` whenever both `Small` and `Large` do, and `StoreSingle` whenever both `Small` and `Large` do, and
+/// likewise for `StoreStable`, `StorePinning`, and `StoreSharing`.
+pub struct Segregator<const THRESHOLD: usize, Small, Large> {
+    small: Small,
+    large: Large,
+}
+
+impl<const THRESHOLD: usize, Small, Large> Segregator<THRESHOLD, Small, Large> {
+    /// Creates a new instance, routing allocations of at most `THRESHOLD` bytes to `small`, and any larger allocation
+    /// to `large`.
+    pub const fn new(small: Small, large: Large) -> Self {
+        Self { small, large }
+    }
+
+    /// Returns the size, in bytes, of the largest allocation routed to the small store.
+    pub const fn threshold(&self) -> usize {
+        THRESHOLD
+    }
+
+    /// Returns a reference to the small store.
+    pub const fn small(&self) -> &Small {
+        &self.small
+    }
+
+    /// Returns a reference to the large store.
+    pub const fn large(&self) -> &Large {
+        &self.large
+    }
+}
+
+impl<const THRESHOLD: usize, Small: Default, Large: Default> Default for Segregator<THRESHOLD, Small, Large> {
+    fn default() -> Self {
+        Self::new(Small::default(), Large::default())
+    }
+}
+
+unsafe impl<const THRESHOLD: usize, Small, Large> StoreDangling for Segregator<THRESHOLD, Small, Large>
+where
+    Small: StoreDangling,
+    Large: StoreDangling,
+{
+    type Handle = SegregatorHandle<Small::Handle, Large::Handle>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        if let Ok(handle) = self.small.dangling(alignment) {
+            return Ok(SegregatorHandle::Small(handle));
+        }
+
+        self.large.dangling(alignment).map(SegregatorHandle::Large)
+    }
+}
+
+unsafe impl<const THRESHOLD: usize, Small: Store, Large: Store> Store for Segregator<THRESHOLD, Small, Large> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the small store.
+            SegregatorHandle::Small(handle) => unsafe { self.small.resolve(handle) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the large store.
+            SegregatorHandle::Large(handle) => unsafe { self.large.resolve(handle) },
+        }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        if Self::is_small(layout) {
+            let (handle, size) = self.small.allocate(layout)?;
+
+            Ok((SegregatorHandle::Small(handle), size))
+        } else {
+            let (handle, size) = self.large.allocate(layout)?;
+
+            Ok((SegregatorHandle::Large(handle), size))
+        }
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the small store, with `layout`.
+            SegregatorHandle::Small(handle) => unsafe { self.small.deallocate(handle, layout) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the large store, with `layout`.
+            SegregatorHandle::Large(handle) => unsafe { self.large.deallocate(handle, layout) },
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        let handle = match handle {
+            SegregatorHandle::Small(handle) if Self::is_small(new_layout) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the small store.
+                let (handle, size) = unsafe { self.small.grow(handle, old_layout, new_layout)? };
+
+                return Ok((SegregatorHandle::Small(handle), size));
+            }
+            SegregatorHandle::Small(handle) => handle,
+            SegregatorHandle::Large(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the large store.
+                let (handle, size) = unsafe { self.large.grow(handle, old_layout, new_layout)? };
+
+                return Ok((SegregatorHandle::Large(handle), size));
+            }
+        };
+
+        let (result, size) = self.large.allocate(new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (old, new) = unsafe { (self.small.resolve(handle), self.large.resolve(result)) };
+
+        //  Safety:
+        //  -   `old` is valid for `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `new` is valid for `old_layout.size()` bytes, as `new_layout.size() >= old_layout.size()`.
+        //  -   `old` and `new` point to non-overlapping areas, since they belong to different live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), old_layout.size()) };
+
+        //  Safety:
+        //  -   `handle` was allocated by the small store, with `old_layout`, and is still valid.
+        unsafe { self.small.deallocate(handle, old_layout) };
+
+        Ok((SegregatorHandle::Large(result), size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        let handle = match handle {
+            SegregatorHandle::Small(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the small store.
+                let (handle, size) = unsafe { self.small.shrink(handle, old_layout, new_layout)? };
+
+                return Ok((SegregatorHandle::Small(handle), size));
+            }
+            SegregatorHandle::Large(handle) if !Self::is_small(new_layout) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the large store.
+                let (handle, size) = unsafe { self.large.shrink(handle, old_layout, new_layout)? };
+
+                return Ok((SegregatorHandle::Large(handle), size));
+            }
+            SegregatorHandle::Large(handle) => handle,
+        };
+
+        let Ok((result, size)) = self.small.allocate(new_layout) else {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the large store.
+            let (handle, size) = unsafe { self.large.shrink(handle, old_layout, new_layout)? };
+
+            return Ok((SegregatorHandle::Large(handle), size));
+        };
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (old, new) = unsafe { (self.large.resolve(handle), self.small.resolve(result)) };
+
+        //  Safety:
+        //  -   `old` is valid for `new_layout.size()` bytes, as `new_layout.size() <= old_layout.size()`.
+        //  -   `new` is valid for `new_layout.size()` bytes, since newly allocated with `new_layout`.
+        //  -   `old` and `new` point to non-overlapping areas, since they belong to different live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), new_layout.size()) };
+
+        //  Safety:
+        //  -   `handle` was allocated by the large store, with `old_layout`, and is still valid.
+        unsafe { self.large.deallocate(handle, old_layout) };
+
+        Ok((SegregatorHandle::Small(result), size))
+    }
+}
+
+unsafe impl<const THRESHOLD: usize, Small, Large> StoreSingle for Segregator<THRESHOLD, Small, Large>
+where
+    Small: StoreSingle,
+    Large: StoreSingle,
+{
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the small store.
+            SegregatorHandle::Small(handle) => unsafe { self.small.resolve(handle) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the large store.
+            SegregatorHandle::Large(handle) => unsafe { self.large.resolve(handle) },
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the small store.
+            SegregatorHandle::Small(handle) => unsafe { self.small.resolve_mut(handle) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the large store.
+            SegregatorHandle::Large(handle) => unsafe { self.large.resolve_mut(handle) },
+        }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        if Self::is_small(layout) {
+            let (handle, size) = self.small.allocate(layout)?;
+
+            Ok((SegregatorHandle::Small(handle), size))
+        } else {
+            let (handle, size) = self.large.allocate(layout)?;
+
+            Ok((SegregatorHandle::Large(handle), size))
+        }
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        match handle {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the small store, with `layout`.
+            SegregatorHandle::Small(handle) => unsafe { self.small.deallocate(handle, layout) },
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the large store, with `layout`.
+            SegregatorHandle::Large(handle) => unsafe { self.large.deallocate(handle, layout) },
+        }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        let handle = match handle {
+            SegregatorHandle::Small(handle) if Self::is_small(new_layout) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the small store.
+                let (handle, size) = unsafe { self.small.grow(handle, old_layout, new_layout)? };
+
+                return Ok((SegregatorHandle::Small(handle), size));
+            }
+            SegregatorHandle::Small(handle) => handle,
+            SegregatorHandle::Large(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the large store.
+                let (handle, size) = unsafe { self.large.grow(handle, old_layout, new_layout)? };
+
+                return Ok((SegregatorHandle::Large(handle), size));
+            }
+        };
+
+        let (result, size) = self.large.allocate(new_layout)?;
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (old, new) = unsafe { (self.small.resolve_mut(handle), self.large.resolve_mut(result)) };
+
+        //  Safety:
+        //  -   `old` is valid for `old_layout.size()` bytes, as per pre-conditions.
+        //  -   `new` is valid for `old_layout.size()` bytes, as `new_layout.size() >= old_layout.size()`.
+        //  -   `old` and `new` point to non-overlapping areas, since they belong to different live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), old_layout.size()) };
+
+        //  Safety:
+        //  -   `handle` was allocated by the small store, with `old_layout`, and is still valid.
+        unsafe { self.small.deallocate(handle, old_layout) };
+
+        Ok((SegregatorHandle::Large(result), size))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        let handle = match handle {
+            SegregatorHandle::Small(handle) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the small store.
+                let (handle, size) = unsafe { self.small.shrink(handle, old_layout, new_layout)? };
+
+                return Ok((SegregatorHandle::Small(handle), size));
+            }
+            SegregatorHandle::Large(handle) if !Self::is_small(new_layout) => {
+                //  Safety:
+                //  -   As per pre-conditions, `handle` was allocated by the large store.
+                let (handle, size) = unsafe { self.large.shrink(handle, old_layout, new_layout)? };
+
+                return Ok((SegregatorHandle::Large(handle), size));
+            }
+            SegregatorHandle::Large(handle) => handle,
+        };
+
+        let Ok((result, size)) = self.small.allocate(new_layout) else {
+            //  Safety:
+            //  -   As per pre-conditions, `handle` was allocated by the large store.
+            let (handle, size) = unsafe { self.large.shrink(handle, old_layout, new_layout)? };
+
+            return Ok((SegregatorHandle::Large(handle), size));
+        };
+
+        //  Safety:
+        //  -   `handle` is valid, as per pre-conditions.
+        //  -   `result` is valid, since newly allocated.
+        let (old, new) = unsafe { (self.large.resolve_mut(handle), self.small.resolve_mut(result)) };
+
+        //  Safety:
+        //  -   `old` is valid for `new_layout.size()` bytes, as `new_layout.size() <= old_layout.size()`.
+        //  -   `new` is valid for `new_layout.size()` bytes, since newly allocated with `new_layout`.
+        //  -   `old` and `new` point to non-overlapping areas, since they belong to different live blocks of memory.
+        unsafe { ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), new_layout.size()) };
+
+        //  Safety:
+        //  -   `handle` was allocated by the large store, with `old_layout`, and is still valid.
+        unsafe { self.large.deallocate(handle, old_layout) };
+
+        Ok((SegregatorHandle::Small(result), size))
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the store which allocated `handle`.
+unsafe impl<const THRESHOLD: usize, Small, Large> StoreStable for Segregator<THRESHOLD, Small, Large>
+where
+    Small: StoreStable,
+    Large: StoreStable,
+{
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the store which allocated `handle`.
+unsafe impl<const THRESHOLD: usize, Small, Large> StorePinning for Segregator<THRESHOLD, Small, Large>
+where
+    Small: StorePinning,
+    Large: StorePinning,
+{
+}
+
+//  Safety:
+//  -   All shares wrap shares of the same small and large stores, hence any handle resolves identically.
+unsafe impl<const THRESHOLD: usize, Small, Large> StoreSharing for Segregator<THRESHOLD, Small, Large>
+where
+    Small: StoreSharing,
+    Large: StoreSharing,
+{
+    type SharingError = SegregatorSharingError<Small::SharingError, Large::SharingError>;
+
+    fn is_sharing_with(&self, other: &Self) -> bool {
+        self.small.is_sharing_with(&other.small) && self.large.is_sharing_with(&other.large)
+    }
+
+    fn share(&self) -> Result<Self, Self::SharingError>
+    where
+        Self: Sized,
+    {
+        let small = self.small.share().map_err(SegregatorSharingError::Small)?;
+        let large = self.large.share().map_err(SegregatorSharingError::Large)?;
+
+        Ok(Self::new(small, large))
+    }
+}
+
+impl<const THRESHOLD: usize, Small, Large> fmt::Debug for Segregator<THRESHOLD, Small, Large>
+where
+    Small: fmt::Debug,
+    Large: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("Segregator")
+            .field("threshold", &THRESHOLD)
+            .field("small", &self.small)
+            .field("large", &self.large)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+impl<const THRESHOLD: usize, Small, Large> Segregator<THRESHOLD, Small, Large> {
+    #[inline(always)]
+    const fn is_small(layout: Layout) -> bool {
+        layout.size() <= THRESHOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::{
+        collection::{LinkedList, StoreVec},
+        store::InlineSlabStore,
+    };
+
+    use super::*;
+
+    type TestStore = Segregator<32, InlineSlabStore<u16, [u64; 64]>, Global>;
+
+    #[test]
+    fn grow_and_shrink_across_threshold() {
+        let store = TestStore::default();
+
+        let (small, large) = (Layout::new::<[u64; 2]>(), Layout::new::<[u64; 16]>());
+
+        let (handle, _) = Store::allocate(&store, small).unwrap();
+
+        assert!(handle.is_small());
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        unsafe { Store::resolve(&store, handle).as_ptr().cast::<[u64; 2]>().write([1, 2]) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `small`, and is still valid.
+        //  -   `large` is larger than `small`.
+        let (handle, _) = unsafe { Store::grow(&store, handle, small, large).unwrap() };
+
+        assert!(handle.is_large());
+
+        //  Safety:
+        //  -   `handle` was grown by `store`, to `large`, and is still valid.
+        //  -   `small` is smaller than `large`.
+        let (handle, _) = unsafe { Store::shrink(&store, handle, large, small).unwrap() };
+
+        assert!(handle.is_small());
+
+        //  Safety:
+        //  -   `handle` was shrunk by `store`, and is still valid.
+        assert_eq!([1, 2], unsafe {
+            Store::resolve(&store, handle).as_ptr().cast::<[u64; 2]>().read()
+        });
+
+        //  Safety:
+        //  -   `handle` was shrunk by `store`, to `small`, and is still valid.
+        unsafe { Store::deallocate(&store, handle, small) };
+    }
+
+    #[test]
+    fn mixed_sizes() {
+        let mut list = LinkedList::<u32, TestStore>::new();
+        let mut v = StoreVec::<u32, TestStore>::new();
+
+        for i in 0..100 {
+            list.try_push_back(i).unwrap();
+            v.push(i);
+        }
+
+        assert!(list.iter().copied().eq(0..100));
+        assert!(v.as_slice().iter().copied().eq(0..100));
+    }
+} // mod tests
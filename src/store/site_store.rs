@@ -0,0 +1,482 @@
+//! A Store adapter, attributing the live bytes of the underlying store to the call sites which allocated them.
+//!
+//! This store is meant to find out where the memory of a store went, for example when a bump store runs out of space,
+//! much like a heap profiler would, but without `alloc` nor any form of unwinding: the call site of each allocation is
+//! obtained through `#[track_caller]`, which `TypedHandle`, `UniqueHandle`, and the collections propagate, so that the
+//! site reported is the user code calling `TypedHandle::new` or `StoreVec::push`, rather than the library internals.
+//!
+//! A `grow` attributes the whole block of memory to the call site of the `grow`, whereas a `shrink` keeps the block of
+//! memory attributed to its current call site.
+
+use core::{
+    alloc::{AllocError, Layout},
+    array,
+    cell::Cell,
+    cmp, fmt,
+    panic::Location,
+    ptr::{Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable};
+
+/// A handle of a `SiteStore`, wrapping the handle of the underlying store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SiteHandle<H> {
+    handle: H,
+    //  Index of the call site the block of memory is attributed to, or `UNATTRIBUTED`, or `DANGLING`.
+    site: u32,
+}
+
+/// The statistics of a single call site of a `SiteStore`.
+///
+/// The number of bytes are computed from the layouts passed to the store, rather than from the sizes it returned.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SiteStats {
+    /// Call site of the allocations.
+    pub location: &'static Location<'static>,
+    /// Number of successful allocations and grows.
+    pub allocations: u64,
+    /// Number of bytes currently allocated.
+    pub live_bytes: usize,
+    /// Maximum number of bytes allocated at any one time.
+    pub peak_bytes: usize,
+}
+
+/// A report of the call sites of a `SiteStore`, ordered by decreasing number of live bytes.
+///
+/// The `Display` implementation formats one line per call site, with the biggest callers first.
+#[derive(Clone, Copy, Debug)]
+pub struct SiteReport<const N: usize> {
+    sites: [Option<SiteStats>; N],
+    unattributed_bytes: usize,
+}
+
+impl<const N: usize> SiteReport<N> {
+    /// Returns the call sites, ordered by decreasing number of live bytes, then decreasing peak number of bytes.
+    pub fn iter(&self) -> impl Iterator<Item = &SiteStats> {
+        self.sites.iter().map_while(Option::as_ref)
+    }
+
+    /// Returns the number of live bytes allocated once all `N` call sites were already tracked.
+    pub const fn unattributed_bytes(&self) -> usize {
+        self.unattributed_bytes
+    }
+}
+
+impl<const N: usize> fmt::Display for SiteReport<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        for site in self.iter() {
+            writeln!(
+                f,
+                "{:>10} bytes live, {:>10} bytes peak, {:>8} allocations, at {}",
+                site.live_bytes, site.peak_bytes, site.allocations, site.location
+            )?;
+        }
+
+        if self.unattributed_bytes > 0 {
+            writeln!(f, "{:>10} bytes live, unattributed", self.unattributed_bytes)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An adapter of `Store` or `StoreSingle`, attributing the live bytes of the underlying store to their call sites.
+///
+/// Implements `Store` whenever `S` does, and `StoreSingle` whenever `S` does.
+///
+/// Generic parameters:
+///
+/// -   `S` is the underlying store.
+/// -   `N` is the maximum number of call sites tracked, the bytes allocated from further call sites are counted as
+///     unattributed.
+pub struct SiteStore<S, const N: usize = 32> {
+    sites: [Cell<Option<SiteStats>>; N],
+    unattributed_bytes: Cell<usize>,
+    store: S,
+}
+
+impl<S, const N: usize> SiteStore<S, N> {
+    /// Creates a new instance, atop `store`.
+    pub fn new_in(store: S) -> Self {
+        assert!(N < UNATTRIBUTED as usize, "{N} call sites cannot be represented");
+
+        let sites = array::from_fn(|_| Cell::new(None));
+
+        Self {
+            sites,
+            unattributed_bytes: Cell::new(0),
+            store,
+        }
+    }
+
+    /// Returns a report of the call sites tracked so far, with the biggest callers first.
+    pub fn report(&self) -> SiteReport<N> {
+        let mut sites: [Option<SiteStats>; N] = array::from_fn(|index| self.sites[index].get());
+
+        //  `None` sorts last, as `Reverse(None)` is greater than any `Reverse(Some(_))`.
+        sites.sort_unstable_by_key(|site| cmp::Reverse(site.map(|site| (site.live_bytes, site.peak_bytes))));
+
+        SiteReport {
+            sites,
+            unattributed_bytes: self.unattributed_bytes.get(),
+        }
+    }
+
+    /// Returns a reference to the underlying store.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<S: Default, const N: usize> Default for SiteStore<S, N> {
+    fn default() -> Self {
+        Self::new_in(S::default())
+    }
+}
+
+unsafe impl<S: StoreDangling, const N: usize> StoreDangling for SiteStore<S, N> {
+    type Handle = SiteHandle<S::Handle>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let handle = self.store.dangling(alignment)?;
+
+        Ok(SiteHandle { handle, site: DANGLING })
+    }
+}
+
+unsafe impl<S: Store, const N: usize> Store for SiteStore<S, N> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    #[track_caller]
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let (handle, size) = self.store.allocate(layout)?;
+
+        Ok((self.attribute(handle, layout, Location::caller()), size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        self.release(handle.site, layout);
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle.handle, layout) }
+    }
+
+    #[track_caller]
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.grow(handle.handle, old_layout, new_layout)? };
+
+        self.release(handle.site, old_layout);
+
+        Ok((self.attribute(inner, new_layout, Location::caller()), size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.shrink(handle.handle, old_layout, new_layout)? };
+
+        self.resize(handle.site, old_layout, new_layout);
+
+        let handle = SiteHandle {
+            handle: inner,
+            site: handle.site,
+        };
+
+        Ok((handle, size))
+    }
+}
+
+unsafe impl<S: StoreSingle, const N: usize> StoreSingle for SiteStore<S, N> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve_mut(handle.handle) }
+    }
+
+    #[track_caller]
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let (handle, size) = self.store.allocate(layout)?;
+
+        Ok((self.attribute(handle, layout, Location::caller()), size))
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.release(handle.site, layout);
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle.handle, layout) }
+    }
+
+    #[track_caller]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.grow(handle.handle, old_layout, new_layout)? };
+
+        self.release(handle.site, old_layout);
+
+        Ok((self.attribute(inner, new_layout, Location::caller()), size))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let (inner, size) = unsafe { self.store.shrink(handle.handle, old_layout, new_layout)? };
+
+        self.resize(handle.site, old_layout, new_layout);
+
+        let handle = SiteHandle {
+            handle: inner,
+            site: handle.site,
+        };
+
+        Ok((handle, size))
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: StoreStable, const N: usize> StoreStable for SiteStore<S, N> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: StorePinning, const N: usize> StorePinning for SiteStore<S, N> {}
+
+impl<S: fmt::Debug, const N: usize> fmt::Debug for SiteStore<S, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("SiteStore")
+            .field("report", &self.report())
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+//  Site of the blocks of memory allocated once all call sites are tracked.
+const UNATTRIBUTED: u32 = u32::MAX - 1;
+
+//  Site of dangling handles.
+const DANGLING: u32 = u32::MAX;
+
+impl<S, const N: usize> SiteStore<S, N> {
+    //  Attributes a freshly allocated, or grown, block of memory to `location`.
+    fn attribute<H>(&self, handle: H, layout: Layout, location: &'static Location<'static>) -> SiteHandle<H> {
+        let existing = self
+            .sites
+            .iter()
+            .position(|site| site.get().map_or(false, |site| site.location == location));
+
+        let Some(index) = existing.or_else(|| self.sites.iter().position(|site| site.get().is_none())) else {
+            self.unattributed_bytes
+                .set(self.unattributed_bytes.get() + layout.size());
+
+            return SiteHandle {
+                handle,
+                site: UNATTRIBUTED,
+            };
+        };
+
+        let mut site = self.sites[index].get().unwrap_or(SiteStats {
+            location,
+            allocations: 0,
+            live_bytes: 0,
+            peak_bytes: 0,
+        });
+
+        site.allocations += 1;
+        site.live_bytes += layout.size();
+        site.peak_bytes = site.peak_bytes.max(site.live_bytes);
+
+        self.sites[index].set(Some(site));
+
+        SiteHandle {
+            handle,
+            site: index as u32,
+        }
+    }
+
+    //  Releases the bytes of a block of memory from its site.
+    fn release(&self, site: u32, layout: Layout) {
+        self.resize(site, layout, Layout::new::<()>());
+    }
+
+    //  Adjusts the number of live bytes of a site, after a block of memory changed size.
+    fn resize(&self, site: u32, old_layout: Layout, new_layout: Layout) {
+        if site == UNATTRIBUTED {
+            let bytes = self.unattributed_bytes.get().saturating_sub(old_layout.size()) + new_layout.size();
+
+            self.unattributed_bytes.set(bytes);
+
+            return;
+        }
+
+        let Some(cell) = self.sites.get(site as usize) else {
+            return;
+        };
+
+        let Some(mut stats) = cell.get() else { return };
+
+        stats.live_bytes = stats.live_bytes.saturating_sub(old_layout.size()) + new_layout.size();
+
+        cell.set(Some(stats));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{alloc::Global, string::String};
+
+    use core::fmt::Write;
+
+    use crate::{
+        collection::{LinkedList, StoreVec},
+        extension::typed::TypedHandle,
+        store::InlineBumpStore,
+    };
+
+    use super::*;
+
+    #[test]
+    fn attribution() {
+        let store = SiteStore::<Global>::default();
+
+        let (small, small_line) = (TypedHandle::new([1u8; 16], &store), line!());
+        let (large, large_line) = (TypedHandle::new([2u8; 64], &store), line!());
+
+        let report = store.report();
+        let sites: Vec<_> = report.iter().collect();
+
+        assert_eq!(2, sites.len());
+
+        assert_eq!(file!(), sites[0].location.file());
+        assert_eq!(large_line, sites[0].location.line());
+        assert_eq!(64, sites[0].live_bytes);
+
+        assert_eq!(file!(), sites[1].location.file());
+        assert_eq!(small_line, sites[1].location.line());
+        assert_eq!(16, sites[1].live_bytes);
+
+        //  Safety:
+        //  -   `large` was allocated by `store`, and is still valid.
+        unsafe { large.deallocate(&store) };
+
+        let report = store.report();
+        let sites: Vec<_> = report.iter().collect();
+
+        assert_eq!(small_line, sites[0].location.line());
+        assert_eq!((0, 64), (sites[1].live_bytes, sites[1].peak_bytes));
+
+        //  Safety:
+        //  -   `small` was allocated by `store`, and is still valid.
+        unsafe { small.deallocate(&store) };
+
+        assert!(store.report().iter().all(|site| site.live_bytes == 0));
+    }
+
+    #[test]
+    fn collections() {
+        let mut vec = StoreVec::<u32, SiteStore<Global>>::new();
+        let mut list = LinkedList::<u32, SiteStore<Global>>::new();
+
+        let mut push_line = 0;
+
+        for i in 0..32 {
+            (push_line, _) = (line!(), vec.push(i));
+        }
+
+        let (try_push_line, _) = (line!(), list.try_push_back(1));
+
+        let report = vec.store().report();
+        let sites: Vec<_> = report.iter().collect();
+
+        assert_eq!(1, sites.len(), "{report}");
+        assert_eq!(push_line, sites[0].location.line());
+        assert_eq!(vec.capacity() * 4, sites[0].live_bytes);
+
+        let report = list.store().report();
+        let sites: Vec<_> = report.iter().collect();
+
+        assert_eq!(1, sites.len(), "{report}");
+        assert_eq!(try_push_line, sites[0].location.line());
+    }
+
+    #[test]
+    fn unattributed() {
+        let store = SiteStore::<InlineBumpStore<u16, [u64; 32]>, 1>::default();
+
+        let first = TypedHandle::new(1u32, &store);
+        let second = TypedHandle::new(2u64, &store);
+
+        let report = store.report();
+
+        assert_eq!(1, report.iter().count());
+        assert_eq!(8, report.unattributed_bytes());
+
+        //  Safety:
+        //  -   `first` and `second` were allocated by `store`, and are still valid.
+        unsafe {
+            first.deallocate(&store);
+            second.deallocate(&store);
+        }
+
+        assert_eq!(0, store.report().unattributed_bytes());
+    }
+
+    #[test]
+    fn display() {
+        let store = SiteStore::<Global>::default();
+
+        let (handle, line) = (TypedHandle::new([0u8; 48], &store), line!());
+
+        let mut report = String::new();
+        write!(report, "{}", store.report()).unwrap();
+
+        assert!(report.contains("48 bytes live"), "{report}");
+        assert!(report.contains(&format!("{}:{line}:", file!())), "{report}");
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        unsafe { handle.deallocate(&store) };
+    }
+} // mod tests
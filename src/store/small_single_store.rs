@@ -0,0 +1,401 @@
+//! An implementation of `StoreSingle` providing a single, inline, block of memory, spilling to an allocator when full.
+//!
+//! This store is suitable for `Box`, `Vec`, or `VecDeque`, for example: `StoreVec<u8, SmallSingleStore<[u8; 24],
+//! Global>>` is a small vector, which only allocates once it holds more than 24 bytes.
+//!
+//! The block of memory is inline for as long as it fits within `T`, and is moved to the allocator as soon as a `grow`
+//! exceeds `T`. A `shrink` only moves it back inline if `inline_on_shrink` is set, as a workload which grew the block
+//! of memory once is likely to grow it again.
+
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    fmt,
+    mem::{self, MaybeUninit},
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::interface::{StoreDangling, StoreSingle, StoreStable};
+
+/// A handle of a `SmallSingleStore`, either inline, or pointing to a block of memory of the allocator.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SmallHandle(Option<NonNull<u8>>);
+
+impl SmallHandle {
+    /// Returns whether the block of memory associated to the handle was spilled to the allocator.
+    pub const fn is_spilled(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+//  Safety:
+//  -   The handle is only a pointer, which is only dereferenced through the store.
+unsafe impl Send for SmallHandle {}
+
+//  Safety:
+//  -   The handle is only a pointer, which is only dereferenced through the store.
+unsafe impl Sync for SmallHandle {}
+
+/// An implementation of `StoreSingle` providing a single, inline, block of memory, spilling to `A` when full.
+///
+/// The inline block of memory is aligned and sized as per `T`.
+pub struct SmallSingleStore<T, A> {
+    inline: MaybeUninit<T>,
+    //  Whether `shrink` moves the block of memory back inline, when it fits.
+    inline_on_shrink: bool,
+    allocator: A,
+}
+
+impl<T, A> SmallSingleStore<T, A> {
+    /// Creates a new instance, spilling to `allocator`.
+    pub const fn new_in(allocator: A) -> Self {
+        Self {
+            inline: MaybeUninit::uninit(),
+            inline_on_shrink: false,
+            allocator,
+        }
+    }
+
+    /// Returns whether `shrink` moves a spilled block of memory back inline, when it fits.
+    pub const fn inline_on_shrink(&self) -> bool {
+        self.inline_on_shrink
+    }
+
+    /// Sets whether `shrink` moves a spilled block of memory back inline, when it fits.
+    ///
+    /// Defaults to `false`.
+    pub fn set_inline_on_shrink(&mut self, inline_on_shrink: bool) {
+        self.inline_on_shrink = inline_on_shrink;
+    }
+
+    /// Returns a reference to the allocator.
+    pub const fn allocator(&self) -> &A {
+        &self.allocator
+    }
+}
+
+impl<T, A: Default> Default for SmallSingleStore<T, A> {
+    fn default() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+unsafe impl<T, A> StoreDangling for SmallSingleStore<T, A> {
+    type Handle = SmallHandle;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        if alignment.as_usize() <= Alignment::of::<T>().as_usize() {
+            return Ok(SmallHandle(None));
+        }
+
+        //  The inline block is not suitably aligned, as any allocation with this alignment would be spilled.
+        let pointer = ptr::invalid_mut(alignment.as_usize());
+
+        //  Safety:
+        //  -   `pointer` is non null, as `alignment` is non zero.
+        Ok(SmallHandle(Some(unsafe { NonNull::new_unchecked(pointer) })))
+    }
+}
+
+unsafe impl<T, A: Allocator> StoreSingle for SmallSingleStore<T, A> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        if let Some(pointer) = handle.0 {
+            return pointer;
+        }
+
+        let pointer = self.inline.as_ptr() as *mut T;
+
+        //  Safety:
+        //  -   `self` is non null.
+        unsafe { NonNull::new_unchecked(pointer) }.cast()
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if let Some(pointer) = handle.0 {
+            return pointer;
+        }
+
+        let pointer = self.inline.as_mut_ptr();
+
+        //  Safety:
+        //  -   `self` is non null.
+        unsafe { NonNull::new_unchecked(pointer) }.cast()
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        if Self::fits_inline(layout) {
+            return Ok((SmallHandle(None), mem::size_of::<T>()));
+        }
+
+        let slice = Allocator::allocate(&self.allocator, layout)?;
+
+        Ok((SmallHandle(Some(slice.as_non_null_ptr())), slice.len()))
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        let Some(pointer) = handle.0 else { return };
+
+        //  Safety:
+        //  -   `pointer` was allocated by `self.allocator`, with `layout`, as per pre-conditions.
+        unsafe { Allocator::deallocate(&self.allocator, pointer, layout) };
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() >= old_layout.size(),
+            "{new_layout:?} must have a greater size than {old_layout:?}"
+        );
+
+        if let Some(pointer) = handle.0 {
+            //  Safety:
+            //  -   `pointer` was allocated by `self.allocator`, with `old_layout`, as per pre-conditions.
+            //  -   `new_layout.size()` is greater than or equal to `old_layout.size()`, as per pre-conditions.
+            let slice = unsafe { Allocator::grow(&self.allocator, pointer, old_layout, new_layout)? };
+
+            return Ok((SmallHandle(Some(slice.as_non_null_ptr())), slice.len()));
+        }
+
+        if Self::fits_inline(new_layout) {
+            return Ok((SmallHandle(None), mem::size_of::<T>()));
+        }
+
+        let slice = Allocator::allocate(&self.allocator, new_layout)?;
+
+        //  Safety:
+        //  -   The source is valid for reads of `old_layout.size()` bytes, as `old_layout` fits the inline block.
+        //  -   The destination is valid for writes of `old_layout.size()` bytes, as it is larger.
+        //  -   Source and destination do not overlap, as the destination was just allocated.
+        unsafe { ptr::copy_nonoverlapping(self.inline.as_ptr() as *const u8, slice.as_mut_ptr(), old_layout.size()) };
+
+        Ok((SmallHandle(Some(slice.as_non_null_ptr())), slice.len()))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        debug_assert!(
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
+        );
+
+        let Some(pointer) = handle.0 else {
+            if Self::fits_inline(new_layout) {
+                return Ok((SmallHandle(None), mem::size_of::<T>()));
+            }
+
+            //  `new_layout` may be more aligned than the inline block, in which case the block must be spilled.
+            let slice = Allocator::allocate(&self.allocator, new_layout)?;
+
+            //  Safety:
+            //  -   The source is valid for reads of `new_layout.size()` bytes, as `old_layout` fits the inline block,
+            //      and is larger.
+            //  -   The destination is valid for writes of `new_layout.size()` bytes, as it was allocated with it.
+            //  -   Source and destination do not overlap, as the destination was just allocated.
+            unsafe {
+                ptr::copy_nonoverlapping(self.inline.as_ptr() as *const u8, slice.as_mut_ptr(), new_layout.size())
+            };
+
+            return Ok((SmallHandle(Some(slice.as_non_null_ptr())), slice.len()));
+        };
+
+        if !self.inline_on_shrink || !Self::fits_inline(new_layout) {
+            //  Safety:
+            //  -   `pointer` was allocated by `self.allocator`, with `old_layout`, as per pre-conditions.
+            //  -   `new_layout.size()` is less than or equal to `old_layout.size()`, as per pre-conditions.
+            let slice = unsafe { Allocator::shrink(&self.allocator, pointer, old_layout, new_layout)? };
+
+            return Ok((SmallHandle(Some(slice.as_non_null_ptr())), slice.len()));
+        }
+
+        //  Safety:
+        //  -   The source is valid for reads of `new_layout.size()` bytes, as it is larger.
+        //  -   The destination is valid for writes of `new_layout.size()` bytes, as `new_layout` fits inline.
+        //  -   Source and destination do not overlap, as the source was allocated by `self.allocator`.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                pointer.as_ptr() as *const u8,
+                self.inline.as_mut_ptr() as *mut u8,
+                new_layout.size(),
+            )
+        };
+
+        //  Safety:
+        //  -   `pointer` was allocated by `self.allocator`, with `old_layout`, as per pre-conditions.
+        unsafe { Allocator::deallocate(&self.allocator, pointer, old_layout) };
+
+        Ok((SmallHandle(None), mem::size_of::<T>()))
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, as long as `self` doesn't move.
+unsafe impl<T, A: Allocator> StoreStable for SmallSingleStore<T, A> {}
+
+impl<T, A: fmt::Debug> fmt::Debug for SmallSingleStore<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let layout = Layout::new::<T>();
+
+        f.debug_struct("SmallSingleStore")
+            .field("size", &layout.size())
+            .field("align", &layout.align())
+            .field("inline_on_shrink", &self.inline_on_shrink)
+            .field("allocator", &self.allocator)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+impl<T, A> SmallSingleStore<T, A> {
+    const fn fits_inline(layout: Layout) -> bool {
+        let own = Layout::new::<T>();
+
+        layout.align() <= own.align() && layout.size() <= own.size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::collection::StoreVec;
+
+    use super::*;
+
+    type SmallVec = StoreVec<u8, SmallSingleStore<[u8; 24], Global>>;
+
+    fn is_inline(v: &SmallVec) -> bool {
+        let store = v.store() as *const _ as usize;
+        let elements = v.as_slice().as_ptr() as usize;
+
+        (store..store + mem::size_of_val(v.store())).contains(&elements)
+    }
+
+    #[test]
+    fn inline() {
+        let mut v = SmallVec::with_capacity(24);
+
+        for i in 0..24 {
+            v.push(i);
+        }
+
+        assert_eq!(24, v.capacity());
+
+        assert!(is_inline(&v));
+    }
+
+    #[test]
+    fn spill() {
+        let mut v = SmallVec::new();
+
+        for i in 0..100 {
+            v.push(i);
+        }
+
+        assert!(!is_inline(&v));
+        assert!(v.as_slice().iter().copied().eq(0..100));
+    }
+
+    #[test]
+    fn spill_over_aligned() {
+        //  The elements are more aligned than the inline block, hence are always spilled.
+        let mut v = StoreVec::<u32, SmallSingleStore<[u8; 24], Global>>::new();
+
+        assert!(v.is_empty());
+
+        for i in 0..10 {
+            v.push(i);
+        }
+
+        assert!(v.as_slice().iter().copied().eq(0..10));
+    }
+
+    #[test]
+    fn inline_on_shrink() {
+        let mut store = SmallSingleStore::<[u64; 4], Global>::default();
+
+        let (small, large) = (Layout::new::<[u64; 2]>(), Layout::new::<[u64; 8]>());
+
+        let (handle, _) = store.allocate(large).unwrap();
+
+        assert!(handle.is_spilled());
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        unsafe { store.resolve_mut(handle).as_ptr().cast::<[u64; 8]>().write([7; 8]) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `large`, and is still valid.
+        //  -   `small` is smaller than `large`.
+        let (handle, _) = unsafe { store.shrink(handle, large, small).unwrap() };
+
+        assert!(handle.is_spilled());
+
+        store.set_inline_on_shrink(true);
+
+        let tiny = Layout::new::<u64>();
+
+        //  Safety:
+        //  -   `handle` was shrunk by `store`, to `small`, and is still valid.
+        //  -   `tiny` is smaller than `small`.
+        let (handle, _) = unsafe { store.shrink(handle, small, tiny).unwrap() };
+
+        assert!(!handle.is_spilled());
+
+        //  Safety:
+        //  -   `handle` was shrunk by `store`, and is still valid.
+        assert_eq!(7, unsafe { store.resolve(handle).as_ptr().cast::<u64>().read() });
+
+        //  Safety:
+        //  -   `handle` was shrunk by `store`, to `tiny`, and is still valid.
+        unsafe { store.deallocate(handle, tiny) };
+    }
+
+    #[test]
+    fn shrink_over_aligned() {
+        let mut store = SmallSingleStore::<[u8; 32], Global>::default();
+
+        let (old_layout, new_layout) = (Layout::new::<[u8; 16]>(), Layout::from_size_align(8, 16).unwrap());
+
+        let (handle, _) = store.allocate(old_layout).unwrap();
+
+        assert!(!handle.is_spilled());
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, and is still valid.
+        unsafe { store.resolve_mut(handle).as_ptr().cast::<[u8; 16]>().write([7; 16]) };
+
+        //  Safety:
+        //  -   `handle` was allocated by `store`, with `old_layout`, and is still valid.
+        //  -   `new_layout` is smaller than `old_layout`.
+        let (handle, _) = unsafe { store.shrink(handle, old_layout, new_layout).unwrap() };
+
+        //  The inline block is only aligned on 1 byte, hence the block is spilled.
+        assert!(handle.is_spilled());
+
+        //  Safety:
+        //  -   `handle` was shrunk by `store`, and is still valid.
+        let pointer = unsafe { store.resolve(handle) };
+
+        assert_eq!(0, pointer.addr().get() % new_layout.align());
+
+        //  Safety:
+        //  -   `pointer` is valid for reads of `new_layout.size()` bytes.
+        assert_eq!([7; 8], unsafe { pointer.as_ptr().cast::<[u8; 8]>().read() });
+
+        //  Safety:
+        //  -   `handle` was shrunk by `store`, to `new_layout`, and is still valid.
+        unsafe { store.deallocate(handle, new_layout) };
+    }
+} // mod tests
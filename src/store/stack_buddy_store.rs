@@ -0,0 +1,320 @@
+//! A "buddy allocator" Store, referencing its block of memory.
+//!
+//! A store which references a stack or statically allocated fixed-sized block of memory, managed as a buddy allocator.
+//! Multiple instances may reference the same block, and all instances referencing the same block are fungible.
+//!
+//! See `InlineBuddyStore` for the details of the buddy allocator itself.
+
+use core::{
+    alloc::{AllocError, Layout},
+    fmt,
+    marker::PhantomData,
+    mem,
+    ptr::{self, Alignment, NonNull},
+};
+
+use crate::{
+    interface::{Store, StoreDangling, StorePinning, StoreSharing, StoreSingle, StoreStable},
+    store::{InlineBuddyStore, Region, StoreLayoutDump},
+};
+
+/// The backing block of memory for the store.
+///
+/// Generic parameters:
+///
+/// -   The block of memory is aligned and sized as per `T`.
+///
+/// A small portion of the block of memory -- up to 1/16th -- is reserved for book-keeping.
+pub struct StackBuddyBlock<T> {
+    //  The links of the free lists are stored as `usize`, so that stores of any handle type may share the block.
+    buddy: InlineBuddyStore<usize, T>,
+}
+
+impl<T> StackBuddyBlock<T> {
+    /// Creates a new, empty, block.
+    pub fn new() -> Self {
+        let buddy = InlineBuddyStore::default();
+
+        Self { buddy }
+    }
+
+    /// Creates a new store referencing this block.
+    pub fn create_store<H>(&self) -> StackBuddyStore<'_, H, T> {
+        let block = &self.buddy;
+        let _marker = PhantomData;
+
+        StackBuddyStore { block, _marker }
+    }
+}
+
+impl<T> Default for StackBuddyBlock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A store instance referencing its block.
+///
+/// Generic parameters:
+///
+/// -   `H` is the handle type, it must convertible to and from `usize`.
+/// -   `T` is the type of the memory of the referenced block.
+///
+/// Allocations fail if the size of `T` is not representable by `H`.
+pub struct StackBuddyStore<'a, H, T> {
+    block: &'a InlineBuddyStore<usize, T>,
+    _marker: PhantomData<fn(H) -> H>,
+}
+
+//  Cannot be const, because TryFrom is not marked #[const_trait].
+unsafe impl<'a, H, T> StoreDangling for StackBuddyStore<'a, H, T>
+where
+    H: Copy + TryFrom<usize>,
+{
+    type Handle = H;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let offset = self.block.dangling(alignment)?;
+
+        offset.try_into().map_err(|_| AllocError)
+    }
+}
+
+unsafe impl<'a, H, T> Store for StackBuddyStore<'a, H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        Self::check_representable()?;
+
+        let (offset, size) = Store::allocate(self.block, layout)?;
+
+        Ok((Self::from_offset(offset), size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   `handle` was allocated by a store referencing `self.block`, with `layout`, as per pre-conditions.
+        unsafe { Store::deallocate(self.block, Self::into_offset(handle), layout) };
+    }
+
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   `handle` was allocated by a store referencing `self.block`, as per pre-conditions.
+        unsafe { Store::resolve(self.block, Self::into_offset(handle)) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   `handle` was allocated by a store referencing `self.block`, with `old_layout`, as per pre-conditions.
+        //  -   `new_layout.size()` is greater than or equal to `old_layout.size()`, as per pre-conditions.
+        let (offset, size) = unsafe { Store::grow(self.block, Self::into_offset(handle), old_layout, new_layout)? };
+
+        Ok((Self::from_offset(offset), size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   `handle` was allocated by a store referencing `self.block`, with `old_layout`, as per pre-conditions.
+        //  -   `new_layout.size()` is less than or equal to `old_layout.size()`, as per pre-conditions.
+        let (offset, size) = unsafe { Store::shrink(self.block, Self::into_offset(handle), old_layout, new_layout)? };
+
+        Ok((Self::from_offset(offset), size))
+    }
+}
+
+unsafe impl<'a, H, T> StoreSingle for StackBuddyStore<'a, H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::resolve(self, handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        <Self as Store>::allocate(self, layout)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::grow(self, handle, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::shrink(self, handle, old_layout, new_layout) }
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address.
+unsafe impl<'a, H, T> StoreStable for StackBuddyStore<'a, H, T> where H: Copy + TryFrom<usize> + TryInto<usize> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` always returns the same address, as the block cannot move while referenced.
+unsafe impl<'a, H, T> StorePinning for StackBuddyStore<'a, H, T> where H: Copy + TryFrom<usize> + TryInto<usize> {}
+
+/// Safety:
+/// -   All instances referencing the same StackBuddyBlock are fungible.
+unsafe impl<'a, H, T> StoreSharing for StackBuddyStore<'a, H, T>
+where
+    H: Copy + TryFrom<usize> + TryInto<usize>,
+{
+    type SharingError = !;
+
+    fn is_sharing_with(&self, other: &Self) -> bool {
+        ptr::eq(self.block, other.block)
+    }
+
+    fn share(&self) -> Result<Self, Self::SharingError>
+    where
+        Self: Sized,
+    {
+        let block = self.block;
+        let _marker = PhantomData;
+
+        Ok(Self { block, _marker })
+    }
+}
+
+impl<'a, H, T> StoreLayoutDump for StackBuddyStore<'a, H, T> {
+    fn capacity(&self) -> usize {
+        self.block.capacity()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        self.block.watermark()
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        self.block.visit_regions(visitor)
+    }
+}
+
+impl<'a, H, T> fmt::Debug for StackBuddyStore<'a, H, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("StackBuddyStore").field("block", self.block).finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+impl<'a, H, T> StackBuddyStore<'a, H, T>
+where
+    H: TryFrom<usize>,
+{
+    //  Checks that all offsets within the block are representable by `H`, hence that `from_offset` cannot fail.
+    fn check_representable() -> Result<(), AllocError> {
+        H::try_from(mem::size_of::<T>()).map(|_| ()).map_err(|_| AllocError)
+    }
+
+    #[inline(always)]
+    fn from_offset(offset: usize) -> H {
+        let Ok(handle) = offset.try_into() else {
+            unreachable!("All offsets within memory to be representable")
+        };
+
+        handle
+    }
+}
+
+impl<'a, H, T> StackBuddyStore<'a, H, T>
+where
+    H: TryInto<usize>,
+{
+    #[inline(always)]
+    fn into_offset(handle: H) -> usize {
+        let offset = handle.try_into();
+
+        debug_assert!(offset.is_ok());
+
+        //  Safety:
+        //  -   `handle` was created from `usize`, hence converting back always succeeds.
+        unsafe { offset.unwrap_unchecked() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //  1024 bytes, of which 32 are reserved for the bitmap.
+    type TestBlock = StackBuddyBlock<[u64; 128]>;
+
+    #[test]
+    fn sharing_coalesces() {
+        let block = TestBlock::new();
+        let (first, second) = (block.create_store::<u16>(), block.create_store::<u32>());
+
+        let (small, large) = (Layout::new::<[u64; 2]>(), Layout::new::<[u64; 64]>());
+
+        let handles: Vec<_> = (0..32).map(|_| Store::allocate(&first, small).unwrap().0).collect();
+
+        //  The large block is split.
+        Store::allocate(&second, large).unwrap_err();
+
+        for handle in handles {
+            //  Safety:
+            //  -   `handle` was allocated by a store referencing `block`, with `small`.
+            unsafe { Store::deallocate(&second, handle.into(), small) };
+        }
+
+        //  The large block is coalesced.
+        let (handle, size) = Store::allocate(&second, large).unwrap();
+
+        assert_eq!(512, size);
+
+        //  Safety:
+        //  -   `handle` was allocated by a store referencing `block`, with `large`.
+        unsafe { Store::deallocate(&first, handle.try_into().unwrap(), large) };
+    }
+
+    #[test]
+    fn unrepresentable_handle() {
+        let block = StackBuddyBlock::<[u64; 64]>::new();
+        let store = block.create_store::<u8>();
+
+        Store::allocate(&store, Layout::new::<u8>()).unwrap_err();
+    }
+} // mod tests
@@ -12,7 +12,13 @@ use core::{
     ptr::{self, Alignment, NonNull},
 };
 
-use crate::interface::{Store, StoreDangling, StorePinning, StoreSharing, StoreSingle, StoreStable};
+use crate::{
+    interface::{Store, StoreDangling, StorePinning, StoreSharing, StoreSingle, StoreStable},
+    store::{
+        layout_dump::{visit_watermarked, Region},
+        BumpCheckpoint, StoreLayoutDump,
+    },
+};
 
 /// The backing block of memory for the store.
 ///
@@ -52,6 +58,32 @@ impl<T> StackBumpBlock<T> {
             _marker,
         }
     }
+
+    /// Returns a checkpoint of the current watermark, to rewind to later.
+    pub fn checkpoint(&self) -> BumpCheckpoint {
+        BumpCheckpoint::new(self.watermark.get())
+    }
+
+    /// Rewinds the block to `checkpoint`, releasing all the blocks of memory allocated since.
+    ///
+    /// Since `self` is borrowed mutably, no store referencing this block is alive. All the handles allocated since
+    /// `checkpoint` was taken are invalidated. Only rewinding forward, to a checkpoint above the current watermark, is
+    /// a no-op. A checkpoint taken from another block is not told apart, and rewinds this block all the same.
+    pub fn rewind(&mut self, checkpoint: BumpCheckpoint) {
+        let watermark = checkpoint.watermark();
+
+        if watermark < self.watermark.get() {
+            self.watermark.set(watermark);
+        }
+    }
+
+    /// Resets the block, releasing all the blocks of memory.
+    ///
+    /// Since `self` is borrowed mutably, no store referencing this block is alive. All the handles allocated from this
+    /// block are invalidated.
+    pub fn reset(&mut self) {
+        self.rewind(BumpCheckpoint::new(0));
+    }
 }
 
 impl<T> Default for StackBumpBlock<T> {
@@ -79,7 +111,22 @@ where
     type Handle = H;
 
     fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
-        Self::from_offset(alignment.as_usize())
+        let offset = {
+            //  The block may be less aligned than `alignment`, hence the address, rather than the offset, is aligned.
+            let base = self.memory.as_mut_ptr().addr();
+
+            let alignment_mask = alignment.as_usize() - 1;
+
+            //  As for `NonNull::dangling`, the offset is never 0.
+            ((base + 1 + alignment_mask) & !alignment_mask) - base
+        };
+
+        //  The block may be too small to contain any suitably aligned offset.
+        if offset > self.memory.len() {
+            return Err(AllocError);
+        }
+
+        Self::from_offset(offset)
     }
 }
 
@@ -94,8 +141,14 @@ where
         Ok((result, layout.size()))
     }
 
-    #[inline(always)]
-    unsafe fn deallocate(&self, _handle: Self::Handle, _layout: Layout) {}
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        //  If `handle` points to the last allocation, its memory block can be reclaimed by lowering the watermark.
+        let offset = Self::into_offset(handle);
+
+        if offset + layout.size() == self.watermark.get() {
+            self.watermark.set(offset);
+        }
+    }
 
     #[inline(always)]
     unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
@@ -130,7 +183,7 @@ where
             let watermark = self.watermark.get();
 
             if offset + old_layout.size() == watermark
-                && new_layout.align() <= old_layout.align()
+                && (self.memory.as_mut_ptr().addr() + offset) % new_layout.align() == 0
                 && offset + new_layout.size() <= self.memory.len()
             {
                 let new_watermark = watermark - old_layout.size() + new_layout.size();
@@ -143,18 +196,28 @@ where
         self.grow_by_relocation(handle, old_layout, new_layout)
     }
 
-    #[inline(always)]
     unsafe fn shrink(
         &self,
         handle: Self::Handle,
         old_layout: Layout,
-        _new_layout: Layout,
+        new_layout: Layout,
     ) -> Result<(Self::Handle, usize), AllocError> {
         debug_assert!(
-            _new_layout.size() >= old_layout.size(),
-            "{_new_layout:?} must have a smaller size than {old_layout:?}"
+            new_layout.size() <= old_layout.size(),
+            "{new_layout:?} must have a smaller size than {old_layout:?}"
         );
 
+        //  As an optimization, if `handle` points to the last allocation, the tail of its memory block may be
+        //  reclaimed.
+        let offset = Self::into_offset(handle);
+
+        if offset + old_layout.size() == self.watermark.get() {
+            let new_watermark = offset + new_layout.size();
+            self.watermark.set(new_watermark);
+
+            return Ok((handle, new_layout.size()));
+        }
+
         Ok((handle, old_layout.size()))
     }
 }
@@ -181,8 +244,11 @@ where
         <Self as Store>::allocate(self, layout)
     }
 
-    #[inline(always)]
-    unsafe fn deallocate(&mut self, _handle: Self::Handle, _layout: Layout) {}
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { <Self as Store>::deallocate(self, handle, layout) }
+    }
 
     unsafe fn grow(
         &mut self,
@@ -244,6 +310,30 @@ where
     }
 }
 
+impl<'a, H> StackBumpStore<'a, H> {
+    /// Returns a checkpoint of the current watermark of the referenced block, to rewind the block to later.
+    ///
+    /// See `StackBumpBlock::rewind`.
+    pub fn checkpoint(&self) -> BumpCheckpoint {
+        BumpCheckpoint::new(self.watermark.get())
+    }
+}
+
+impl<'a, H> StoreLayoutDump for StackBumpStore<'a, H> {
+    fn capacity(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn watermark(&self) -> Option<usize> {
+        Some(self.watermark.get())
+    }
+
+    fn visit_regions(&self, visitor: &mut dyn FnMut(Region)) {
+        //  The boundaries of the blocks of memory are not tracked, hence all memory below the watermark is used.
+        visit_watermarked(self.watermark.get(), self.capacity(), visitor);
+    }
+}
+
 impl<'a, H> fmt::Debug for StackBumpStore<'a, H> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         f.debug_struct("StackBumpStore")
@@ -292,11 +382,14 @@ where
         let watermark = self.watermark.get();
 
         let aligned = {
+            //  The block may be less aligned than `layout`, hence the address, rather than the offset, is aligned.
+            let base = self.memory.as_mut_ptr().addr();
+
             //  Since `layout.align()` is always a power of 2, aligning to the next multiple of `layout.align()` can be
             //  done with this one simple trick.
             let alignment_mask = layout.align() - 1;
 
-            (watermark + alignment_mask) & !alignment_mask
+            ((base + watermark + alignment_mask) & !alignment_mask) - base
         };
 
         let new_watermark = aligned + layout.size();
@@ -339,3 +432,92 @@ where
         Ok((result, new_layout.size()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::collection::StoreVec;
+
+    use super::*;
+
+    #[test]
+    fn rewind() {
+        let mut block = StackBumpBlock::<[u32; 8]>::new();
+
+        let checkpoint = block.checkpoint();
+
+        for _ in 0..3 {
+            let mut v = StoreVec::<u32, StackBumpStore<'_, usize>>::new_in(block.create_store());
+
+            for i in 0..8 {
+                v.push(i);
+            }
+
+            assert_eq!(8, v.len());
+
+            drop(v);
+
+            block.rewind(checkpoint);
+        }
+    }
+
+    #[test]
+    fn under_aligned_block() {
+        #[repr(align(16))]
+        struct Blocks([StackBumpBlock<[u8; 16]>; 2]);
+
+        //  The blocks are 24 bytes apart, hence at least one of them is not 16-bytes aligned.
+        let blocks = Blocks([StackBumpBlock::new(), StackBumpBlock::new()]);
+
+        let (small, large) = (Layout::new::<u8>(), Layout::from_size_align(8, 16).unwrap());
+
+        for block in &blocks.0 {
+            let store = block.create_store::<usize>();
+
+            let (handle, _) = Store::allocate(&store, small).unwrap();
+
+            //  Safety:
+            //  -   `handle` was allocated by `store`, with `small`, and is still valid.
+            //  -   `large` is larger than `small`.
+            let (handle, _) = unsafe { Store::grow(&store, handle, small, large).unwrap() };
+
+            //  Safety:
+            //  -   `handle` was grown by `store`, and is still valid.
+            let pointer = unsafe { Store::resolve(&store, handle) };
+
+            assert_eq!(0, pointer.addr().get() % large.align());
+        }
+    }
+
+    #[test]
+    fn dangling_larger_than_block() {
+        //  The block is aligned on 8 bytes, as per its watermark, yet only 4 bytes large.
+        let block = StackBumpBlock::<[u8; 4]>::new();
+        let store = block.create_store::<usize>();
+
+        store.dangling(Alignment::new(4).unwrap()).unwrap();
+        store.dangling(Alignment::new(8).unwrap()).unwrap_err();
+    }
+
+    #[test]
+    fn under_aligned_dangling() {
+        #[repr(align(16))]
+        struct Blocks([StackBumpBlock<[u8; 16]>; 2]);
+
+        //  The blocks are 24 bytes apart, hence at least one of them is not 16-bytes aligned.
+        let blocks = Blocks([StackBumpBlock::new(), StackBumpBlock::new()]);
+
+        let alignment = Alignment::new(16).unwrap();
+
+        for block in &blocks.0 {
+            let store = block.create_store::<usize>();
+
+            let handle = store.dangling(alignment).unwrap();
+
+            //  Safety:
+            //  -   `handle` is a dangling handle of `store`.
+            let pointer = unsafe { Store::resolve(&store, handle) };
+
+            assert_eq!(0, pointer.addr().get() % alignment.as_usize());
+        }
+    }
+} // mod tests
@@ -0,0 +1,422 @@
+//! A Store adapter, collecting statistics about the calls made to the underlying store.
+//!
+//! This store is meant to compare stores against one another, for example `InlineSingleStore` against `Global` for a
+//! `StoreVec`, and to size fixed-capacity stores, such as `StackBumpBlock`, from actual data.
+
+use core::{
+    alloc::{AllocError, Layout},
+    cell::Cell,
+    fmt,
+    ptr::{Alignment, NonNull},
+};
+
+use crate::interface::{Store, StoreDangling, StorePinning, StoreSingle, StoreStable};
+
+/// A snapshot of the statistics collected by a `StatsStore`.
+///
+/// The number of bytes are computed from the sizes returned by the store, rather than from the layouts passed to it,
+/// and thus account for the whole of the blocks of memory handed out. Each handle carries the size of its block of
+/// memory, so that the very same size is released on deallocation.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct StoreStats {
+    /// Number of successful allocations.
+    pub allocations: u64,
+    /// Number of deallocations.
+    pub deallocations: u64,
+    /// Number of successful grows, in place or not.
+    pub grows: u64,
+    /// Number of successful grows which did not move the block of memory.
+    pub in_place_grows: u64,
+    /// Number of successful grows which moved the block of memory.
+    pub relocating_grows: u64,
+    /// Number of successful shrinks.
+    pub shrinks: u64,
+    /// Number of failed calls to allocate, grow, or shrink.
+    pub failures: u64,
+    /// Number of bytes currently allocated.
+    pub live_bytes: usize,
+    /// Maximum number of bytes allocated at any one time.
+    pub peak_bytes: usize,
+}
+
+/// A handle of a `StatsStore`, wrapping the handle of the underlying store alongside the size of its block of memory.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StatsHandle<H> {
+    handle: H,
+    //  Size of the block of memory, as returned by the underlying store, or 0 if dangling.
+    size: usize,
+}
+
+/// An adapter of `Store` or `StoreSingle`, collecting statistics about the calls made to the underlying store.
+///
+/// Implements `Store` whenever `S` does, and `StoreSingle` whenever `S` does.
+pub struct StatsStore<S> {
+    stats: Cell<StoreStats>,
+    store: S,
+}
+
+impl<S> StatsStore<S> {
+    /// Creates a new instance, atop `store`.
+    pub const fn new_in(store: S) -> Self {
+        let stats = Cell::new(StoreStats {
+            allocations: 0,
+            deallocations: 0,
+            grows: 0,
+            in_place_grows: 0,
+            relocating_grows: 0,
+            shrinks: 0,
+            failures: 0,
+            live_bytes: 0,
+            peak_bytes: 0,
+        });
+
+        Self { stats, store }
+    }
+
+    /// Returns a snapshot of the statistics collected so far.
+    pub fn snapshot(&self) -> StoreStats {
+        self.stats.get()
+    }
+
+    /// Returns a reference to the underlying store.
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<S: Default> Default for StatsStore<S> {
+    fn default() -> Self {
+        Self::new_in(S::default())
+    }
+}
+
+unsafe impl<S: StoreDangling> StoreDangling for StatsStore<S> {
+    type Handle = StatsHandle<S::Handle>;
+
+    fn dangling(&self, alignment: Alignment) -> Result<Self::Handle, AllocError> {
+        let handle = self.store.dangling(alignment)?;
+
+        Ok(StatsHandle { handle, size: 0 })
+    }
+}
+
+unsafe impl<S: Store> Store for StatsStore<S> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let result = self.store.allocate(layout);
+
+        self.record_allocate(result.as_ref().ok().map(|(_, size)| *size));
+
+        result.map(|(handle, size)| (StatsHandle { handle, size }, size))
+    }
+
+    unsafe fn deallocate(&self, handle: Self::Handle, layout: Layout) {
+        self.record_deallocate(handle.size);
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle.handle, layout) }
+    }
+
+    unsafe fn grow(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let before = unsafe { self.store.resolve(handle.handle) };
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let result = unsafe { self.store.grow(handle.handle, old_layout, new_layout) };
+
+        //  Safety:
+        //  -   `handle` was returned by a successful `grow`, and is thus valid.
+        let after = result
+            .as_ref()
+            .ok()
+            .map(|(handle, _)| unsafe { self.store.resolve(*handle) });
+
+        let grown = result.as_ref().ok().map(|(_, size)| *size).zip(after);
+
+        self.record_grow(handle.size, grown.map(|(size, after)| (size, after == before)));
+
+        result.map(|(handle, size)| (StatsHandle { handle, size }, size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let result = unsafe { self.store.shrink(handle.handle, old_layout, new_layout) };
+
+        self.record_shrink(handle.size, result.as_ref().ok().map(|(_, size)| *size));
+
+        result.map(|(handle, size)| (StatsHandle { handle, size }, size))
+    }
+}
+
+unsafe impl<S: StoreSingle> StoreSingle for StatsStore<S> {
+    #[inline(always)]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve(handle.handle) }
+    }
+
+    #[inline(always)]
+    unsafe fn resolve_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.resolve_mut(handle.handle) }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<(Self::Handle, usize), AllocError> {
+        let result = self.store.allocate(layout);
+
+        self.record_allocate(result.as_ref().ok().map(|(_, size)| *size));
+
+        result.map(|(handle, size)| (StatsHandle { handle, size }, size))
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.record_deallocate(handle.size);
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        unsafe { self.store.deallocate(handle.handle, layout) }
+    }
+
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let before = unsafe { self.store.resolve(handle.handle) };
+
+        //  Safety:
+        //  -   As per pre-conditions.
+        let result = unsafe { self.store.grow(handle.handle, old_layout, new_layout) };
+
+        //  Safety:
+        //  -   `handle` was returned by a successful `grow`, and is thus valid.
+        let after = result
+            .as_ref()
+            .ok()
+            .map(|(handle, _)| unsafe { self.store.resolve(*handle) });
+
+        let grown = result.as_ref().ok().map(|(_, size)| *size).zip(after);
+
+        self.record_grow(handle.size, grown.map(|(size, after)| (size, after == before)));
+
+        result.map(|(handle, size)| (StatsHandle { handle, size }, size))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<(Self::Handle, usize), AllocError> {
+        //  Safety:
+        //  -   As per pre-conditions.
+        let result = unsafe { self.store.shrink(handle.handle, old_layout, new_layout) };
+
+        self.record_shrink(handle.size, result.as_ref().ok().map(|(_, size)| *size));
+
+        result.map(|(handle, size)| (StatsHandle { handle, size }, size))
+    }
+}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: StoreStable> StoreStable for StatsStore<S> {}
+
+//  Safety:
+//  -   `self.resolve(handle)` resolves to the same block of memory as the underlying store.
+unsafe impl<S: StorePinning> StorePinning for StatsStore<S> {}
+
+impl<S: fmt::Debug> fmt::Debug for StatsStore<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("StatsStore")
+            .field("stats", &self.stats.get())
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+//
+//  Implementation
+//
+
+impl<S> StatsStore<S> {
+    fn update(&self, fun: impl FnOnce(&mut StoreStats)) {
+        let mut stats = self.stats.get();
+
+        fun(&mut stats);
+
+        stats.peak_bytes = stats.peak_bytes.max(stats.live_bytes);
+
+        self.stats.set(stats);
+    }
+
+    //  `size` is `None` on failure.
+    fn record_allocate(&self, size: Option<usize>) {
+        self.update(|stats| match size {
+            Some(size) => {
+                stats.allocations += 1;
+                stats.live_bytes += size;
+            }
+            None => stats.failures += 1,
+        });
+    }
+
+    fn record_deallocate(&self, size: usize) {
+        self.update(|stats| {
+            stats.deallocations += 1;
+            stats.live_bytes -= size;
+        });
+    }
+
+    //  `grown` is the new size, and whether the block was grown in place, or `None` on failure.
+    fn record_grow(&self, old_size: usize, grown: Option<(usize, bool)>) {
+        self.update(|stats| match grown {
+            Some((size, in_place)) => {
+                stats.grows += 1;
+
+                if in_place {
+                    stats.in_place_grows += 1;
+                } else {
+                    stats.relocating_grows += 1;
+                }
+
+                stats.live_bytes = stats.live_bytes - old_size + size;
+            }
+            None => stats.failures += 1,
+        });
+    }
+
+    //  `size` is the new size, or `None` on failure.
+    fn record_shrink(&self, old_size: usize, size: Option<usize>) {
+        self.update(|stats| match size {
+            Some(size) => {
+                stats.shrinks += 1;
+                stats.live_bytes = stats.live_bytes - old_size + size;
+            }
+            None => stats.failures += 1,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use crate::{
+        collection::{LinkedList, StoreVec},
+        store::{InlineBumpStore, InlineSingleStore, InlineSlabStore},
+    };
+
+    use super::*;
+
+    #[test]
+    fn inline_single() {
+        let mut v = StoreVec::<u32, StatsStore<InlineSingleStore<[u32; 16]>>>::new();
+
+        for i in 0..16 {
+            v.push(i);
+        }
+
+        let stats = v.store().snapshot();
+
+        //  The whole inline block of memory is handed out on the first allocation, hence no growth is necessary.
+        assert_eq!(1, stats.allocations);
+        assert_eq!(0, stats.grows);
+        assert_eq!(64, stats.peak_bytes);
+    }
+
+    #[test]
+    fn bump() {
+        let mut v = StoreVec::<u8, StatsStore<InlineBumpStore<u8, [u8; 64]>>>::new();
+
+        for i in 0..64 {
+            v.push(i);
+        }
+
+        let stats = v.store().snapshot();
+
+        assert_eq!(1, stats.allocations);
+        assert_eq!(0, stats.relocating_grows);
+        assert_eq!(64, stats.peak_bytes);
+    }
+
+    #[test]
+    fn linked_list() {
+        let mut list = LinkedList::<u64, StatsStore<Global>>::new();
+
+        for i in 0..10 {
+            list.try_push_back(i).unwrap();
+        }
+
+        for _ in 0..4 {
+            list.pop_front();
+        }
+
+        let stats = list.store().snapshot();
+
+        assert_eq!(10, stats.allocations);
+        assert_eq!(4, stats.deallocations);
+        assert_eq!(stats.peak_bytes / 10 * 6, stats.live_bytes);
+    }
+
+    #[test]
+    fn rounding() {
+        let store = StatsStore::new_in(InlineSlabStore::<u16, [u64; 64]>::default());
+
+        let layout = |size| Layout::from_size_align(size, 1).unwrap();
+
+        let [a, b, c, d] = [3, 5, 17, 33].map(|size| Store::allocate(&store, layout(size)).unwrap().0);
+
+        //  The sizes are rounded up to their size classes.
+        assert_eq!(4 + 8 + 32 + 64, store.snapshot().live_bytes);
+
+        //  Safety:
+        //  -   `a` was allocated by `store`, with `layout(3)`, and is still valid.
+        let (a, _) = unsafe { Store::grow(&store, a, layout(3), layout(9)).unwrap() };
+
+        //  Safety:
+        //  -   `d` was allocated by `store`, with `layout(33)`, and is still valid.
+        let (d, _) = unsafe { Store::shrink(&store, d, layout(33), layout(7)).unwrap() };
+
+        assert_eq!(16 + 8 + 32 + 8, store.snapshot().live_bytes);
+
+        //  Safety:
+        //  -   All handles were allocated, grown, or shrunk, by `store`, with those layouts, and are still valid.
+        unsafe {
+            Store::deallocate(&store, a, layout(9));
+            Store::deallocate(&store, b, layout(5));
+            Store::deallocate(&store, c, layout(17));
+            Store::deallocate(&store, d, layout(7));
+        }
+
+        let stats = store.snapshot();
+
+        assert_eq!(0, stats.live_bytes);
+        assert_eq!(4 + 8 + 32 + 64 - 4 + 16, stats.peak_bytes);
+    }
+} // mod tests